@@ -0,0 +1,3 @@
+ALTER TABLE chat_message
+    DROP COLUMN deleted_at,
+    DROP COLUMN edited_at;
@@ -0,0 +1,5 @@
+-- Edited and deleted messages stay in the timeline: a deletion leaves a tombstone row so replies,
+-- read markers and cursors that point at it keep resolving.
+ALTER TABLE chat_message
+    ADD COLUMN edited_at  TIMESTAMP(6) WITH TIME ZONE,
+    ADD COLUMN deleted_at TIMESTAMP(6) WITH TIME ZONE;
@@ -0,0 +1,10 @@
+-- One row per (message, user, emoji): a user may react with several different emojis, but each
+-- only once. Reactions die with their message, including when a whole room is deleted.
+CREATE TABLE message_reaction
+(
+    message_id UUID                        NOT NULL REFERENCES chat_message (message_id) ON DELETE CASCADE,
+    user_id    UUID                        NOT NULL,
+    emoji      VARCHAR(64)                 NOT NULL,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (message_id, user_id, emoji)
+);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_chat_message_thread;
+
+ALTER TABLE chat_message
+    DROP COLUMN last_reply_at,
+    DROP COLUMN reply_count,
+    DROP COLUMN thread_root_id;
@@ -0,0 +1,11 @@
+-- Threads are one level deep: a reply points at its root, and a root never points anywhere.
+-- The counter and last-reply timestamp live on the root so the main timeline can render
+-- "3 replies" badges without aggregating over the thread on every page.
+ALTER TABLE chat_message
+    ADD COLUMN thread_root_id UUID REFERENCES chat_message (message_id) ON DELETE CASCADE,
+    ADD COLUMN reply_count    INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_reply_at  TIMESTAMP(6) WITH TIME ZONE;
+
+CREATE INDEX idx_chat_message_thread
+    ON chat_message (thread_root_id, created_at, message_id)
+    WHERE thread_root_id IS NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE app_user
+    DROP COLUMN last_seen_at;
@@ -0,0 +1,4 @@
+-- When a user was last connected to a live stream. Written when their last connection closes
+-- (and when one opens), not on every heartbeat; the live online/away state lives in Redis.
+ALTER TABLE app_user
+    ADD COLUMN last_seen_at TIMESTAMP(6) WITH TIME ZONE;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_participants_one_owner;
+
+ALTER TABLE chat_room_participant
+    DROP COLUMN role;
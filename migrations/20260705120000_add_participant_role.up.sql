@@ -0,0 +1,23 @@
+-- Who may manage a group. One owner per group, any number of admins, everyone else a member.
+-- 1-1 rooms have nobody to manage: both participants stay members.
+ALTER TABLE chat_room_participant
+    ADD COLUMN role varchar(16) NOT NULL DEFAULT 'MEMBER'
+        CONSTRAINT chat_room_participant_role_check
+            CHECK ((role)::text = ANY ((ARRAY ['OWNER'::character varying, 'ADMIN'::character varying, 'MEMBER'::character varying])::text[]));
+
+-- Existing groups get their longest-standing member as owner.
+UPDATE chat_room_participant AS participant
+SET role = 'OWNER'
+FROM (
+    SELECT DISTINCT ON (p.room_id) p.room_id, p.user_id
+    FROM chat_room_participant p
+    JOIN chat_room r ON r.id = p.room_id
+    WHERE r.room_type = 'Group'
+    ORDER BY p.room_id, p.joined_at, p.user_id
+) AS first_member
+WHERE participant.room_id = first_member.room_id
+  AND participant.user_id = first_member.user_id;
+
+CREATE UNIQUE INDEX idx_participants_one_owner
+    ON chat_room_participant (room_id)
+    WHERE role = 'OWNER';
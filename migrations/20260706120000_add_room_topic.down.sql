@@ -0,0 +1,2 @@
+ALTER TABLE chat_room
+    DROP COLUMN room_topic;
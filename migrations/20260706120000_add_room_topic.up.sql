@@ -0,0 +1,3 @@
+-- A group's description, shown with its details. NULL when none was ever set or it was cleared.
+ALTER TABLE chat_room
+    ADD COLUMN room_topic varchar(500);
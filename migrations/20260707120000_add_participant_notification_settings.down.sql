@@ -0,0 +1,3 @@
+ALTER TABLE chat_room_participant
+    DROP COLUMN muted_until,
+    DROP COLUMN notification_level;
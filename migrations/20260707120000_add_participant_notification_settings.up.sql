@@ -0,0 +1,7 @@
+-- How much of a room a participant wants pushed to them while offline. `muted_until` only applies
+-- to MUTED: NULL mutes until changed, a timestamp until then, after which the room behaves as ALL.
+ALTER TABLE chat_room_participant
+    ADD COLUMN notification_level varchar(16) NOT NULL DEFAULT 'ALL'
+        CONSTRAINT chat_room_participant_notification_level_check
+            CHECK ((notification_level)::text = ANY ((ARRAY ['ALL'::character varying, 'MENTIONS'::character varying, 'MUTED'::character varying])::text[])),
+    ADD COLUMN muted_until TIMESTAMP(6) WITH TIME ZONE;
@@ -0,0 +1,14 @@
+-- Shareable links into a group. The token is the whole secret; revoking a link deletes it, and
+-- links die with their room.
+CREATE TABLE room_invite_link
+(
+    token      VARCHAR(64)                 PRIMARY KEY,
+    room_id    UUID                        NOT NULL REFERENCES chat_room (id) ON DELETE CASCADE,
+    created_by UUID                        NOT NULL,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP(6) WITH TIME ZONE,
+    max_uses   INTEGER CHECK (max_uses > 0),
+    use_count  INTEGER                     NOT NULL DEFAULT 0
+);
+
+CREATE INDEX idx_room_invite_link_room ON room_invite_link (room_id);
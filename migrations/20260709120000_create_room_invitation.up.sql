@@ -0,0 +1,12 @@
+-- An invitation into a group the invitee has not answered yet. Accepting makes them a participant
+-- and deletes the row, declining only deletes it. Invitations die with their room.
+CREATE TABLE room_invitation
+(
+    room_id    UUID                        NOT NULL REFERENCES chat_room (id) ON DELETE CASCADE,
+    user_id    UUID                        NOT NULL,
+    invited_by UUID                        NOT NULL,
+    created_at TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (room_id, user_id)
+);
+
+CREATE INDEX idx_room_invitation_user ON room_invitation (user_id, created_at DESC);
@@ -0,0 +1,2 @@
+ALTER TABLE chat_room
+    DROP COLUMN retention_secs;
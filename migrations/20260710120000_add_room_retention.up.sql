@@ -0,0 +1,4 @@
+-- How long a room keeps its messages, in seconds. NULL inherits the server-wide default, 0 keeps
+-- them forever. The reaper deletes what has aged out; the timeline hides it until then.
+ALTER TABLE chat_room
+    ADD COLUMN retention_secs INTEGER CHECK (retention_secs >= 0);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_chat_message_search;
+
+ALTER TABLE chat_message
+    DROP COLUMN search_vector;
+
+DROP FUNCTION IF EXISTS chat_message_search_text(msg_type, JSONB);
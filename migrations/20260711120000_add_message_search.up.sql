@@ -0,0 +1,23 @@
+-- Full-text search over what people wrote: the text of a Text message, the new text of a Reply and
+-- the alt text of a Media message. Room changes and quoted content are not searchable.
+-- `simple` rather than a language configuration: rooms are multilingual, and stemming for the
+-- wrong language does more harm than none.
+CREATE FUNCTION chat_message_search_text(kind msg_type, body JSONB) RETURNS TEXT
+    LANGUAGE sql
+    IMMUTABLE
+    PARALLEL SAFE
+AS
+$$
+SELECT CASE kind
+           WHEN 'Text' THEN body ->> 'text'
+           WHEN 'Reply' THEN body ->> 'replyText'
+           WHEN 'Media' THEN body ->> 'altText'
+           END
+$$;
+
+ALTER TABLE chat_message
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', COALESCE(chat_message_search_text(msg_type, msg_body), ''))
+        ) STORED;
+
+CREATE INDEX idx_chat_message_search ON chat_message USING GIN (search_vector);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_chat_message_client_id;
+
+ALTER TABLE chat_message
+    DROP COLUMN client_message_id;
@@ -0,0 +1,9 @@
+-- The id a client gave a message before sending it, so a retried send finds the message its first
+-- attempt stored instead of storing it twice. Unique per sender: clients generate these on their
+-- own, and two users' keys colliding must not make one of them lose a message.
+ALTER TABLE chat_message
+    ADD COLUMN client_message_id VARCHAR(64);
+
+CREATE UNIQUE INDEX idx_chat_message_client_id
+    ON chat_message (sender_id, client_message_id)
+    WHERE client_message_id IS NOT NULL;
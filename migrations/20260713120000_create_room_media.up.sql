@@ -0,0 +1,15 @@
+-- Files uploaded into a room. The bytes live in object storage under `object_key`; this row is
+-- what makes them the room's: only its members may download them, and only the uploader may
+-- attach them to a message. Uploads die with their room.
+CREATE TABLE room_media
+(
+    media_id    UUID                        PRIMARY KEY,
+    room_id     UUID                        NOT NULL REFERENCES chat_room (id) ON DELETE CASCADE,
+    object_key  VARCHAR(128)                NOT NULL UNIQUE,
+    uploaded_by UUID                        NOT NULL,
+    mime_type   VARCHAR(255)                NOT NULL,
+    size_bytes  BIGINT                      NOT NULL CHECK (size_bytes > 0),
+    created_at  TIMESTAMP(6) WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_room_media_room ON room_media (room_id);
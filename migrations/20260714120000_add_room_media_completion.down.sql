@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_room_media_pending;
+
+DELETE FROM room_media WHERE completed_at IS NULL;
+
+ALTER TABLE room_media DROP COLUMN IF EXISTS completed_at;
@@ -0,0 +1,8 @@
+-- Uploads that go straight to object storage through a presigned URL are reserved first and only
+-- usable once the server has checked what arrived. `completed_at` is NULL until then; uploads
+-- that went through the API are complete the moment they are stored.
+ALTER TABLE room_media ADD COLUMN completed_at TIMESTAMP(6) WITH TIME ZONE;
+
+UPDATE room_media SET completed_at = created_at;
+
+CREATE INDEX idx_room_media_pending ON room_media (created_at) WHERE completed_at IS NULL;
@@ -0,0 +1,14 @@
+-- Who a message mentions, for the mentions feed. The mentions themselves live in `msg_body`; this
+-- is the index over them. `user_id` is NULL for an `@room` mention, which reaches whoever is in the
+-- room. Mentions die with their message.
+CREATE TABLE message_mention
+(
+    message_id   UUID                        NOT NULL REFERENCES chat_message (message_id) ON DELETE CASCADE,
+    chat_room_id UUID                        NOT NULL,
+    user_id      UUID,
+    created_at   TIMESTAMP(6) WITH TIME ZONE NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_message_mention_unique ON message_mention (message_id, user_id) NULLS NOT DISTINCT;
+CREATE INDEX idx_message_mention_user ON message_mention (user_id, created_at DESC) WHERE user_id IS NOT NULL;
+CREATE INDEX idx_message_mention_room ON message_mention (chat_room_id, created_at DESC) WHERE user_id IS NULL;
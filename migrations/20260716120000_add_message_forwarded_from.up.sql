@@ -0,0 +1,5 @@
+-- Where a forwarded message came from: the original's id, sender and send time, as JSON. A
+-- snapshot like a reply's quote, so it outlives the original being deleted or expiring; NULL for
+-- everything that was not forwarded.
+ALTER TABLE chat_message
+    ADD COLUMN forwarded_from JSONB;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_chat_message_room_timeline;
+
+CREATE INDEX idx_chat_message_room_timeline ON chat_message (chat_room_id, created_at DESC);
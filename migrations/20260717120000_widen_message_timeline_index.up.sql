@@ -0,0 +1,6 @@
+-- The timeline pages over `(created_at, message_id)` now, in both directions: the id breaks ties
+-- between messages stored with the same timestamp, which `created_at` alone let a page boundary
+-- skip. A b-tree is read backwards as readily as forwards, so one ascending index serves both.
+DROP INDEX IF EXISTS idx_chat_message_room_timeline;
+
+CREATE INDEX idx_chat_message_room_timeline ON chat_message (chat_room_id, created_at, message_id);
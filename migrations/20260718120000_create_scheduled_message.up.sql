@@ -0,0 +1,26 @@
+-- Messages a user composed to be sent later. `msg_body` is the body as the client sent it, not yet
+-- resolved into what `chat_message` stores: a reply quotes its message, and an upload is attached,
+-- only when the message is actually sent. A row is deleted once its message is sent; one that
+-- could not be sent stays behind with `failed_at` and `failure` so its sender can see why.
+-- Scheduled messages die with their room.
+CREATE TABLE scheduled_message
+(
+    scheduled_message_id UUID                        PRIMARY KEY,
+    chat_room_id         UUID                        NOT NULL REFERENCES chat_room (id) ON DELETE CASCADE,
+    sender_id            UUID                        NOT NULL,
+    msg_body             JSONB                       NOT NULL,
+    msg_type             msg_type                    NOT NULL,
+    thread_root_id       UUID,
+    send_at              TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    created_at           TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    failed_at            TIMESTAMP(6) WITH TIME ZONE,
+    failure              TEXT
+);
+
+-- What the dispatcher polls: the pending rows, soonest first.
+CREATE INDEX idx_scheduled_message_due
+    ON scheduled_message (send_at)
+    WHERE failed_at IS NULL;
+
+CREATE INDEX idx_scheduled_message_sender
+    ON scheduled_message (sender_id, send_at);
@@ -0,0 +1,4 @@
+-- Postgres cannot drop a value from an enum, so 'Poll' stays in the type. The polls themselves
+-- go: the code this rolls back to cannot decode their bodies.
+DELETE FROM scheduled_message WHERE msg_type = 'Poll';
+DELETE FROM chat_message WHERE msg_type = 'Poll';
@@ -0,0 +1,3 @@
+-- Polls are messages of their own type. Its own migration because Postgres does not let a
+-- transaction use an enum value it added, and the next migration's search function names it.
+ALTER TYPE msg_type ADD VALUE 'Poll';
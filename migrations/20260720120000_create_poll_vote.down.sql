@@ -0,0 +1,14 @@
+CREATE OR REPLACE FUNCTION chat_message_search_text(kind msg_type, body JSONB) RETURNS TEXT
+    LANGUAGE sql
+    IMMUTABLE
+    PARALLEL SAFE
+AS
+$$
+SELECT CASE kind
+           WHEN 'Text' THEN body ->> 'text'
+           WHEN 'Reply' THEN body ->> 'replyText'
+           WHEN 'Media' THEN body ->> 'altText'
+           END
+$$;
+
+DROP TABLE IF EXISTS poll_vote;
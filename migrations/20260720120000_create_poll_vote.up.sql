@@ -0,0 +1,27 @@
+-- One row per (poll, user, option): a user picks one option of a single-choice poll, one or more of
+-- a multiple-choice one. `option_index` points into the poll message's `options`, which never
+-- change once sent. Votes die with their poll.
+CREATE TABLE poll_vote
+(
+    message_id   UUID                        NOT NULL REFERENCES chat_message (message_id) ON DELETE CASCADE,
+    user_id      UUID                        NOT NULL,
+    option_index SMALLINT                    NOT NULL,
+    created_at   TIMESTAMP(6) WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (message_id, user_id, option_index)
+);
+
+-- A poll is found by its question and its options.
+CREATE OR REPLACE FUNCTION chat_message_search_text(kind msg_type, body JSONB) RETURNS TEXT
+    LANGUAGE sql
+    IMMUTABLE
+    PARALLEL SAFE
+AS
+$$
+SELECT CASE kind
+           WHEN 'Text' THEN body ->> 'text'
+           WHEN 'Reply' THEN body ->> 'replyText'
+           WHEN 'Media' THEN body ->> 'altText'
+           WHEN 'Poll' THEN concat_ws(' ', body ->> 'question',
+                                      array_to_string(ARRAY(SELECT jsonb_array_elements_text(body -> 'options')), ' '))
+           END
+$$;
@@ -0,0 +1 @@
+ALTER TABLE room_media DROP COLUMN IF EXISTS message_id;
@@ -0,0 +1,11 @@
+-- The message an upload is attached to. An upload is attached at most once, so deleting that
+-- message can delete the upload with it; `NULL` until a message attaches it.
+ALTER TABLE room_media ADD COLUMN message_id UUID UNIQUE REFERENCES chat_message (message_id) ON DELETE CASCADE;
+
+UPDATE room_media
+SET message_id = (SELECT message.message_id
+                  FROM chat_message message
+                  WHERE message.chat_room_id = room_media.room_id
+                    AND message.msg_body ->> 'mediaKey' = room_media.object_key
+                  ORDER BY message.created_at
+                  LIMIT 1);
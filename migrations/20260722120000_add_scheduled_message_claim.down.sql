@@ -0,0 +1 @@
+ALTER TABLE scheduled_message DROP COLUMN IF EXISTS claimed_until;
@@ -0,0 +1,4 @@
+-- Dispatchers claim due messages with a lease instead of holding row locks while they send: a
+-- claimed row is left alone by other dispatchers until `claimed_until`, and one whose dispatcher
+-- died is picked up again once the lease has run out.
+ALTER TABLE scheduled_message ADD COLUMN claimed_until TIMESTAMP(6) WITH TIME ZONE;
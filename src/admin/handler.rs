@@ -0,0 +1,60 @@
+use crate::admin::AdminService;
+use crate::admin::request::AdminRoomListQuery;
+use crate::admin::response::RoomOverviewResponse;
+use crate::auth::{AppRole, CurrentUser};
+use crate::core::ValidatedQuery;
+use crate::core::cursor::{CursorResults, decode_cursor};
+use crate::core::errors::{AppError, AppResponse};
+use crate::expect_role;
+use crate::rooms::model::RoomCreatedCursor;
+use crate::rooms::response::RoomDetailResponse;
+use axum::Json;
+use axum::extract::{Path, State};
+use uuid::Uuid;
+
+pub async fn handle_get_rooms(
+    user: CurrentUser,
+    State(admin): State<AdminService>,
+    ValidatedQuery(params): ValidatedQuery<AdminRoomListQuery>,
+) -> AppResponse<Json<CursorResults<RoomOverviewResponse>>> {
+    expect_role!(&user, AppRole::Admin);
+    let cursor: RoomCreatedCursor = decode_cursor(params.cursor).map_err(|_| AppError::Validation("Invalid Cursor-Parameters.".to_string()))?;
+    let rooms = admin.get_rooms(params.name, cursor, params.limit.get()).await?;
+    Ok(Json(rooms))
+}
+
+pub async fn handle_get_room(user: CurrentUser, State(admin): State<AdminService>, Path(room_id): Path<Uuid>) -> AppResponse<Json<RoomDetailResponse>> {
+    expect_role!(&user, AppRole::Admin);
+    let room = admin.get_room(room_id).await?;
+    Ok(Json(room))
+}
+
+pub async fn handle_delete_room(user: CurrentUser, State(admin): State<AdminService>, Path(room_id): Path<Uuid>) -> AppResponse<()> {
+    expect_role!(&user, AppRole::Admin);
+    admin.delete_room(user.subject, room_id).await?;
+    Ok(())
+}
+
+pub async fn handle_remove_member(user: CurrentUser, State(admin): State<AdminService>, Path((room_id, user_id)): Path<(Uuid, Uuid)>) -> AppResponse<()> {
+    expect_role!(&user, AppRole::Admin);
+    admin.remove_member(user.subject, room_id, user_id).await?;
+    Ok(())
+}
+
+pub async fn handle_delete_message(user: CurrentUser, State(admin): State<AdminService>, Path((room_id, message_id)): Path<(Uuid, Uuid)>) -> AppResponse<()> {
+    expect_role!(&user, AppRole::Admin);
+    admin.delete_message(user.subject, room_id, message_id).await?;
+    Ok(())
+}
+
+pub async fn handle_ban_user(user: CurrentUser, State(admin): State<AdminService>, Path(user_id): Path<Uuid>) -> AppResponse<()> {
+    expect_role!(&user, AppRole::Admin);
+    admin.set_banned(user.subject, user_id, true).await?;
+    Ok(())
+}
+
+pub async fn handle_unban_user(user: CurrentUser, State(admin): State<AdminService>, Path(user_id): Path<Uuid>) -> AppResponse<()> {
+    expect_role!(&user, AppRole::Admin);
+    admin.set_banned(user.subject, user_id, false).await?;
+    Ok(())
+}
@@ -0,0 +1,7 @@
+mod handler;
+pub mod request;
+pub mod response;
+pub mod routes;
+pub mod service;
+
+pub use service::AdminService;
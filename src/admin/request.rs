@@ -0,0 +1,20 @@
+//! Client-supplied inputs for the admin API.
+
+use crate::core::ApiRequest;
+use crate::core::cursor::PageSize;
+use serde::Deserialize;
+use validator::Validate;
+
+/// Query params for `GET /api/v1/admin/rooms`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdminRoomListQuery {
+    /// Optional case-insensitive filter on the group name or any member's display name. Bounded
+    /// because it reaches an `ILIKE '%…%'` pattern.
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters long."))]
+    pub name: Option<String>,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: PageSize,
+}
+
+impl ApiRequest for AdminRoomListQuery {}
@@ -0,0 +1,30 @@
+//! Client-facing shapes for the admin API.
+
+use crate::core::ApiResponse;
+use crate::rooms::entity::RoomOverviewRow;
+use crate::rooms::response::RoomResponse;
+use serde::Serialize;
+
+/// A room in the moderation list. The room is flattened, so the payload is a [`RoomResponse`] with
+/// one extra `memberCount` key.
+///
+/// Unlike a member's view, the room is not resolved against a caller: a `Single` room has no
+/// `roomName` or `roomImageUrl` of its own, and `unread` is always `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomOverviewResponse {
+    #[serde(flatten)]
+    pub room: RoomResponse,
+    pub member_count: i64,
+}
+
+impl ApiResponse for RoomOverviewResponse {}
+
+impl From<RoomOverviewRow> for RoomOverviewResponse {
+    fn from(row: RoomOverviewRow) -> Self {
+        RoomOverviewResponse {
+            room: RoomResponse::from(row.room),
+            member_count: row.member_count,
+        }
+    }
+}
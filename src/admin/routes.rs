@@ -0,0 +1,18 @@
+use crate::admin::handler::{
+    handle_ban_user, handle_delete_message, handle_delete_room, handle_get_room, handle_get_rooms, handle_remove_member, handle_unban_user,
+};
+use crate::core::AppState;
+use axum::Router;
+use axum::routing::{delete, get, post};
+use std::sync::Arc;
+
+/// Moderation endpoints. Every handler behind these asserts `AppRole::Admin` itself; the router
+/// applies no role check of its own.
+pub fn create_admin_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/admin/rooms", get(handle_get_rooms))
+        .route("/admin/rooms/{room_id}", get(handle_get_room).delete(handle_delete_room))
+        .route("/admin/rooms/{room_id}/members/{user_id}", delete(handle_remove_member))
+        .route("/admin/rooms/{room_id}/messages/{message_id}", delete(handle_delete_message))
+        .route("/admin/users/{user_id}/ban", post(handle_ban_user).delete(handle_unban_user))
+}
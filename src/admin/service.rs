@@ -0,0 +1,138 @@
+use crate::admin::response::RoomOverviewResponse;
+use crate::broadcast::BroadcastChannel;
+use crate::broadcast::NotificationEvent::UserBanned;
+use crate::core::Service;
+use crate::core::cursor::{CursorResults, next_cursor};
+use crate::core::errors::AppError;
+use crate::messaging::MessageService;
+use crate::rooms::model::RoomCreatedCursor;
+use crate::rooms::response::{RoomDetailResponse, RoomMemberResponse, RoomResponse};
+use crate::rooms::{RoomRepository, RoomService};
+use crate::users::UserRepository;
+use chrono::Utc;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+/// Moderation: looking into any room and undoing what users did there.
+///
+/// None of this checks permissions. The admin role is asserted by every handler in front of it,
+/// and membership — the rule every other service enforces — is exactly what a moderator has to be
+/// exempt from.
+///
+/// Depends on [`RoomService`] and [`MessageService`] for the same reason as
+/// [`UserService`](crate::users::UserService): removing a member and deleting a message are use
+/// cases with their own transactions and broadcasts, and a moderator triggering them must not get a
+/// second copy that drifts. Nothing depends on this service, so it closes the graph.
+/// See [`crate::core::Service`].
+#[derive(Clone)]
+pub struct AdminService {
+    rooms: RoomRepository,
+    users: UserRepository,
+    room_service: RoomService,
+    message_service: MessageService,
+    bus: Arc<BroadcastChannel>,
+}
+
+impl Service for AdminService {
+    const NAME: &'static str = "AdminService";
+}
+
+impl AdminService {
+    pub fn new(rooms: RoomRepository, users: UserRepository, room_service: RoomService, message_service: MessageService, bus: Arc<BroadcastChannel>) -> Self {
+        Self {
+            rooms,
+            users,
+            room_service,
+            message_service,
+            bus,
+        }
+    }
+
+    pub async fn get_rooms(
+        &self,
+        name_filter: Option<String>,
+        cursor: RoomCreatedCursor,
+        page_size: usize,
+    ) -> Result<CursorResults<RoomOverviewResponse>, AppError> {
+        let mut rooms = self.rooms.select_all_rooms(name_filter.as_deref(), cursor, (page_size + 1) as i64).await?;
+
+        let next_cursor = next_cursor(&mut rooms, page_size, |row| RoomCreatedCursor {
+            last_created_at: Some(row.room.created_at),
+            last_room_id: Some(row.room.id),
+        })
+        .map_err(|e| AppError::Processing(format!("Cursor encoding failed: {}", e)))?;
+
+        Ok(CursorResults {
+            cursor: next_cursor,
+            content: rooms.into_iter().map(RoomOverviewResponse::from).collect(),
+        })
+    }
+
+    pub async fn get_room(&self, room_id: Uuid) -> Result<RoomDetailResponse, AppError> {
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&room_id),
+            self.rooms.select_all_room_member(&room_id)
+        )?;
+        Ok(RoomDetailResponse {
+            room: RoomResponse::from(room),
+            users: users.into_iter().map(RoomMemberResponse::from).collect(),
+        })
+    }
+
+    /// Deletes a room with all of its messages; see [`RoomService::delete_room`].
+    pub async fn delete_room(&self, admin_id: Uuid, room_id: Uuid) -> Result<(), AppError> {
+        self.room_service.delete_room(room_id).await?;
+        info!(%admin_id, %room_id, "Admin deleted room");
+        Ok(())
+    }
+
+    /// Removes a member as if they had left themselves: the room sees `UserLeft`, the member gets
+    /// `LeaveRoom`, and a 1-1 room is deleted outright.
+    pub async fn remove_member(&self, admin_id: Uuid, room_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        if !self.rooms.is_user_in_room(&user_id, &room_id).await? {
+            return Err(AppError::NotFound("User is not in this room.".to_string()));
+        }
+        self.room_service.leave_room(user_id, room_id).await?;
+        info!(%admin_id, %room_id, %user_id, "Admin removed room member");
+        Ok(())
+    }
+
+    /// Deletes any message, leaving the same tombstone as when its author deletes it.
+    pub async fn delete_message(&self, admin_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<(), AppError> {
+        self.message_service.moderate_delete_message(room_id, message_id).await?;
+        info!(%admin_id, %room_id, %message_id, "Admin deleted message");
+        Ok(())
+    }
+
+    /// Bans or unbans a user by soft-deleting them, which hides them everywhere users are offered.
+    /// Repeating either is a no-op and announces nothing.
+    ///
+    /// This does not sign the user out: ISM trusts any valid token, and keeping a banned user from
+    /// getting one is Keycloak's job.
+    pub async fn set_banned(&self, admin_id: Uuid, user_id: Uuid, banned: bool) -> Result<(), AppError> {
+        if admin_id == user_id {
+            return Err(AppError::Validation("Admins cannot ban themselves.".to_string()));
+        }
+        if self.users.find_user_by_id(&user_id).await?.is_none() {
+            return Err(AppError::NotFound("UserID not found.".to_string()));
+        }
+
+        let now = Utc::now();
+        let changed = if banned {
+            self.users.soft_delete_user(&user_id, now).await?
+        } else {
+            self.users.restore_user(&user_id, now).await?
+        };
+        if !changed {
+            return Ok(());
+        }
+        info!(%admin_id, %user_id, banned, "Admin changed ban");
+
+        let mut audience = self.users.select_presence_audience(&user_id).await?;
+        audience.push(user_id);
+        self.bus.notify_all(audience, UserBanned { user_id, banned }).await;
+        Ok(())
+    }
+}
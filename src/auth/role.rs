@@ -1,8 +1,9 @@
 //! Realm and client roles, and the macros that assert them inside a handler.
 //!
-//! ISM does not check roles yet — every handler is reachable by any authenticated user — but the
-//! `Role` trait is what the `<R>` generic on `KeycloakToken` and `KeycloakAuthLayer` binds, and
-//! `String` is the default role type. See `docs/auth.md` for using a custom enum instead.
+//! ISM checks a role in one place only — the `/admin` routes assert `AppRole::Admin` — and every
+//! other handler is reachable by any authenticated user. The `Role` trait is what the `<R>` generic
+//! on `KeycloakToken` and `KeycloakAuthLayer` binds, and `String` is the default role type. See
+//! `docs/auth.md` for using a custom enum instead.
 
 use std::fmt::{Debug, Display};
 
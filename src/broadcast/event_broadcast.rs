@@ -1,8 +1,11 @@
+use crate::broadcast::relay::{NodeRelay, RelayBatch, RelayedNotification};
 use crate::broadcast::{Notification, NotificationEvent};
 use crate::cache::redis_cache::{Cache, ReplayResult};
 use crate::kafka::{EventProducer, PushNotificationProducer};
+use crate::rooms::response::UnreadCountResponse;
+use crate::users::model::PresenceStatus;
 use futures::StreamExt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -30,8 +33,9 @@ const PUSH_BATCH_SIZE: usize = 500;
 /// production log level without anyone turning on `debug`.
 const SLOW_FANOUT: Duration = Duration::from_millis(250);
 
-/// Outcome of one recipient's delivery. `Offline` is not a failure: it is what the fan-out collects
-/// into the single batched push notification.
+/// Outcome of one recipient's delivery on this instance. `Offline` is not a failure: it is what the
+/// fan-out narrows down to the recipients offline everywhere and collects into the single batched
+/// push notification.
 enum Delivery {
     Live,
     Offline,
@@ -51,6 +55,10 @@ enum Delivery {
 /// The `BroadcastChannel` is designed to support multi-threaded operations where multiple threads
 /// may add, retrieve, or remove channels or broadcast messages safely.
 ///
+/// # Several instances
+/// The map only holds this process's receivers. Every fan-out is also handed to the
+/// [`NodeRelay`], which publishes it to the other instances; they deliver it to their own
+/// receivers through [`Self::deliver_relayed`]. Only the producing instance sequences and pushes.
 ///
 /// # Thread Safety
 /// The usage of `RwLock` ensures that the operations on the `HashMap` are synchronized
@@ -60,6 +68,9 @@ pub struct BroadcastChannel {
     channel: UserConnectionMap,
     cache: Arc<dyn Cache>,
     push_notification_producer: PushNotificationProducer,
+    relay: NodeRelay,
+    /// Identifies this instance's publications, so it can ignore its own echo.
+    node_id: Uuid,
 }
 
 type UserConnectionMap = RwLock<HashMap<Uuid, Sender<Notification>>>;
@@ -76,9 +87,18 @@ impl BroadcastChannel {
             channel: RwLock::new(HashMap::new()),
             push_notification_producer: producer,
             cache,
+            relay: NodeRelay::Local,
+            node_id: Uuid::new_v4(),
         }
     }
 
+    /// Relays every fan-out to the other instances. Without it the bus only reaches receivers
+    /// attached to this process, which is only correct for a single instance.
+    pub fn with_relay(mut self, relay: NodeRelay) -> Self {
+        self.relay = relay;
+        self
+    }
+
     pub async fn subscribe_to_user_events(&self, user_id: Uuid) -> Receiver<Notification> {
         let mut lock = self.channel.write().await;
         let sender = lock.entry(user_id).or_insert_with(|| channel::<Notification>(100).0);
@@ -106,6 +126,23 @@ impl BroadcastChannel {
         self.send_event_to_all(user_ids, Notification::new(event)).await;
     }
 
+    /// Like [`Self::notify_all`], but each recipient found in `unread` gets the event with their own
+    /// counters attached — see [`NotificationEvent::with_unread`].
+    ///
+    /// The push notification for offline recipients still goes out as one shared record, built from
+    /// the event *without* counters: a batch has no single correct value, for the same reason it
+    /// has no `seq`. Recipients in `quiet` — those who silenced the room — get the event live and
+    /// in replay like everyone else, but are left out of that record.
+    pub async fn notify_all_with_unread(
+        &self,
+        user_ids: Vec<Uuid>,
+        event: NotificationEvent,
+        unread: HashMap<Uuid, UnreadCountResponse>,
+        quiet: &HashSet<Uuid>,
+    ) {
+        self.fan_out(user_ids, Notification::new(event), &unread, quiet).await;
+    }
+
     /// Sends an already-built envelope to many users. Prefer [`Self::notify_all`], which builds the
     /// envelope for you.
     ///
@@ -116,33 +153,54 @@ impl BroadcastChannel {
     ///
     /// Offline recipients are collected and pushed in **one** Kafka record rather than one each.
     pub async fn send_event_to_all(&self, user_ids: Vec<Uuid>, notification: Notification) {
+        self.fan_out(user_ids, notification, &HashMap::new(), &HashSet::new()).await;
+    }
+
+    async fn fan_out(&self, user_ids: Vec<Uuid>, notification: Notification, unread: &HashMap<Uuid, UnreadCountResponse>, quiet: &HashSet<Uuid>) {
         let ephemeral = notification.body.is_ephemeral();
         let recipients = user_ids.len();
         let started = Instant::now();
 
         // A sequence number is per-user, so every recipient gets its own clone with its own seq
         // rather than a single shared notification.
-        let offline: Vec<Uuid> = futures::stream::iter(user_ids)
+        let delivered: Vec<(Uuid, Notification, Delivery)> = futures::stream::iter(user_ids)
             .map(|user_id| {
-                let notification = notification.clone();
+                let mut notification = notification.clone();
+                if let Some(counts) = unread.get(&user_id) {
+                    notification.body = notification.body.with_unread(*counts);
+                }
                 async move {
-                    match self.deliver_to_user(&user_id, notification).await {
-                        Delivery::Live => None,
-                        Delivery::Offline => Some(user_id),
-                    }
+                    let (notification, delivery) = self.deliver_to_user(&user_id, notification).await;
+                    (user_id, notification, delivery)
                 }
             })
             .buffer_unordered(FANOUT_CONCURRENCY)
-            .filter_map(std::future::ready)
             .collect()
             .await;
 
-        // Measured before the push, so the number reflects the fan-out itself.
+        // Measured before the relay and the push, so the number reflects the fan-out itself.
         let elapsed = started.elapsed();
-        let offline_count = offline.len();
 
+        // Every recipient is relayed, including those delivered here: a user may well have a
+        // second device attached to another instance.
+        let mut offline = Vec::new();
+        let mut relayed = Vec::with_capacity(delivered.len());
+        for (user_id, notification, delivery) in delivered {
+            if let Delivery::Offline = delivery
+                && !quiet.contains(&user_id)
+            {
+                offline.push(user_id);
+            }
+            relayed.push(RelayedNotification { user_id, notification });
+        }
+        self.relay.publish(self.node_id, relayed).await;
+
+        let offline_count = offline.len();
         if !ephemeral && !offline.is_empty() {
-            self.send_undeliverable_notifications(notification, offline).await;
+            let offline = self.offline_everywhere(offline).await;
+            if !offline.is_empty() {
+                self.send_undeliverable_notifications(notification, offline).await;
+            }
         }
 
         let duration_ms = elapsed.as_millis() as u64;
@@ -153,13 +211,46 @@ impl BroadcastChannel {
         }
     }
 
+    /// Narrows the recipients without a receiver here down to those without one anywhere, using
+    /// the shared presence. A user connected to another instance got the event through the relay
+    /// and must not also get a push.
+    ///
+    /// Without shared presence (no Redis) this instance is the only one, so its view is complete.
+    /// If presence cannot be read, everyone is pushed: a duplicate push beats a lost one.
+    async fn offline_everywhere(&self, offline_here: Vec<Uuid>) -> Vec<Uuid> {
+        match self.cache.get_presence(&offline_here).await {
+            Ok(Some(statuses)) => offline_here
+                .into_iter()
+                .zip(statuses)
+                .filter(|(_, status)| *status == PresenceStatus::Offline)
+                .map(|(user_id, _)| user_id)
+                .collect(),
+            Ok(None) => offline_here,
+            Err(error) => {
+                warn!(error = %error, "Failed to read presence, pushing to every locally offline recipient");
+                offline_here
+            }
+        }
+    }
+
+    /// Delivers a fan-out published by another instance to the receivers attached here. Nothing is
+    /// sequenced, cached or pushed: the publishing instance already did all three.
+    pub(crate) async fn deliver_relayed(&self, batch: RelayBatch) {
+        if batch.origin == self.node_id {
+            return;
+        }
+        for RelayedNotification { user_id, notification } in batch.deliveries {
+            self.deliver_live(&user_id, notification).await;
+        }
+    }
+
     /// Deliver a single notification to a single user.
     ///
     /// Durable events are sequenced and cached for replay in one atomic Redis call before
-    /// delivery; ephemeral events (typing, resync signals) are sent live-only. Reports whether a
-    /// live connection took it — the push fallback belongs to the caller, which batches every
-    /// offline recipient of a fan-out into one record.
-    async fn deliver_to_user(&self, user_id: &Uuid, mut notification: Notification) -> Delivery {
+    /// delivery; ephemeral events (typing, resync signals) are sent live-only. Returns the envelope
+    /// as delivered, for the relay, and whether a live connection here took it — the push fallback
+    /// belongs to the caller, which batches every offline recipient of a fan-out into one record.
+    async fn deliver_to_user(&self, user_id: &Uuid, mut notification: Notification) -> (Notification, Delivery) {
         if !notification.body.is_ephemeral() {
             match self.cache.append_notification(user_id, &notification).await {
                 // Sequencing available (Redis): the event is now durable under this seq.
@@ -173,6 +264,12 @@ impl BroadcastChannel {
             }
         }
 
+        let delivery = self.deliver_live(user_id, notification.clone()).await;
+        (notification, delivery)
+    }
+
+    /// Hands an envelope to the user's receivers on this instance, if there are any.
+    async fn deliver_live(&self, user_id: &Uuid, notification: Notification) -> Delivery {
         let lock = self.channel.read().await;
         match lock.get(user_id).map(|sender| sender.send(notification)) {
             Some(Ok(receivers)) => {
@@ -192,7 +289,10 @@ impl BroadcastChannel {
         let should_send = matches!(
             //Only sends push notifications for these notification types, add more if needed
             notification.body,
-            NotificationEvent::ChatMessage { .. } | NotificationEvent::FriendRequestReceived { .. } | NotificationEvent::NewRoom { .. }
+            NotificationEvent::ChatMessage { .. }
+                | NotificationEvent::FriendRequestReceived { .. }
+                | NotificationEvent::NewRoom { .. }
+                | NotificationEvent::RoomInvitationReceived { .. }
         );
 
         if !should_send {
@@ -259,6 +359,8 @@ mod tests {
         Notification::new(UserReadChat {
             user_id,
             room_id: Uuid::new_v4(),
+            read_at: None,
+            unread: None,
         })
     }
 
@@ -326,6 +428,8 @@ mod tests {
             UserReadChat {
                 user_id: Uuid::new_v4(),
                 room_id: Uuid::new_v4(),
+                read_at: None,
+                unread: None,
             },
         )
         .await;
@@ -379,6 +483,79 @@ mod tests {
         assert_eq!(notification.seq, None);
     }
 
+    /// Unread counts are per reader, so only the recipient they were computed for may see them.
+    #[tokio::test]
+    async fn unread_counts_are_personalised_per_recipient() {
+        let bc = BroadcastChannel::new(Arc::new(InMemoryCache::new()), logging_producer());
+
+        let reader = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let mut reader_rx = bc.subscribe_to_user_events(reader).await;
+        let mut other_rx = bc.subscribe_to_user_events(other).await;
+
+        let counts = UnreadCountResponse { room: 2, total: Some(9) };
+        bc.notify_all_with_unread(
+            vec![reader, other],
+            UserReadChat {
+                user_id: reader,
+                room_id: Uuid::new_v4(),
+                read_at: None,
+                unread: None,
+            },
+            HashMap::from([(reader, counts)]),
+            &HashSet::new(),
+        )
+        .await;
+
+        match reader_rx.recv().await.expect("reader received nothing").body {
+            UserReadChat { unread, .. } => assert_eq!(unread, Some(counts)),
+            other => panic!("unexpected event {other:?}"),
+        }
+        match other_rx.recv().await.expect("other received nothing").body {
+            UserReadChat { unread, .. } => assert_eq!(unread, None),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+
+    /// Silencing a room only silences the push: a quiet recipient still gets the event live, and
+    /// offline ones are dropped from the push record rather than sent an empty one.
+    #[tokio::test]
+    async fn quiet_recipients_are_left_out_of_the_push() {
+        let recorder = Arc::new(RecordingEventProducer::new());
+        let bc = BroadcastChannel::new(Arc::new(InMemoryCache::new()), PushNotificationProducer::Recording(recorder.clone()));
+
+        let quiet_online = Uuid::new_v4();
+        let quiet_offline = Uuid::new_v4();
+        let loud_offline = Uuid::new_v4();
+        let mut quiet_rx = bc.subscribe_to_user_events(quiet_online).await;
+
+        bc.notify_all_with_unread(
+            vec![quiet_online, quiet_offline, loud_offline],
+            NotificationEvent::FriendRequestReceived {
+                from_user: profile(Uuid::new_v4()),
+            },
+            HashMap::new(),
+            &HashSet::from([quiet_online, quiet_offline]),
+        )
+        .await;
+
+        assert!(quiet_rx.recv().await.is_ok(), "a quiet recipient still gets the live event");
+        let sent = recorder.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, vec![loud_offline]);
+
+        bc.notify_all_with_unread(
+            vec![quiet_offline],
+            NotificationEvent::FriendRequestReceived {
+                from_user: profile(Uuid::new_v4()),
+            },
+            HashMap::new(),
+            &HashSet::from([quiet_offline]),
+        )
+        .await;
+        assert_eq!(recorder.sent().len(), 1, "nobody left to push to, so nothing is sent");
+    }
+
     /// Ephemeral events are live-only in both directions: no sequence, no cache entry, and no push
     /// for the recipients that were offline.
     #[tokio::test]
@@ -393,4 +570,73 @@ mod tests {
         assert!(recorder.sent().is_empty());
         assert_eq!(cache.cached_count(), 0);
     }
+
+    /// A user connected to another instance got the event through the relay. Pushing it to their
+    /// phone as well would be a duplicate, so only users offline *everywhere* are pushed.
+    #[tokio::test]
+    async fn users_connected_elsewhere_are_not_pushed() {
+        let recorder = Arc::new(RecordingEventProducer::new());
+        let cache = Arc::new(InMemoryCache::new());
+        let bc = BroadcastChannel::new(cache.clone(), PushNotificationProducer::Recording(recorder.clone()));
+
+        let elsewhere = Uuid::new_v4();
+        let offline = Uuid::new_v4();
+        cache.put_presence(&elsewhere, &Uuid::new_v4()).await.expect("presence");
+
+        bc.notify_all(
+            vec![elsewhere, offline],
+            NotificationEvent::FriendRequestReceived {
+                from_user: profile(Uuid::new_v4()),
+            },
+        )
+        .await;
+
+        let sent = recorder.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, vec![offline]);
+    }
+
+    /// A relayed envelope arrives exactly as the publishing instance sequenced it: the receiving
+    /// instance neither re-sequences nor caches it.
+    #[tokio::test]
+    async fn relayed_notifications_reach_local_receivers_unchanged() {
+        let cache = Arc::new(InMemoryCache::new());
+        let bc = BroadcastChannel::new(cache.clone(), logging_producer());
+
+        let user_id = Uuid::new_v4();
+        let mut rx = bc.subscribe_to_user_events(user_id).await;
+        let notification = Notification {
+            seq: Some(7),
+            ..read_receipt(user_id)
+        };
+
+        bc.deliver_relayed(RelayBatch {
+            origin: Uuid::new_v4(),
+            deliveries: vec![RelayedNotification { user_id, notification }],
+        })
+        .await;
+
+        assert_eq!(rx.recv().await.expect("relayed event").seq, Some(7));
+        assert_eq!(cache.cached_count(), 0);
+    }
+
+    /// Pub/sub echoes a publication back to its publisher, whose receivers already have the event.
+    #[tokio::test]
+    async fn an_instance_ignores_its_own_relay_echo() {
+        let bc = BroadcastChannel::new(Arc::new(NoOpCache), logging_producer());
+
+        let user_id = Uuid::new_v4();
+        let mut rx = bc.subscribe_to_user_events(user_id).await;
+
+        bc.deliver_relayed(RelayBatch {
+            origin: bc.node_id,
+            deliveries: vec![RelayedNotification {
+                user_id,
+                notification: read_receipt(user_id),
+            }],
+        })
+        .await;
+
+        assert!(rx.try_recv().is_err(), "the echo was delivered a second time");
+    }
 }
@@ -8,6 +8,8 @@
 mod event_broadcast;
 mod macros;
 mod notification;
+mod relay;
 
 pub use event_broadcast::BroadcastChannel;
 pub use notification::{Notification, NotificationEvent};
+pub use relay::{NodeRelay, RedisRelay, RedisRelayListener};
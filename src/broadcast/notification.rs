@@ -1,6 +1,7 @@
-use crate::messaging::response::MessageResponse;
+use crate::messaging::response::{MessageResponse, PollResultsResponse, ThreadSummaryResponse};
 use crate::rooms::response::RoomMemberResponse;
-use crate::rooms::response::{LastMessagePreviewResponse, RoomResponse};
+use crate::rooms::response::{LastMessagePreviewResponse, RoomInvitationResponse, RoomResponse, UnreadCountResponse};
+use crate::users::model::PresenceStatus;
 use crate::users::response::UserProfileResponse;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -55,12 +56,19 @@ pub enum NotificationEvent {
      * Different chat messages, sent to all active users in a room. `sender` carries the
      * message author's profile so clients can render a first-time sender without a
      * separate lookup (the timeline page bundles historical senders the same way).
+     * `thread` is present when the message is a thread reply and carries the root's updated
+     * counters; it is omitted for messages in the main timeline. `unread` holds the recipient's
+     * own unread count in the room after this message, so it differs per recipient.
      */
     #[serde(rename_all = "camelCase")]
     ChatMessage {
         message: MessageResponse,
         room_preview_text: LastMessagePreviewResponse,
         sender: RoomMemberResponse,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thread: Option<ThreadSummaryResponse>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unread: Option<UnreadCountResponse>,
     },
 
     /**
@@ -69,9 +77,10 @@ pub enum NotificationEvent {
     SystemMessage { message: serde_json::Value },
 
     /**
-     * Sending this event to a newly invited user. `first_message` carries the optional
+     * Sending this event to a user who was added to a room: by creating it with them, or by
+     * accepting an invitation or following an invite link. `first_message` carries the optional
      * first message the room was created with (authored by `created_by`), so the client
-     * can render it immediately without a separate timeline fetch. `None` for invites
+     * can render it immediately without a separate timeline fetch. `None` for joins
      * and rooms created without a first message.
      */
     #[serde(rename_all = "camelCase")]
@@ -81,6 +90,13 @@ pub enum NotificationEvent {
         first_message: Option<MessageResponse>,
     },
 
+    /**
+     * Sending this event to a user who was invited to a group. They are not in the room until
+     * they accept; `NewRoom` follows then.
+     */
+    #[serde(rename_all = "camelCase")]
+    RoomInvitationReceived { invitation: RoomInvitationResponse },
+
     /**
      * Sending this event to a user who has left a room
      */
@@ -88,7 +104,9 @@ pub enum NotificationEvent {
     LeaveRoom { room_id: Uuid },
 
     /**
-     * Sending this event to all users in a room where a member has left
+     * Sending this event to all users in a room whose membership or metadata changed: a member
+     * joined, left or was removed, a role changed, or the room was renamed, given a new image,
+     * topic or retention. `message` is the room-change record, which carries the new values.
      */
     #[serde(rename_all = "camelCase")]
     RoomChangeEvent {
@@ -97,10 +115,115 @@ pub enum NotificationEvent {
     },
 
     /**
-     * Sending this event to all users in a room when a user has read the latest message
+     * Sending this event to all users in a room when a message was edited. `room_preview_text`
+     * is only present when the edited message is the room's newest, i.e. when the room list
+     * entry has to change too.
+     */
+    #[serde(rename_all = "camelCase")]
+    MessageEdited {
+        message: MessageResponse,
+        room_preview_text: Option<LastMessagePreviewResponse>,
+    },
+
+    /**
+     * Sending this event to all users in a room when a message was deleted. `message` is the
+     * tombstone the timeline will return from now on; `room_preview_text` as in `MessageEdited`.
+     */
+    #[serde(rename_all = "camelCase")]
+    MessageDeleted {
+        message: MessageResponse,
+        room_preview_text: Option<LastMessagePreviewResponse>,
+    },
+
+    /**
+     * Sending this event to all users in a room when messages outlived the room's retention and
+     * were deleted. Clients drop every message of the room created at or before `until`, with
+     * the thread replies under them, and the room preview when nothing newer is left.
+     */
+    #[serde(rename_all = "camelCase")]
+    MessagesExpired { room_id: Uuid, until: DateTime<Utc> },
+
+    /**
+     * Sending this event to all users in a room when a member added (`reacted == true`) or
+     * removed a reaction. `count` is the number of users left reacting with this emoji after
+     * the change, so clients can set the value instead of counting along.
      */
     #[serde(rename_all = "camelCase")]
-    UserReadChat { user_id: Uuid, room_id: Uuid },
+    ReactionChanged {
+        room_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+        reacted: bool,
+        count: i64,
+    },
+
+    /**
+     * Sending this event to all users in a room when a poll's votes changed or it was closed.
+     * `results` are the totals after the change, without anyone's `myVotes`. For a vote or a
+     * retraction, `user_id` is the voter and `choices` what they picked now — empty once
+     * retracted. For a close, both are omitted and `closed_at` is set.
+     */
+    #[serde(rename_all = "camelCase")]
+    PollUpdated {
+        room_id: Uuid,
+        message_id: Uuid,
+        results: PollResultsResponse,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_id: Option<Uuid>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        choices: Option<Vec<u32>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        closed_at: Option<DateTime<Utc>>,
+    },
+
+    /**
+     * Sending this event to all users in a room when a user's read marker moved forward.
+     * `read_at` is the new marker: every message created at or before it has been read, which
+     * is what per-message "seen by" avatars are drawn from.
+     * `unread` is only set on the copy delivered to the reader themselves, for their other
+     * devices; the rest of the room never sees another member's counters.
+     */
+    #[serde(rename_all = "camelCase")]
+    UserReadChat {
+        user_id: Uuid,
+        room_id: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        read_at: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unread: Option<UnreadCountResponse>,
+    },
+
+    /**
+     * A room member started (`active`) or stopped typing. Sent to the other members only, and
+     * ephemeral: never sequenced, cached or pushed. The server sends the `active: false` itself
+     * once a member has not refreshed their typing state for a few seconds, so clients never
+     * need a timer of their own to clear a stale indicator.
+     */
+    #[serde(rename_all = "camelCase")]
+    Typing { room_id: Uuid, user_id: Uuid, active: bool },
+
+    /**
+     * A user went online, away or offline. Sent to their friends and to everyone they share a
+     * room with, except users on either side of a block. `last_seen_at` is set when the user
+     * went offline. Ephemeral: after a reconnect, clients reload presence via
+     * `POST /users/presence` instead of replaying stale transitions.
+     */
+    #[serde(rename_all = "camelCase")]
+    PresenceChanged {
+        user_id: Uuid,
+        status: PresenceStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_seen_at: Option<DateTime<Utc>>,
+    },
+
+    /**
+     * An admin banned (`banned == true`) or unbanned a user. Sent to the user themselves and to
+     * everyone who sees their presence, so clients can drop them from friend lists and search
+     * results, or bring them back, without a reload.
+     */
+    #[serde(rename_all = "camelCase")]
+    UserBanned { user_id: Uuid, banned: bool },
 
     /**
      * Control event: the client's last known sequence is too old to be replayed from the
@@ -113,21 +236,38 @@ pub enum NotificationEvent {
 }
 
 impl NotificationEvent {
+    /// Attaches one recipient's unread counters, for the events that carry them. Every other event
+    /// is returned unchanged.
+    pub fn with_unread(mut self, counts: UnreadCountResponse) -> Self {
+        match &mut self {
+            NotificationEvent::ChatMessage { unread, .. } | NotificationEvent::UserReadChat { unread, .. } => *unread = Some(counts),
+            _ => {}
+        }
+        self
+    }
+
     /// Ephemeral events are delivered live-only: they never receive a sequence number and
     /// are never cached for replay. A typing indicator from 30 minutes ago is irrelevant,
     /// so re-delivering it after a reconnect would be wrong. Durable events (the default)
     /// are sequenced and cached so a reconnecting client can catch up without loss.
     pub fn is_ephemeral(&self) -> bool {
         match self {
-            NotificationEvent::Resync { .. } => true,
+            NotificationEvent::Resync { .. } | NotificationEvent::Typing { .. } | NotificationEvent::PresenceChanged { .. } => true,
             NotificationEvent::FriendRequestReceived { .. }
             | NotificationEvent::FriendRequestAccepted { .. }
             | NotificationEvent::ChatMessage { .. }
             | NotificationEvent::SystemMessage { .. }
             | NotificationEvent::NewRoom { .. }
+            | NotificationEvent::RoomInvitationReceived { .. }
             | NotificationEvent::LeaveRoom { .. }
             | NotificationEvent::RoomChangeEvent { .. }
-            | NotificationEvent::UserReadChat { .. } => false,
+            | NotificationEvent::MessageEdited { .. }
+            | NotificationEvent::MessageDeleted { .. }
+            | NotificationEvent::MessagesExpired { .. }
+            | NotificationEvent::ReactionChanged { .. }
+            | NotificationEvent::PollUpdated { .. }
+            | NotificationEvent::UserReadChat { .. }
+            | NotificationEvent::UserBanned { .. } => false,
         }
     }
 }
@@ -0,0 +1,140 @@
+//! Cross-instance delivery for the notification bus.
+//!
+//! [`BroadcastChannel`] only knows the receivers attached to its own process. Behind a load
+//! balancer, the instance that produces an event is often not the one holding the recipient's
+//! stream, so every fan-out is also published to the other instances, which hand it to whichever
+//! of their local receivers it concerns.
+//!
+//! What crosses the wire is the *finished* envelope — already sequenced, unread counters already
+//! attached. Sequencing happens once, on the producing instance, through
+//! [`Cache::append_notification`](crate::cache::redis_cache::Cache::append_notification); a
+//! receiving instance only delivers. That is what keeps the replay stream free of duplicates no
+//! matter how many instances hear the event.
+
+use crate::broadcast::{BroadcastChannel, Notification};
+use crate::cache::util::NOTIFICATION_RELAY;
+use futures::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::{AsyncTypedCommands, Client, ErrorKind, RedisError, RedisResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Deliveries per published message. Mirrors the push batching: one message per fan-out is the
+/// point, but a large room must not produce a payload Redis and every subscriber have to swallow
+/// whole.
+const RELAY_BATCH_SIZE: usize = 500;
+
+/// How long the listener waits before resubscribing after losing its connection.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// One fan-out, as published to the other instances.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RelayBatch {
+    /// The publishing instance. Pub/sub echoes a message back to its publisher, whose receivers
+    /// already have it.
+    pub(crate) origin: Uuid,
+    pub(crate) deliveries: Vec<RelayedNotification>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RelayedNotification {
+    pub(crate) user_id: Uuid,
+    pub(crate) notification: Notification,
+}
+
+/// How a fan-out reaches receivers attached to other instances.
+///
+/// An enum for the same reason as [`PushNotificationProducer`](crate::kafka::PushNotificationProducer):
+/// the backends are known at compile time.
+pub enum NodeRelay {
+    /// A single instance: every receiver is local and there is nobody to relay to. Used whenever
+    /// Redis is deactivated, since without a shared cache there is no shared sequencing either.
+    Local,
+    /// Redis pub/sub on one channel that every instance subscribes to.
+    Redis(RedisRelay),
+}
+
+impl NodeRelay {
+    /// Publishes one fan-out to the other instances. A failure is logged, not returned: the local
+    /// receivers already have the event, and remote ones recover through replay on reconnect.
+    pub(crate) async fn publish(&self, origin: Uuid, deliveries: Vec<RelayedNotification>) {
+        let NodeRelay::Redis(relay) = self else { return };
+
+        let mut deliveries = deliveries;
+        while !deliveries.is_empty() {
+            let rest = deliveries.split_off(deliveries.len().min(RELAY_BATCH_SIZE));
+            let batch = RelayBatch { origin, deliveries };
+            if let Err(error) = relay.publish(&batch).await {
+                warn!(recipients = batch.deliveries.len(), error = %error, "Failed to relay notifications to other instances");
+            }
+            deliveries = rest;
+        }
+    }
+}
+
+/// The publishing half of the Redis relay. The subscribing half is [`RedisRelayListener`].
+pub struct RedisRelay {
+    connection: ConnectionManager,
+}
+
+impl RedisRelay {
+    /// Connects both halves. The listener needs a dedicated connection — a subscribed connection
+    /// can do nothing else — so it keeps the client and opens its own.
+    pub async fn connect(redis_url: &str) -> RedisResult<(Self, RedisRelayListener)> {
+        let client = Client::open(redis_url)?;
+        let connection = client.get_connection_manager().await?;
+
+        info!("Notification relay enabled, fan-outs reach every instance.");
+        Ok((Self { connection }, RedisRelayListener { client }))
+    }
+
+    async fn publish(&self, batch: &RelayBatch) -> RedisResult<()> {
+        let payload = serde_json::to_string(batch).map_err(|err| RedisError::from((ErrorKind::Parse, "Failed to serialize relay batch", err.to_string())))?;
+        let mut con = self.connection.clone();
+        let receivers = con.publish(NOTIFICATION_RELAY, payload).await?;
+        debug!(receivers, "Relayed fan-out to other instances");
+        Ok(())
+    }
+}
+
+/// Hands fan-outs published by other instances to this instance's receivers.
+pub struct RedisRelayListener {
+    client: Client,
+}
+
+impl RedisRelayListener {
+    /// Listens forever, resubscribing whenever the connection drops. Spawned once by the builder,
+    /// which keeps the handle so shutdown can abort it.
+    ///
+    /// Events published while the listener is reconnecting are not delivered live. Durable ones are
+    /// still in the recipients' replay streams, so a client that notices the gap in `seq` (or simply
+    /// reconnects) gets them back; ephemeral ones are gone, which is what ephemeral means.
+    pub async fn run(self, bus: Arc<BroadcastChannel>) {
+        loop {
+            if let Err(error) = self.listen(&bus).await {
+                warn!(error = %error, "Notification relay subscription lost, resubscribing");
+            }
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    }
+
+    async fn listen(&self, bus: &BroadcastChannel) -> RedisResult<()> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(NOTIFICATION_RELAY).await?;
+        let mut messages = pubsub.on_message();
+
+        while let Some(message) = messages.next().await {
+            let payload: String = message.get_payload()?;
+            match serde_json::from_str::<RelayBatch>(&payload) {
+                Ok(batch) => bus.deliver_relayed(batch).await,
+                Err(error) => warn!(error = %error, "Dropping unparsable relay batch"),
+            }
+        }
+        Err(RedisError::from((ErrorKind::Io, "relay subscription closed")))
+    }
+}
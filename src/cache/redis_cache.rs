@@ -1,9 +1,11 @@
 use crate::broadcast::Notification;
-use crate::cache::util::{ROOM_CONTEXT, USER_NOTIFICATIONS, USER_SEQUENCE};
+use crate::cache::util::{PRESENCE, ROOM_CONTEXT, USER_NOTIFICATIONS, USER_SEQUENCE};
 use crate::rooms::model::RoomContext;
+use crate::users::model::PresenceStatus;
 use async_trait::async_trait;
 use redis::aio::ConnectionManager;
 use redis::{AsyncTypedCommands, Client, ErrorKind, RedisError, RedisResult, Script};
+use std::collections::HashMap;
 use std::sync::LazyLock;
 use tracing::{info, warn};
 use uuid::Uuid;
@@ -21,6 +23,14 @@ const STREAM_MAX_LEN: usize = 300;
 /// Single field under which the serialized notification JSON is stored in each stream entry.
 const STREAM_FIELD: &str = "data";
 
+/// How long a connection counts as live after its last heartbeat, and the TTL of the whole presence
+/// hash. Several heartbeat intervals long, so one late heartbeat does not flicker a user offline,
+/// while an instance that died without cleaning up stops claiming its users within a minute.
+pub const PRESENCE_TTL_SECONDS: i64 = 60;
+
+/// Field of the presence hash that marks a user as away. Every other field is a connection id.
+const PRESENCE_AWAY_FIELD: &str = "away";
+
 /// Decoded `XRANGE` reply: a list of `(entry_id, [(field, value), ...])`.
 type StreamEntries = Vec<(String, Vec<(String, String)>)>;
 
@@ -94,6 +104,25 @@ fn parse_stream_seq(id: &str) -> Option<u64> {
     id.split('-').next()?.parse().ok()
 }
 
+/// Derives a user's presence from the fields of their presence hash.
+///
+/// Connections whose heartbeat is older than [`PRESENCE_TTL_SECONDS`] are ignored: the hash only
+/// expires as a whole, so a connection whose instance died keeps its field until the last live
+/// connection stops refreshing the key.
+fn status_from_fields(fields: &HashMap<String, String>, now: i64) -> PresenceStatus {
+    let live = fields
+        .iter()
+        .filter(|(field, _)| field.as_str() != PRESENCE_AWAY_FIELD)
+        .filter_map(|(_, beat)| beat.parse::<i64>().ok())
+        .any(|beat| now - beat < PRESENCE_TTL_SECONDS);
+
+    match (live, fields.contains_key(PRESENCE_AWAY_FIELD)) {
+        (false, _) => PresenceStatus::Offline,
+        (true, true) => PresenceStatus::Away,
+        (true, false) => PresenceStatus::Online,
+    }
+}
+
 /// Outcome of a replay request. Either the missing notifications could be served from the
 /// cache, or the client's last known sequence is too old (gap larger than the retention
 /// window) and it must re-fetch authoritative state via REST.
@@ -125,6 +154,15 @@ pub trait Cache: Send + Sync {
     async fn get_room_context(&self, room_id: &Uuid) -> RedisResult<Option<RoomContext>>;
     async fn set_room_context(&self, room_id: &Uuid, context: &RoomContext) -> RedisResult<()>;
     async fn invalidate_room_context(&self, room_id: &Uuid) -> RedisResult<()>;
+    /// Record a heartbeat for one live connection of a user. Shared by every instance, so presence
+    /// reflects all of a user's connections, wherever they are attached.
+    async fn put_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()>;
+    /// Forget one connection of a user, on a clean disconnect.
+    async fn remove_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()>;
+    async fn set_presence_away(&self, user_id: &Uuid, away: bool) -> RedisResult<()>;
+    /// Presence of each user, in the order given, or `None` when presence is not shared (no
+    /// Redis), in which case each instance only knows about its own connections.
+    async fn get_presence(&self, user_ids: &[Uuid]) -> RedisResult<Option<Vec<PresenceStatus>>>;
 }
 
 //docs: https://docs.rs/redis/latest/redis/
@@ -298,6 +336,55 @@ impl Cache for RedisCache {
         con.del(&key).await?;
         Ok(())
     }
+
+    async fn put_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()> {
+        let mut con = self.connection.clone();
+        let key = format!("{}{}", PRESENCE, user_id);
+        redis::pipe()
+            .atomic()
+            .hset(&key, connection_id.to_string(), chrono::Utc::now().timestamp())
+            .expire(&key, PRESENCE_TTL_SECONDS)
+            .exec_async(&mut con)
+            .await
+    }
+
+    async fn remove_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()> {
+        let mut con = self.connection.clone();
+        let key = format!("{}{}", PRESENCE, user_id);
+        con.hdel(&key, connection_id.to_string()).await?;
+        Ok(())
+    }
+
+    async fn set_presence_away(&self, user_id: &Uuid, away: bool) -> RedisResult<()> {
+        let mut con = self.connection.clone();
+        let key = format!("{}{}", PRESENCE, user_id);
+        if away {
+            redis::pipe()
+                .atomic()
+                .hset(&key, PRESENCE_AWAY_FIELD, 1)
+                .expire(&key, PRESENCE_TTL_SECONDS)
+                .exec_async(&mut con)
+                .await
+        } else {
+            con.hdel(&key, PRESENCE_AWAY_FIELD).await?;
+            Ok(())
+        }
+    }
+
+    async fn get_presence(&self, user_ids: &[Uuid]) -> RedisResult<Option<Vec<PresenceStatus>>> {
+        if user_ids.is_empty() {
+            return Ok(Some(vec![]));
+        }
+        let mut con = self.connection.clone();
+        let mut pipe = redis::pipe();
+        for user_id in user_ids {
+            pipe.hgetall(format!("{}{}", PRESENCE, user_id));
+        }
+        let hashes: Vec<HashMap<String, String>> = pipe.query_async(&mut con).await?;
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(Some(hashes.iter().map(|fields| status_from_fields(fields, now)).collect()))
+    }
 }
 
 pub struct NoOpCache;
@@ -325,6 +412,22 @@ impl Cache for NoOpCache {
     async fn invalidate_room_context(&self, _room_id: &Uuid) -> RedisResult<()> {
         Ok(())
     }
+
+    async fn put_presence(&self, _user_id: &Uuid, _connection_id: &Uuid) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn remove_presence(&self, _user_id: &Uuid, _connection_id: &Uuid) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn set_presence_away(&self, _user_id: &Uuid, _away: bool) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn get_presence(&self, _user_ids: &[Uuid]) -> RedisResult<Option<Vec<PresenceStatus>>> {
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
@@ -397,4 +500,37 @@ mod tests {
             ReplayResult::Events(events) if events.is_empty()
         ));
     }
+
+    fn presence_fields(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields.iter().map(|(field, value)| (field.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn presence_follows_the_freshest_connection() {
+        let now = 1_000_000;
+        let fresh = (now - 5).to_string();
+        let stale = (now - PRESENCE_TTL_SECONDS).to_string();
+
+        assert_eq!(status_from_fields(&presence_fields(&[]), now), PresenceStatus::Offline);
+        assert_eq!(status_from_fields(&presence_fields(&[("a", &stale)]), now), PresenceStatus::Offline);
+        assert_eq!(
+            status_from_fields(&presence_fields(&[("a", &stale), ("b", &fresh)]), now),
+            PresenceStatus::Online
+        );
+    }
+
+    #[test]
+    fn the_away_flag_only_matters_while_connected() {
+        let now = 1_000_000;
+        let fresh = (now - 5).to_string();
+
+        assert_eq!(
+            status_from_fields(&presence_fields(&[("a", &fresh), (PRESENCE_AWAY_FIELD, "1")]), now),
+            PresenceStatus::Away
+        );
+        assert_eq!(
+            status_from_fields(&presence_fields(&[(PRESENCE_AWAY_FIELD, "1")]), now),
+            PresenceStatus::Offline
+        );
+    }
 }
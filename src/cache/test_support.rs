@@ -14,9 +14,10 @@
 use crate::broadcast::Notification;
 use crate::cache::redis_cache::{Cache, ReplayResult};
 use crate::rooms::model::RoomContext;
+use crate::users::model::PresenceStatus;
 use async_trait::async_trait;
 use redis::{ErrorKind, RedisError, RedisResult};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Mutex;
 use uuid::Uuid;
 
@@ -34,6 +35,9 @@ struct UserStream {
 #[derive(Default)]
 pub struct InMemoryCache {
     users: Mutex<HashMap<Uuid, UserStream>>,
+    /// Live connection ids per user, the in-memory presence hash. Heartbeat ages are not modelled:
+    /// a connection is live until it is removed.
+    presence: Mutex<HashMap<Uuid, HashSet<Uuid>>>,
 }
 
 impl InMemoryCache {
@@ -130,6 +134,34 @@ impl Cache for InMemoryCache {
     async fn invalidate_room_context(&self, _room_id: &Uuid) -> RedisResult<()> {
         Ok(())
     }
+
+    async fn put_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()> {
+        self.presence.lock().expect("cache mutex").entry(*user_id).or_default().insert(*connection_id);
+        Ok(())
+    }
+
+    async fn remove_presence(&self, user_id: &Uuid, connection_id: &Uuid) -> RedisResult<()> {
+        if let Some(connections) = self.presence.lock().expect("cache mutex").get_mut(user_id) {
+            connections.remove(connection_id);
+        }
+        Ok(())
+    }
+
+    async fn set_presence_away(&self, _user_id: &Uuid, _away: bool) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn get_presence(&self, user_ids: &[Uuid]) -> RedisResult<Option<Vec<PresenceStatus>>> {
+        let presence = self.presence.lock().expect("cache mutex");
+        let statuses = user_ids
+            .iter()
+            .map(|user_id| match presence.get(user_id) {
+                Some(connections) if !connections.is_empty() => PresenceStatus::Online,
+                _ => PresenceStatus::Offline,
+            })
+            .collect();
+        Ok(Some(statuses))
+    }
 }
 
 /// A `Cache` where every operation fails, for the error branches that a working cache cannot reach.
@@ -166,4 +198,20 @@ impl Cache for FailingCache {
     async fn invalidate_room_context(&self, _room_id: &Uuid) -> RedisResult<()> {
         Err(Self::error())
     }
+
+    async fn put_presence(&self, _user_id: &Uuid, _connection_id: &Uuid) -> RedisResult<()> {
+        Err(Self::error())
+    }
+
+    async fn remove_presence(&self, _user_id: &Uuid, _connection_id: &Uuid) -> RedisResult<()> {
+        Err(Self::error())
+    }
+
+    async fn set_presence_away(&self, _user_id: &Uuid, _away: bool) -> RedisResult<()> {
+        Err(Self::error())
+    }
+
+    async fn get_presence(&self, _user_ids: &[Uuid]) -> RedisResult<Option<Vec<PresenceStatus>>> {
+        Err(Self::error())
+    }
 }
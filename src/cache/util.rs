@@ -1,7 +1,8 @@
 /**
- * Cached participant snapshot per room, used for fast membership lookups and broadcast fan-out
+ * Cached participant snapshot per room, used for fast membership lookups and broadcast fan-out.
+ * `v2`: members carry their room role.
  */
-pub const ROOM_CONTEXT: &str = "room_context:";
+pub const ROOM_CONTEXT: &str = "room_context:v2:";
 
 /**
  * Per-user Redis Stream holding recent durable notifications for reconnect replay.
@@ -13,3 +14,15 @@ pub const USER_NOTIFICATIONS: &str = "user_notifications:";
  * Monotonic per-user sequence counter (INCR), used to order and replay durable notifications
  */
 pub const USER_SEQUENCE: &str = "user_seq:";
+
+/**
+ * Per-user presence hash: one field per live connection (value: unix seconds of its last heartbeat)
+ * plus an optional `away` flag. Expires on its own once every connection stopped heartbeating.
+ */
+pub const PRESENCE: &str = "presence:";
+
+/**
+ * Pub/sub channel every instance subscribes to. Carries each fan-out, already sequenced, to the
+ * instances holding the recipients' live connections.
+ */
+pub const NOTIFICATION_RELAY: &str = "notification_relay";
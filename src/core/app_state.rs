@@ -1,9 +1,12 @@
 //! The wired application, and how a handler gets a piece of it.
 
+use crate::admin::AdminService;
 use crate::core::ISMConfig;
-use crate::messaging::{MessageService, NotificationService};
+use crate::messaging::{
+    ForwardService, MediaService, MentionService, MessageService, NotificationService, PollService, ScheduleService, SearchService, TypingService,
+};
 use crate::rooms::{RoomService, ShareService, TimelineService};
-use crate::users::UserService;
+use crate::users::{PresenceService, UserService};
 use axum::extract::FromRef;
 use std::sync::Arc;
 
@@ -23,9 +26,18 @@ pub struct AppState {
     pub room_service: RoomService,
     pub share_service: ShareService,
     pub timeline_service: TimelineService,
+    pub search_service: SearchService,
+    pub mention_service: MentionService,
     pub message_service: MessageService,
+    pub poll_service: PollService,
+    pub media_service: MediaService,
     pub notification_service: NotificationService,
+    pub typing_service: TypingService,
+    pub presence_service: PresenceService,
     pub user_service: UserService,
+    pub schedule_service: ScheduleService,
+    pub forward_service: ForwardService,
+    pub admin_service: AdminService,
 }
 
 /// Lets a handler write `State<RoomService>` instead of `State<Arc<AppState>>`.
@@ -49,7 +61,16 @@ service_from_state! {
     RoomService => room_service,
     ShareService => share_service,
     TimelineService => timeline_service,
+    SearchService => search_service,
+    MentionService => mention_service,
     MessageService => message_service,
+    PollService => poll_service,
+    MediaService => media_service,
     NotificationService => notification_service,
+    TypingService => typing_service,
+    PresenceService => presence_service,
     UserService => user_service,
+    ScheduleService => schedule_service,
+    ForwardService => forward_service,
+    AdminService => admin_service,
 }
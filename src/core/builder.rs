@@ -5,14 +5,19 @@
 //! the service graph a DAG — a service can only be given something that already exists a few lines
 //! above it, so a cycle is not expressible.
 
-use crate::broadcast::BroadcastChannel;
+use crate::admin::AdminService;
+use crate::broadcast::{BroadcastChannel, NodeRelay, RedisRelay};
 use crate::cache::redis_cache::{Cache, NoOpCache, RedisCache};
 use crate::core::{AppState, Database, ISMConfig, Repository, Service, ShutdownController};
 use crate::kafka::PushNotificationProducer;
-use crate::messaging::{ChatRepository, MessageService, NotificationService};
+use crate::messaging::{
+    ChatRepository, ForwardService, MediaService, MentionService, MessageService, NotificationService, PollService, RetentionService, ScheduleService,
+    SearchService, TypingService,
+};
 use crate::object_storage::ObjectStorage;
+use crate::rooms::model::RetentionPolicy;
 use crate::rooms::{RoomNotifier, RoomRepository, RoomService, ShareService, TimelineService};
-use crate::users::{UserRepository, UserService};
+use crate::users::{PresenceService, UserRepository, UserService};
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
@@ -210,10 +215,8 @@ impl AppStateBuilder {
     /// Wires everything, in dependency order.
     pub async fn build(self) -> StartupResult<Bootstrap> {
         let config = self.config;
-        // Currently empty: nothing the builder wires needs a background task of its own. Kept
-        // because the *shutdown* contract lives here — anything spawned during wiring must be
-        // pushed onto this, or `Shutdown::run` cannot abort it before the pool is closed.
-        #[allow(unused_mut)]
+        // The *shutdown* contract lives here — anything spawned during wiring must be pushed onto
+        // this, or `Shutdown::run` cannot abort it before the pool is closed.
         let mut tasks: Vec<JoinHandle<()>> = Vec::new();
 
         // Created first: services that own long-lived connections need the listen-only half, and
@@ -227,12 +230,14 @@ impl AppStateBuilder {
         };
         info!("Established connection to the PostgreSQL database.");
 
-        let cache: Arc<dyn Cache> = match (self.cache, &config.redis_cache_url) {
-            (Some(cache), _) => cache,
-            (None, Some(url)) => Arc::new(RedisCache::connect(url.clone()).await?),
+        // The relay shares Redis with the cache: an injected cache means a test without a live
+        // Redis, and no Redis means a single instance with nobody to relay to.
+        let (cache, relay): (Arc<dyn Cache>, _) = match (self.cache, &config.redis_cache_url) {
+            (Some(cache), _) => (cache, None),
+            (None, Some(url)) => (Arc::new(RedisCache::connect(url.clone()).await?), Some(RedisRelay::connect(url).await?)),
             (None, None) => {
                 info!("Redis is deactivated. Initializing NoOpCache...");
-                Arc::new(NoOpCache)
+                (Arc::new(NoOpCache), None)
             }
         };
 
@@ -243,7 +248,11 @@ impl AppStateBuilder {
 
         // ── 2. Event bus ─────────────────────────────────────────────────────
         let producer = PushNotificationProducer::connect(config.use_kafka, config.kafka_config.clone())?;
-        let bus = Arc::new(BroadcastChannel::new(cache.clone(), producer));
+        let (relay, relay_listener) = match relay {
+            Some((relay, listener)) => (NodeRelay::Redis(relay), Some(listener)),
+            None => (NodeRelay::Local, None),
+        };
+        let bus = Arc::new(BroadcastChannel::new(cache.clone(), producer).with_relay(relay));
 
         // ── 3. Repositories ──────────────────────────────────────────────────
         let rooms = RoomRepository::new(&database);
@@ -254,43 +263,92 @@ impl AppStateBuilder {
         let notifier = RoomNotifier::new(bus.clone(), rooms.clone(), cache.clone());
 
         // ── 5. Services, in dependency order ─────────────────────────────────
-        // Everything below depends only on what is already above it. `UserService` is last
-        // because it is the only service that depends on another service.
+        // Everything below depends only on what is already above it. `UserService`,
+        // `ScheduleService`, `ForwardService` and `AdminService` come last because they are the
+        // services that depend on other services.
+        let retention = RetentionPolicy::new(config.default_message_retention_secs);
         let room_service = RoomService::new(
             database.clone(),
             rooms.clone(),
             chats.clone(),
             users.clone(),
             notifier.clone(),
-            storage,
+            storage.clone(),
             config.object_db_config.bucket_name.clone(),
+            retention,
         );
         let share_service = ShareService::new(rooms.clone());
-        let timeline_service = TimelineService::new(rooms.clone(), chats.clone());
-        let message_service = MessageService::new(database.clone(), rooms, chats, notifier);
-        let notification_service = NotificationService::new(bus.clone(), cache, shutdown_controller.signal());
-        let user_service = UserService::new(database.clone(), users, room_service.clone(), bus);
+        let timeline_service = TimelineService::new(rooms.clone(), chats.clone(), retention);
+        let search_service = SearchService::new(chats.clone(), retention);
+        let mention_service = MentionService::new(chats.clone(), retention);
+        let retention_service = RetentionService::new(database.clone(), rooms.clone(), chats.clone(), notifier.clone(), storage.clone(), retention);
+        let media_service = MediaService::new(chats.clone(), notifier.clone(), storage.clone());
+        let message_service = MessageService::new(database.clone(), rooms.clone(), chats.clone(), notifier.clone(), storage, retention);
+        let poll_service = PollService::new(database.clone(), chats.clone(), notifier.clone());
+        let typing_service = TypingService::new(notifier.clone());
+        let notification_service = NotificationService::new(bus.clone(), cache.clone(), shutdown_controller.signal());
+        let presence_service = PresenceService::new(cache, bus.clone(), users.clone());
+        let user_service = UserService::new(database.clone(), users.clone(), room_service.clone(), bus.clone());
+        let schedule_service = ScheduleService::new(rooms.clone(), chats.clone(), notifier.clone(), message_service.clone());
+        let forward_service = ForwardService::new(
+            rooms.clone(),
+            chats,
+            users.clone(),
+            notifier,
+            room_service.clone(),
+            message_service.clone(),
+            retention,
+        );
+        let admin_service = AdminService::new(rooms, users, room_service.clone(), message_service.clone(), bus.clone());
 
         for name in [
             RoomService::NAME,
             ShareService::NAME,
             TimelineService::NAME,
+            SearchService::NAME,
+            MentionService::NAME,
             MessageService::NAME,
+            PollService::NAME,
+            MediaService::NAME,
+            RetentionService::NAME,
             NotificationService::NAME,
+            TypingService::NAME,
+            PresenceService::NAME,
             UserService::NAME,
+            ScheduleService::NAME,
+            ForwardService::NAME,
+            AdminService::NAME,
         ] {
             info!(service = name, "Service wired");
         }
 
+        // ── 6. Background tasks ──────────────────────────────────────────────
+        tasks.push(tokio::spawn(typing_service.clone().run_expiry()));
+        tasks.push(tokio::spawn(retention_service.run_reaper()));
+        tasks.push(tokio::spawn(media_service.clone().run_sweeper()));
+        tasks.push(tokio::spawn(schedule_service.clone().run_dispatcher()));
+        if let Some(listener) = relay_listener {
+            tasks.push(tokio::spawn(listener.run(bus.clone())));
+        }
+
         Ok(Bootstrap {
             state: AppState {
                 env: config,
                 room_service,
                 share_service,
                 timeline_service,
+                search_service,
+                mention_service,
                 message_service,
+                poll_service,
+                media_service,
                 notification_service,
+                typing_service: typing_service.clone(),
+                presence_service,
                 user_service,
+                schedule_service,
+                forward_service,
+                admin_service,
             },
             shutdown: Shutdown {
                 tasks,
@@ -25,6 +25,10 @@ pub struct ISMConfig {
     pub log_level: String,
     pub cors_origin: String,
     pub redis_cache_url: Option<String>,
+    /// How long messages are kept, in seconds, in rooms without a setting of their own. `0`, the
+    /// default, keeps them forever.
+    #[serde(default)]
+    pub default_message_retention_secs: u32,
     pub room_db_config: RoomDbConfig,
     pub object_db_config: ObjectStorageConfig,
     pub token_issuer: TokenIssuer,
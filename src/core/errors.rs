@@ -6,7 +6,7 @@ use serde::Serialize;
 use thiserror::Error;
 use validator::ValidationErrors;
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ErrorResponse {
     timestamp: String,
     status: u16,
@@ -31,7 +31,7 @@ impl ErrorResponse {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, Copy)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ErrorCode {
     // Authentication & Authorization
@@ -125,8 +125,13 @@ impl AppError {
     }
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
+impl AppError {
+    /// The status and body a client gets for this error, after internal details have been logged
+    /// and replaced by a generic message.
+    ///
+    /// Shared by the HTTP response below and the error frames of the WebSocket command protocol, so
+    /// both transports report a failure with the same `errorCode` and the same sanitising.
+    pub fn into_error_response(self) -> (StatusCode, ErrorResponse) {
         // Log every internal error with its full details before the message is sanitised.
         match &self {
             // Not an internal failure: a caller asking for a row that does not exist is an
@@ -175,7 +180,13 @@ impl IntoResponse for AppError {
             ),
         };
 
-        let body = ErrorResponse::new(status, error_code, message);
+        (status, ErrorResponse::new(status, error_code, message))
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.into_error_response();
         (status, Json(body)).into_response()
     }
 }
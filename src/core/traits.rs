@@ -70,8 +70,8 @@ pub trait Repository: Clone + Send + Sync + 'static {
 /// When a genuine service-to-service dependency exists, the graph must stay a DAG. Rust has no
 /// garbage collector, so a cycle of `Arc`s is a permanent leak; here the cycle cannot even be
 /// built, because the composition root constructs services in dependency order and a service can
-/// only be handed something that already exists. The current graph has exactly one such edge:
-/// `UserService` → `RoomService`.
+/// only be handed something that already exists. The current graph has three such edges:
+/// `UserService` → `RoomService`, and `AdminService` → `RoomService` / `MessageService`.
 pub trait Service: Clone + Send + Sync + 'static {
     /// Stable name for the startup wiring log and tracing spans.
     const NAME: &'static str;
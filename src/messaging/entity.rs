@@ -12,6 +12,7 @@
 use crate::core::{DbRow, JsonColumn};
 use crate::messaging::model::MsgType;
 use crate::rooms::entity::RoomMemberSnapshotJson;
+use crate::rooms::model::RoomRole;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -25,6 +26,22 @@ pub struct MessageRow {
     pub msg_body: sqlx::types::Json<MessageBodyJson>,
     pub msg_type: MsgType,
     pub created_at: DateTime<Utc>,
+    /// Set on every edit; `None` for a message that was never changed.
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Set when the message was deleted. The row stays behind as a tombstone — see
+    /// [`MessageRow::tombstone_body`].
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The root of the thread this message was posted into; `None` for messages in the main
+    /// timeline, roots included.
+    pub thread_root_id: Option<Uuid>,
+    /// Number of thread replies, only ever non-zero on a root. `INTEGER` in the schema.
+    pub reply_count: i32,
+    pub last_reply_at: Option<DateTime<Utc>>,
+    /// The sender's own id for the message, when their client sent one. Unique per sender; what
+    /// makes a retried send idempotent.
+    pub client_message_id: Option<String>,
+    /// The original, when this message is a forward of one.
+    pub forwarded_from: Option<sqlx::types::Json<ForwardedFromJson>>,
 }
 
 impl DbRow for MessageRow {}
@@ -41,6 +58,7 @@ impl MessageRow {
             MessageBodyJson::Media(_) => MsgType::Media,
             MessageBodyJson::Reply(_) => MsgType::Reply,
             MessageBodyJson::RoomChange(_) => MsgType::RoomChange,
+            MessageBodyJson::Poll(_) => MsgType::Poll,
         };
         MessageRow {
             chat_room_id: room_id,
@@ -49,10 +67,165 @@ impl MessageRow {
             msg_body: sqlx::types::Json(msg_body),
             msg_type,
             created_at: Utc::now(),
+            edited_at: None,
+            deleted_at: None,
+            thread_root_id: None,
+            reply_count: 0,
+            last_reply_at: None,
+            client_message_id: None,
+            forwarded_from: None,
         }
     }
+
+    /// What a deleted message's `msg_body` is overwritten with.
+    ///
+    /// Deleting wipes the content rather than hiding it behind `deleted_at`: a flag alone would keep
+    /// the text readable to anyone with database access, which is not what a user pressing "delete"
+    /// expects. The row itself survives so replies and cursors that point at it keep resolving.
+    pub fn tombstone_body() -> MessageBodyJson {
+        MessageBodyJson::Text(TextJson {
+            text: String::new(),
+            mentions: Vec::new(),
+        })
+    }
+}
+
+/// The reactions on one message for one emoji, aggregated over `message_reaction`.
+///
+/// `reacted_by_me` is relative to the user the timeline was loaded for, which is why this is an
+/// aggregate row and not something stored on the message.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReactionCountRow {
+    pub message_id: Uuid,
+    pub emoji: String,
+    pub count: i64,
+    pub reacted_by_me: bool,
+}
+
+impl DbRow for ReactionCountRow {}
+
+/// The votes on one poll, aggregated over `poll_vote`: one row per option voted for, plus one with
+/// `option_index` `None` whose `count` is the number of distinct voters. `voted_by_me` is relative
+/// to the viewer, as on [`ReactionCountRow`].
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PollVoteCountRow {
+    pub message_id: Uuid,
+    pub option_index: Option<i16>,
+    pub count: i64,
+    pub voted_by_me: bool,
+}
+
+impl DbRow for PollVoteCountRow {}
+
+/// A message matching a search, with its relevance and a highlighted excerpt of the matched text.
+#[derive(Debug, sqlx::FromRow)]
+pub struct MessageSearchRow {
+    #[sqlx(flatten)]
+    pub message: MessageRow,
+    pub snippet: String,
+    pub rank: f32,
 }
 
+impl DbRow for MessageSearchRow {}
+
+/// What one reaper batch removed from one room: `count` messages, the newest created at `until`.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ExpiredMessagesRow {
+    pub chat_room_id: Uuid,
+    pub until: DateTime<Utc>,
+    pub count: i64,
+    /// Object keys of the uploads those messages attached. Their rows are gone; the objects are
+    /// for the caller to delete once the batch is committed.
+    pub media_keys: Vec<String>,
+}
+
+impl DbRow for ExpiredMessagesRow {}
+
+/// A row of `room_media`: one uploaded file and the room it belongs to.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RoomMediaRow {
+    pub media_id: Uuid,
+    pub room_id: Uuid,
+    /// Where the bytes are in object storage, and the `mediaKey` a message attaches them by.
+    pub object_key: String,
+    pub uploaded_by: Uuid,
+    /// Sniffed from the content on upload, never taken from the client. For a presigned upload
+    /// this is what the client announced until completion has checked it against the content.
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    /// `None` while a presigned upload is still reserved; nothing can use the upload until then.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// The message the upload is attached to, once one is; no other message can attach it then.
+    pub message_id: Option<Uuid>,
+}
+
+impl DbRow for RoomMediaRow {}
+
+impl RoomMediaRow {
+    /// Where the client `PUT`s a presigned upload. Never served and never attached; completion
+    /// copies it to `object_key`. The URL stays valid after that, so whatever deletes the upload
+    /// deletes this key as well.
+    pub fn upload_key(&self) -> String {
+        format!("rooms/{}/uploads/{}", self.room_id, self.media_id)
+    }
+}
+
+/// A row of `scheduled_message`: a message waiting to be sent at `send_at`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScheduledMessageRow {
+    pub scheduled_message_id: Uuid,
+    pub chat_room_id: Uuid,
+    pub sender_id: Uuid,
+    pub msg_body: sqlx::types::Json<ScheduledBodyJson>,
+    pub msg_type: MsgType,
+    pub thread_root_id: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Set, together with `failure`, when sending was attempted and refused for good — the sender
+    /// left the room, say, or the message being replied to is gone. Never retried after that.
+    pub failed_at: Option<DateTime<Utc>>,
+    pub failure: Option<String>,
+}
+
+impl DbRow for ScheduledMessageRow {}
+
+/// The stored value of `scheduled_message.msg_body`: the body as the client sent it, resolved only
+/// when the message is sent.
+///
+/// Tagged, unlike [`MessageBodyJson`]: the `msg_type` column cannot tell media linked by URL from
+/// an attached upload — both are `Media` — so the variant is named in the JSON instead of being
+/// recovered by shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum ScheduledBodyJson {
+    Text {
+        text: String,
+    },
+    Media {
+        media_url: String,
+        media_type: String,
+        mime_type: Option<String>,
+        alt_text: Option<String>,
+    },
+    Reply {
+        reply_msg_id: Uuid,
+        reply_text: String,
+    },
+    UploadedMedia {
+        media_key: String,
+        alt_text: Option<String>,
+    },
+    Poll {
+        question: String,
+        options: Vec<String>,
+        multiple_choice: bool,
+        closes_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl JsonColumn for ScheduledBodyJson {}
+
 /// The stored value of `chat_message.msg_body`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -61,6 +234,7 @@ pub enum MessageBodyJson {
     Media(MediaJson),
     Reply(ReplyJson),
     RoomChange(RoomChangeJson),
+    Poll(PollJson),
 }
 
 impl JsonColumn for MessageBodyJson {}
@@ -69,10 +243,24 @@ impl JsonColumn for MessageBodyJson {}
 #[serde(rename_all = "camelCase")]
 pub struct TextJson {
     pub text: String,
+    /// Parsed from `text` by the server when the message was sent or last edited.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<MentionJson>,
 }
 
 impl JsonColumn for TextJson {}
 
+/// A mention inside a message's text: `@` followed by a user id, or `@room` for everyone in the
+/// room. `offset` and `length` locate it in the text, counted in characters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum MentionJson {
+    User { user_id: Uuid, offset: u32, length: u32 },
+    Room { offset: u32, length: u32 },
+}
+
+impl JsonColumn for MentionJson {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaJson {
@@ -80,6 +268,9 @@ pub struct MediaJson {
     pub media_type: String,
     pub mime_type: Option<String>,
     pub alt_text: Option<String>,
+    /// The upload this message attaches; `None` for media linked by URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_key: Option<String>,
 }
 
 impl JsonColumn for MediaJson {}
@@ -88,6 +279,10 @@ impl JsonColumn for MediaJson {}
 ///
 /// The quoted fields are a snapshot on purpose: editing or deleting the original must not silently
 /// rewrite every reply that quotes it.
+///
+/// This is a *quote*, not thread membership. Replies that belong together are linked through
+/// `chat_message.thread_root_id`, which is what the thread endpoint pages over; a quote can sit in
+/// the main timeline or inside a thread alike.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReplyJson {
@@ -97,10 +292,54 @@ pub struct ReplyJson {
     pub reply_created_at: DateTime<Utc>,
     pub reply_msg_details: RepliedMessageJson,
     pub reply_text: String,
+    /// Parsed from `reply_text`, as on [`TextJson`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<MentionJson>,
 }
 
 impl JsonColumn for ReplyJson {}
 
+/// A poll. The votes are rows of `poll_vote`, which point at `options` by index — the reason the
+/// options can never change once the poll is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollJson {
+    pub question: String,
+    pub options: Vec<String>,
+    /// Whether a voter may pick more than one option.
+    pub multiple_choice: bool,
+    /// When the poll stops taking votes by itself; `None` keeps it open until it is closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closes_at: Option<DateTime<Utc>>,
+    /// Set when the sender closed the poll early.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl JsonColumn for PollJson {}
+
+impl PollJson {
+    /// Whether the poll still takes votes at `now`.
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.closed_at.is_none() && self.closes_at.is_none_or(|closes_at| closes_at > now)
+    }
+}
+
+/// The stored value of `chat_message.forwarded_from`: which message a forward copies, and who
+/// sent it when.
+///
+/// A snapshot like [`ReplyJson`]'s quote. The original lives in a room the forward's readers may
+/// not be in, so they could not look it up anyway; deleting it does not unmark its forwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardedFromJson {
+    pub message_id: Uuid,
+    pub sender_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl JsonColumn for ForwardedFromJson {}
+
 /// The quoted content inside a [`ReplyJson`]. A reply to a reply keeps only the text, so the chain
 /// does not nest without bound.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,21 +347,56 @@ impl JsonColumn for ReplyJson {}
 pub enum RepliedMessageJson {
     Text(TextJson),
     Media(MediaJson),
-    Reply { reply_text: String },
+    Reply {
+        reply_text: String,
+    },
+    /// Only the question; the results go on changing after the reply was sent.
+    Poll {
+        question: String,
+    },
 }
 
 impl JsonColumn for RepliedMessageJson {}
 
-/// A membership change recorded in the timeline.
+/// A membership or metadata change recorded in the timeline.
 ///
 /// `related_user` is a [`RoomMemberSnapshotJson`] rather than a live member type — see that type
-/// for why the shape is frozen.
+/// for why the shape is frozen. Who *caused* a change, where that is someone else — the member who
+/// kicked, the owner who promoted, whoever renamed the room — is the message's `sender_id`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum RoomChangeJson {
-    UserJoined { related_user: RoomMemberSnapshotJson },
-    UserLeft { related_user: RoomMemberSnapshotJson },
-    UserInvited { related_user: RoomMemberSnapshotJson },
+    UserJoined {
+        related_user: RoomMemberSnapshotJson,
+    },
+    UserLeft {
+        related_user: RoomMemberSnapshotJson,
+    },
+    UserInvited {
+        related_user: RoomMemberSnapshotJson,
+    },
+    UserKicked {
+        related_user: RoomMemberSnapshotJson,
+    },
+    /// `role` is the one `related_user` holds from now on.
+    UserRoleChanged {
+        related_user: RoomMemberSnapshotJson,
+        role: RoomRole,
+    },
+    RoomRenamed {
+        room_name: String,
+    },
+    ImageChanged {
+        image_url: String,
+    },
+    /// `topic` is `None` when it was cleared.
+    TopicChanged {
+        topic: Option<String>,
+    },
+    /// `retention_secs` as in `chat_room`: `None` when the room went back to the server default.
+    RetentionChanged {
+        retention_secs: Option<i32>,
+    },
 }
 
 impl JsonColumn for RoomChangeJson {}
@@ -136,6 +410,12 @@ mod convention_guards {
     use serde::Serialize;
 
     const _: () = assert!(!impls!(MessageRow: Serialize));
+    const _: () = assert!(!impls!(ReactionCountRow: Serialize));
+    const _: () = assert!(!impls!(PollVoteCountRow: Serialize));
+    const _: () = assert!(!impls!(ExpiredMessagesRow: Serialize));
+    const _: () = assert!(!impls!(MessageSearchRow: Serialize));
+    const _: () = assert!(!impls!(RoomMediaRow: Serialize));
+    const _: () = assert!(!impls!(ScheduledMessageRow: Serialize));
 
     // The storage types must keep both halves of their serde contract, or existing `msg_body`
     // values stop decoding.
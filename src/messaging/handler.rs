@@ -3,22 +3,37 @@
 //! The stream handlers are the longest in the project, but what remains here is transport: axum's
 //! SSE stream adapter, the WebSocket select loop, ping/pong. Everything a client could observe
 //! about *which* events it gets — subscription, replay, deduplication, resync — belongs to
-//! [`NotificationService`].
+//! [`NotificationService`], and what a command frame sent up a WebSocket does belongs to the
+//! services `messaging::socket` dispatches it into.
 
 use crate::auth::CurrentUser;
 use crate::broadcast::Notification;
 use crate::core::ValidatedJson;
 use crate::core::ValidatedQuery;
-use crate::core::errors::AppResponse;
-use crate::messaging::request::{NotificationBacklogQuery, SendMessageRequest, StreamHandshakeQuery};
-use crate::messaging::response::{MessageResponse, NotificationCursorResponse};
-use crate::messaging::service::NotificationService;
+use crate::core::cursor::{CursorResults, decode_cursor};
+use crate::core::errors::{AppError, AppResponse};
+use crate::messaging::model::{MentionCursor, MessageSearchCursor};
+use crate::messaging::request::{
+    EditMessageRequest, ForwardMessageRequest, MediaUploadUrlRequest, MentionFeedQuery, MessageSearchQuery, NotificationBacklogQuery, PollVoteRequest,
+    ReactionRequest, ScheduleMessageRequest, ScheduledMessagesQuery, SendMessageRequest, StreamHandshakeQuery, TypingRequest,
+};
+use crate::messaging::response::{
+    ForwardOutcome, MediaDownloadUrlResponse, MediaUploadResponse, MediaUploadUrlResponse, MessageResponse, MessageSearchHitResponse,
+    NotificationCursorResponse, PollResultsResponse, ScheduledMessageResponse,
+};
+use crate::messaging::service::{
+    ForwardService, MediaService, MentionService, NotificationService, PollService, ScheduleService, SearchService, TypingService,
+};
+use crate::messaging::socket::SocketCommands;
 use crate::messaging::{MessageService, service::ConnectionGuard};
+use crate::rooms::RoomService;
+use crate::users::PresenceService;
 use axum::Json;
-use axum::extract::State;
 use axum::extract::ws::{CloseFrame, Message, Utf8Bytes, WebSocket, WebSocketUpgrade, close_code};
+use axum::extract::{Multipart, Path, State};
+use axum::http::header;
 use axum::response::sse::{Event, KeepAlive};
-use axum::response::{IntoResponse, Sse};
+use axum::response::{IntoResponse, Response, Sse};
 use bytes::Bytes;
 use futures::Stream;
 use std::time::Duration;
@@ -26,18 +41,233 @@ use tokio::sync::broadcast::error::RecvError;
 use tokio::time;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
-use tracing::{debug, warn};
+use tracing::{debug, error, warn};
 use uuid::Uuid;
 
 pub async fn handle_send_message(
     State(messages): State<MessageService>,
+    State(typing): State<TypingService>,
     user: CurrentUser,
     ValidatedJson(payload): ValidatedJson<SendMessageRequest>,
 ) -> AppResponse<Json<MessageResponse>> {
     let response_msg = messages.send_message(payload, user.subject).await?;
+    typing.message_sent(user.subject, response_msg.chat_room_id).await;
     Ok(Json(response_msg))
 }
 
+pub async fn handle_edit_message(
+    State(messages): State<MessageService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<EditMessageRequest>,
+) -> AppResponse<Json<MessageResponse>> {
+    let message = messages.edit_message(user.subject, room_id, message_id, payload).await?;
+    Ok(Json(message))
+}
+
+pub async fn handle_delete_message(
+    State(messages): State<MessageService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<()> {
+    messages.delete_message(user.subject, room_id, message_id).await?;
+    Ok(())
+}
+
+pub async fn handle_forward_message(
+    State(forwards): State<ForwardService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<ForwardMessageRequest>,
+) -> AppResponse<Json<Vec<ForwardOutcome>>> {
+    let outcomes = forwards.forward_message(user.subject, room_id, message_id, payload.targets).await?;
+    Ok(Json(outcomes))
+}
+
+pub async fn handle_schedule_message(
+    State(schedules): State<ScheduleService>,
+    user: CurrentUser,
+    ValidatedJson(payload): ValidatedJson<ScheduleMessageRequest>,
+) -> AppResponse<Json<ScheduledMessageResponse>> {
+    let scheduled = schedules.schedule_message(user.subject, payload).await?;
+    Ok(Json(scheduled))
+}
+
+pub async fn handle_list_scheduled_messages(
+    State(schedules): State<ScheduleService>,
+    user: CurrentUser,
+    ValidatedQuery(params): ValidatedQuery<ScheduledMessagesQuery>,
+) -> AppResponse<Json<Vec<ScheduledMessageResponse>>> {
+    let scheduled = schedules.scheduled_messages(user.subject, params.room_id).await?;
+    Ok(Json(scheduled))
+}
+
+pub async fn handle_cancel_scheduled_message(
+    State(schedules): State<ScheduleService>,
+    user: CurrentUser,
+    Path(scheduled_message_id): Path<Uuid>,
+) -> AppResponse<()> {
+    schedules.cancel_scheduled_message(user.subject, scheduled_message_id).await?;
+    Ok(())
+}
+
+pub async fn handle_add_reaction(
+    State(messages): State<MessageService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<ReactionRequest>,
+) -> AppResponse<()> {
+    messages.add_reaction(user.subject, room_id, message_id, payload.emoji).await?;
+    Ok(())
+}
+
+/// The emoji travels in the query string rather than a body: `DELETE` bodies are dropped by
+/// enough proxies that relying on one would be asking for reactions that cannot be removed.
+pub async fn handle_remove_reaction(
+    State(messages): State<MessageService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedQuery(params): ValidatedQuery<ReactionRequest>,
+) -> AppResponse<()> {
+    messages.remove_reaction(user.subject, room_id, message_id, params.emoji).await?;
+    Ok(())
+}
+
+pub async fn handle_vote_poll(
+    State(polls): State<PollService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<PollVoteRequest>,
+) -> AppResponse<Json<PollResultsResponse>> {
+    let results = polls.vote(user.subject, room_id, message_id, payload.options).await?;
+    Ok(Json(results))
+}
+
+pub async fn handle_retract_poll_vote(
+    State(polls): State<PollService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Json<PollResultsResponse>> {
+    let results = polls.retract_vote(user.subject, room_id, message_id).await?;
+    Ok(Json(results))
+}
+
+pub async fn handle_close_poll(
+    State(polls): State<PollService>,
+    user: CurrentUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Json<MessageResponse>> {
+    let message = polls.close_poll(user.subject, room_id, message_id).await?;
+    Ok(Json(message))
+}
+
+pub async fn handle_search_messages(
+    State(search): State<SearchService>,
+    user: CurrentUser,
+    ValidatedQuery(params): ValidatedQuery<MessageSearchQuery>,
+) -> AppResponse<Json<CursorResults<MessageSearchHitResponse>>> {
+    let cursor: MessageSearchCursor = decode_cursor(params.cursor.clone()).map_err(|_| AppError::Validation("Invalid Cursor-Parameters.".to_string()))?;
+    let hits = search.search_messages(user.subject, params.filter(), cursor, params.limit.get()).await?;
+    Ok(Json(hits))
+}
+
+pub async fn handle_mentions(
+    State(mentions): State<MentionService>,
+    user: CurrentUser,
+    ValidatedQuery(params): ValidatedQuery<MentionFeedQuery>,
+) -> AppResponse<Json<CursorResults<MessageResponse>>> {
+    let cursor: MentionCursor = decode_cursor(params.cursor).map_err(|_| AppError::Validation("Invalid Cursor-Parameters.".to_string()))?;
+    let page = mentions.mentions(user.subject, cursor, params.limit.get()).await?;
+    Ok(Json(page))
+}
+
+pub async fn handle_typing(
+    State(typing): State<TypingService>,
+    user: CurrentUser,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<TypingRequest>,
+) -> AppResponse<()> {
+    typing.set_typing(user.subject, room_id, payload.active).await?;
+    Ok(())
+}
+
+pub async fn handle_upload_media(
+    State(media): State<MediaService>,
+    user: CurrentUser,
+    Path(room_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> AppResponse<Json<MediaUploadResponse>> {
+    // The part's own `Content-Type` is ignored along with its file name: the service types the
+    // upload by its bytes.
+    let mut content: Option<Bytes> = None;
+    loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) => {
+                if field.name() == Some("file") {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|_| AppError::Validation("Error reading the upload byte stream.".to_string()))?;
+                    content = Some(data);
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                error!(error = %err, "Bad media upload");
+                return Err(AppError::Validation("Error reading the upload byte stream.".to_string()));
+            }
+        }
+    }
+
+    let content = content.ok_or_else(|| AppError::Validation("Required field 'file' not found in the upload.".to_string()))?;
+    let response = media.upload_media(user.subject, room_id, content).await?;
+    Ok(Json(response))
+}
+
+pub async fn handle_download_media(
+    State(media): State<MediaService>,
+    user: CurrentUser,
+    Path((room_id, media_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Response> {
+    let media = media.download_media(user.subject, room_id, media_id).await?;
+    // `nosniff` holds browsers to the sniffed type, so a file cannot be reinterpreted as a page.
+    let headers = [
+        (header::CONTENT_TYPE, media.mime_type),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+    ];
+    Ok((headers, media.content).into_response())
+}
+
+pub async fn handle_reserve_media_upload(
+    State(media): State<MediaService>,
+    user: CurrentUser,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<MediaUploadUrlRequest>,
+) -> AppResponse<Json<MediaUploadUrlResponse>> {
+    let reservation = media.reserve_upload(user.subject, room_id, payload).await?;
+    Ok(Json(reservation))
+}
+
+pub async fn handle_complete_media_upload(
+    State(media): State<MediaService>,
+    user: CurrentUser,
+    Path((room_id, media_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Json<MediaUploadResponse>> {
+    let upload = media.complete_upload(user.subject, room_id, media_id).await?;
+    Ok(Json(upload))
+}
+
+pub async fn handle_media_download_url(
+    State(media): State<MediaService>,
+    user: CurrentUser,
+    Path((room_id, media_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Json<MediaDownloadUrlResponse>> {
+    let url = media.download_url(user.subject, room_id, media_id).await?;
+    Ok(Json(url))
+}
+
 /// Build the live notification stream wire format.
 fn notification_to_sse(notification: &Notification) -> Event {
     Event::default().data(serde_json::to_string(notification).unwrap_or_default())
@@ -46,6 +276,7 @@ fn notification_to_sse(notification: &Notification) -> Event {
 pub async fn stream_server_events(
     user: CurrentUser,
     State(notifications): State<NotificationService>,
+    State(presence): State<PresenceService>,
     ValidatedQuery(params): ValidatedQuery<StreamHandshakeQuery>,
 ) -> Sse<impl Stream<Item = Result<Event, BroadcastStreamRecvError>>> {
     use futures::StreamExt;
@@ -57,6 +288,7 @@ pub async fn stream_server_events(
     // buffered and not lost (subscribe-then-replay ordering).
     let receiver = notifications.subscribe(user_id).await;
     let guard: ConnectionGuard = notifications.connection_guard(user_id);
+    let present = presence.track(user_id);
 
     let (replay, high_water) = notifications.resolve_handshake(&user_id, params.last_seq).await;
 
@@ -64,6 +296,7 @@ pub async fn stream_server_events(
 
     let live_stream = BroadcastStream::new(receiver).filter_map(move |result| {
         let _moved_guard = &guard; // tie the guard's lifetime to the live stream
+        let _moved_presence = &present; // the user stays present for exactly as long
         async move {
             match result {
                 Ok(event) => {
@@ -86,33 +319,36 @@ pub async fn stream_server_events(
     // Ends the stream when the server starts shutting down. Without this the response body never
     // completes, and axum's graceful shutdown waits on this connection forever — see
     // `NotificationService::cancelled`. Ending the stream drops it, which drops the
-    // `ConnectionGuard` tied into `live_stream` below, so the usual unsubscribe still runs.
-    let stream = replay_stream
-        .chain(live_stream)
-        .take_until(notifications.cancelled());
+    // `ConnectionGuard` tied into `live_stream` below, so the usual unsubscribe still runs (and the
+    // `PresenceGuard` next to it takes the user offline).
+    let stream = replay_stream.chain(live_stream).take_until(notifications.cancelled());
 
-    Sse::new(stream)
-        .keep_alive(
-            KeepAlive::new()
-                .interval(Duration::from_secs(5))
-                .text("live-connection-heartbeat")
-        )
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(5)).text("live-connection-heartbeat"))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn websocket_server_events(
     websocket: WebSocketUpgrade,
     user: CurrentUser,
     State(notifications): State<NotificationService>,
+    State(messages): State<MessageService>,
+    State(rooms): State<RoomService>,
+    State(typing): State<TypingService>,
+    State(presence): State<PresenceService>,
     ValidatedQuery(params): ValidatedQuery<StreamHandshakeQuery>,
 ) -> impl IntoResponse {
     // Bound out of the token so the upgrade closure captures a `Copy` id, not the whole token.
     let user_id = user.subject;
+    let commands = SocketCommands::new(messages, rooms, notifications.clone(), typing);
     websocket
         .on_failed_upgrade(|error| warn!("Error upgrading websocket: {}", error))
-        .on_upgrade(move |socket| handle_socket(socket, notifications, user_id, params.last_seq))
+        .on_upgrade(move |socket| async move {
+            let _present = presence.track(user_id);
+            handle_socket(socket, notifications, commands, user_id, params.last_seq).await
+        })
 }
 
-async fn handle_socket(mut socket: WebSocket, notifications: NotificationService, user_id: Uuid, last_seq: Option<u64>) {
+async fn handle_socket(mut socket: WebSocket, notifications: NotificationService, commands: SocketCommands, user_id: Uuid, last_seq: Option<u64>) {
     let mut broadcast_events = notifications.subscribe(user_id).await;
     let _guard = notifications.connection_guard(user_id);
 
@@ -212,6 +448,18 @@ async fn handle_socket(mut socket: WebSocket, notifications: NotificationService
                         debug!("Client has sent Websocket-Pong");
                         last_pong_received = time::Instant::now();
                     }
+                    Some(Ok(Message::Text(text))) => {
+                        last_pong_received = time::Instant::now();
+                        // Commands run inline: events arriving meanwhile wait in the receiver, so the
+                        // reply to a command always reaches the client before the events it caused.
+                        if let Some(reply) = commands.handle(user_id, &text, &mut high_water).await {
+                            let json = serde_json::to_string(&reply).unwrap_or_default();
+                            if socket.send(Message::text(json)).await.is_err() {
+                                debug!(%user_id, "Failed to send command reply to client, closing");
+                                break;
+                            }
+                        }
+                    }
                     Some(Ok(_)) => {
                         last_pong_received = time::Instant::now();
                     }
@@ -225,10 +473,7 @@ async fn handle_socket(mut socket: WebSocket, notifications: NotificationService
 /// to learn the sequence its snapshot corresponds to, then persists it as the baseline for future
 /// short reconnects. The REST-sync itself opens its live stream **without** a `last_seq` parameter
 /// (fresh connection, no replay) — this endpoint only seeds the stored cursor.
-pub async fn get_notification_cursor(
-    State(notifications): State<NotificationService>,
-    user: CurrentUser
-) -> AppResponse<Json<NotificationCursorResponse>> {
+pub async fn get_notification_cursor(State(notifications): State<NotificationService>, user: CurrentUser) -> AppResponse<Json<NotificationCursorResponse>> {
     let seq = notifications.current_sequence(&user.subject).await?;
     Ok(Json(NotificationCursorResponse { seq }))
 }
@@ -6,6 +6,10 @@ pub mod request;
 pub mod response;
 pub mod routes;
 pub mod service;
+mod socket;
 
 pub use repository::ChatRepository;
-pub use service::{MessageService, NotificationService};
+pub use service::{
+    ForwardService, MediaService, MentionService, MessageService, NotificationService, PollService, RetentionService, ScheduleService, SearchService,
+    TypingService,
+};
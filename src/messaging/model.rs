@@ -1,9 +1,15 @@
 //! Types the messaging domain shares across boundaries.
 //!
 //! [`MsgType`] is simultaneously a Postgres enum value, a request field and a response field, so it
-//! belongs to none of `entity.rs`, `request.rs` or `response.rs` alone.
+//! belongs to none of `entity.rs`, `request.rs` or `response.rs` alone. The search filter and cursor
+//! travel from the handler down to the repository, so they live here as well, as does the content
+//! sniffing that decides what an upload is and the parsing that finds mentions in a text.
 
+use crate::messaging::entity::MentionJson;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// The kind of a message, stored in `chat_message.msg_type`.
 ///
@@ -17,4 +23,270 @@ pub enum MsgType {
     Media,
     RoomChange,
     Reply,
+    Poll,
+}
+
+/// What a message search looks for. Every field but `text` narrows the search when set; `from` is
+/// inclusive and `to` exclusive.
+#[derive(Debug, Clone)]
+pub struct MessageSearchFilter {
+    /// Search terms in web-search syntax: `"exact phrase"`, `or`, `-excluded`.
+    pub text: String,
+    pub room_id: Option<Uuid>,
+    pub sender_id: Option<Uuid>,
+    pub msg_type: Option<MsgType>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Keyset cursor for message search. Hits are ordered by `(rank, created_at, message_id) DESC`; the
+/// rank is recomputed identically for the same query, so it is as stable a key as the other two.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchCursor {
+    pub last_rank: Option<f32>,
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_message_id: Option<Uuid>,
+}
+
+/// Keyset cursor for the mentions feed, ordered by `(created_at, message_id) DESC`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionCursor {
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_message_id: Option<Uuid>,
+}
+
+/// Length of a hyphenated user id, the only form a mention accepts.
+const MENTION_ID_LENGTH: usize = 36;
+
+/// The mentions in `text`, in order: `@` followed by a hyphenated user id, and `@room`.
+///
+/// An `@` directly after a letter or digit is not a mention, so an e-mail address never becomes
+/// one; neither is `@room` running on into a longer word. Whether the users exist is not this
+/// function's business.
+pub fn parse_mentions(text: &str) -> Vec<MentionJson> {
+    let mut mentions = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().enumerate().peekable();
+    while let Some((offset, (at, char))) = chars.next() {
+        let starts_mention = char == '@' && !previous.is_some_and(char::is_alphanumeric);
+        previous = Some(char);
+        if !starts_mention {
+            continue;
+        }
+
+        let rest = &text[at + 1..];
+        let mention = if let Some(id) = rest.get(..MENTION_ID_LENGTH)
+            && let Ok(user_id) = Uuid::try_parse(id)
+        {
+            MentionJson::User {
+                user_id,
+                offset: offset as u32,
+                length: (MENTION_ID_LENGTH + 1) as u32,
+            }
+        } else if let Some(after) = rest.strip_prefix("room")
+            && !after.starts_with(|next: char| next.is_alphanumeric() || next == '_')
+        {
+            MentionJson::Room {
+                offset: offset as u32,
+                length: 5,
+            }
+        } else {
+            continue;
+        };
+
+        // Both forms are ASCII, so their length in characters is their length in bytes.
+        let skip = match &mention {
+            MentionJson::User { length, .. } | MentionJson::Room { length, .. } => *length as usize - 1,
+        };
+        for _ in 0..skip {
+            previous = chars.next().map(|(_, (_, char))| char);
+        }
+        mentions.push(mention);
+    }
+    mentions
+}
+
+/// What an uploaded file is, by its content rather than by what the client claimed.
+///
+/// Everything not recognised here is rejected. SVG and HTML are missing on purpose: served from
+/// this origin they are documents that can run script, not media.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+    Document,
+}
+
+impl MediaKind {
+    /// The `mediaType` of a message that attaches an upload of this kind.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+            MediaKind::Document => "document",
+        }
+    }
+
+    /// The kind of a MIME type that [`sniff_media`] produced.
+    pub fn of_mime_type(mime_type: &str) -> MediaKind {
+        match mime_type.split('/').next() {
+            Some("image") => MediaKind::Image,
+            Some("video") => MediaKind::Video,
+            Some("audio") => MediaKind::Audio,
+            _ => MediaKind::Document,
+        }
+    }
+
+    /// The kind of a MIME type an upload may announce, or `None` for one [`sniff_media`] would never
+    /// produce.
+    pub fn of_accepted_mime_type(mime_type: &str) -> Option<MediaKind> {
+        ACCEPTED_MEDIA.iter().find(|(accepted, _)| *accepted == mime_type).map(|(_, kind)| *kind)
+    }
+
+    /// The largest upload of this kind, in bytes.
+    pub const fn max_size(&self) -> usize {
+        match self {
+            MediaKind::Image => 10 * 1024 * 1024,
+            MediaKind::Video => 50 * 1024 * 1024,
+            MediaKind::Audio | MediaKind::Document => 20 * 1024 * 1024,
+        }
+    }
+}
+
+/// Every MIME type [`sniff_media`] produces.
+const ACCEPTED_MEDIA: [(&str, MediaKind); 12] = [
+    ("image/jpeg", MediaKind::Image),
+    ("image/png", MediaKind::Image),
+    ("image/gif", MediaKind::Image),
+    ("image/webp", MediaKind::Image),
+    ("video/mp4", MediaKind::Video),
+    ("video/quicktime", MediaKind::Video),
+    ("video/webm", MediaKind::Video),
+    ("audio/mpeg", MediaKind::Audio),
+    ("audio/mp4", MediaKind::Audio),
+    ("audio/ogg", MediaKind::Audio),
+    ("audio/wav", MediaKind::Audio),
+    ("application/pdf", MediaKind::Document),
+];
+
+/// How many leading bytes [`sniff_media`] looks at.
+pub const SNIFF_LENGTH: usize = 12;
+
+/// The kind and MIME type of `content`, read from its leading magic bytes, or `None` for a format
+/// uploads do not accept.
+pub fn sniff_media(content: &[u8]) -> Option<(MediaKind, &'static str)> {
+    let at = |offset: usize, magic: &[u8]| content.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        return Some((MediaKind::Image, "image/jpeg"));
+    }
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        return Some((MediaKind::Image, "image/png"));
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some((MediaKind::Image, "image/gif"));
+    }
+    if at(0, b"RIFF") && at(8, b"WEBP") {
+        return Some((MediaKind::Image, "image/webp"));
+    }
+    if at(0, b"RIFF") && at(8, b"WAVE") {
+        return Some((MediaKind::Audio, "audio/wav"));
+    }
+    if at(4, b"ftyp") {
+        // ISO base media: the major brand tells a QuickTime movie and an audio-only M4A apart from
+        // the MP4 every other brand is served as.
+        return Some(match content.get(8..12) {
+            Some(b"qt  ") => (MediaKind::Video, "video/quicktime"),
+            Some(b"M4A ") => (MediaKind::Audio, "audio/mp4"),
+            _ => (MediaKind::Video, "video/mp4"),
+        });
+    }
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some((MediaKind::Video, "video/webm"));
+    }
+    if at(0, b"OggS") {
+        return Some((MediaKind::Audio, "audio/ogg"));
+    }
+    if at(0, b"ID3") || at(0, &[0xFF, 0xFB]) || at(0, &[0xFF, 0xF3]) || at(0, &[0xFF, 0xF2]) {
+        return Some((MediaKind::Audio, "audio/mpeg"));
+    }
+    if at(0, b"%PDF-") {
+        return Some((MediaKind::Document, "application/pdf"));
+    }
+    None
+}
+
+/// Where room members download an upload from.
+pub fn media_download_path(room_id: &Uuid, media_id: &Uuid) -> String {
+    format!("/api/v1/rooms/{room_id}/media/{media_id}")
+}
+
+/// A stored upload on its way out: the bytes, and the type they were sniffed as when they came in.
+#[derive(Debug)]
+pub struct MediaContent {
+    pub mime_type: String,
+    pub content: Bytes,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_are_found_by_user_id_and_room() {
+        let id = Uuid::parse_str("55555555-5555-4555-8555-555555555555").unwrap();
+        let text = format!("hé @{id}, see @room!");
+
+        assert_eq!(
+            parse_mentions(&text),
+            vec![
+                MentionJson::User {
+                    user_id: id,
+                    offset: 3,
+                    length: 37
+                },
+                MentionJson::Room { offset: 46, length: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn addresses_and_longer_words_are_not_mentions() {
+        assert!(parse_mentions("mail me at someone@room.example").is_empty());
+        assert!(parse_mentions("@roommate @room_1 @ @123").is_empty());
+        assert!(parse_mentions("@55555555-5555-4555-8555").is_empty());
+    }
+
+    #[test]
+    fn uploads_are_typed_by_their_content() {
+        assert_eq!(sniff_media(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]), Some((MediaKind::Image, "image/jpeg")));
+        assert_eq!(sniff_media(b"RIFF\x24\0\0\0WEBPVP8 "), Some((MediaKind::Image, "image/webp")));
+        assert_eq!(sniff_media(b"RIFF\x24\0\0\0WAVEfmt "), Some((MediaKind::Audio, "audio/wav")));
+        assert_eq!(sniff_media(b"\0\0\0\x18ftypmp42"), Some((MediaKind::Video, "video/mp4")));
+        assert_eq!(sniff_media(b"\0\0\0\x14ftypqt  "), Some((MediaKind::Video, "video/quicktime")));
+        assert_eq!(sniff_media(b"\0\0\0\x20ftypM4A "), Some((MediaKind::Audio, "audio/mp4")));
+        assert_eq!(sniff_media(b"%PDF-1.7\n"), Some((MediaKind::Document, "application/pdf")));
+    }
+
+    #[test]
+    fn unknown_and_scriptable_formats_are_rejected() {
+        assert_eq!(sniff_media(b""), None);
+        assert_eq!(sniff_media(b"RIFF"), None);
+        assert_eq!(sniff_media(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), None);
+        assert_eq!(sniff_media(b"<!DOCTYPE html>"), None);
+    }
+
+    #[test]
+    fn every_sniffed_type_may_be_announced() {
+        let samples: [&[u8]; 5] = [b"\x89PNG\r\n\x1a\n", b"GIF89a", b"\x1a\x45\xdf\xa3", b"OggS", b"ID3\x04"];
+        for sample in samples {
+            let (kind, mime_type) = sniff_media(sample).unwrap();
+            assert_eq!(MediaKind::of_accepted_mime_type(mime_type), Some(kind));
+        }
+        assert_eq!(MediaKind::of_accepted_mime_type("image/svg+xml"), None);
+    }
 }
@@ -1,10 +1,38 @@
 use crate::core::{Database, Repository};
-use crate::messaging::entity::{MessageBodyJson, MessageRow};
-use crate::messaging::model::MsgType;
+use crate::messaging::entity::{
+    ExpiredMessagesRow, MentionJson, MessageBodyJson, MessageRow, MessageSearchRow, PollVoteCountRow, ReactionCountRow, RoomMediaRow, ScheduledMessageRow,
+};
+use crate::messaging::model::{MentionCursor, MessageSearchCursor, MessageSearchFilter, MsgType};
 use chrono::{DateTime, Utc};
-use sqlx::{Error, Postgres};
+use sqlx::types::Json;
+use sqlx::{Error, PgConnection, Postgres, query_as};
 use uuid::Uuid;
 
+/// Every column of `chat_message` that [`MessageRow`] decodes.
+///
+/// Shared for the same reason as `user_columns!` in the users repository: the runtime-checked
+/// queries below must select exactly the set `MessageRow::from_row` expects, and a missing column
+/// only surfaces when the query runs.
+macro_rules! message_columns {
+    () => {
+        r#"
+            message_id,
+            chat_room_id,
+            sender_id,
+            msg_body,
+            msg_type,
+            created_at,
+            edited_at,
+            deleted_at,
+            thread_root_id,
+            reply_count,
+            last_reply_at,
+            client_message_id,
+            forwarded_from
+        "#
+    };
+}
+
 /// Chat message persistence.
 #[derive(Clone)]
 pub struct ChatRepository {
@@ -18,74 +46,428 @@ impl Repository for ChatRepository {
 }
 
 impl ChatRepository {
-    
-    pub async fn insert_message<'e, E>(&self, exec: E, message: &MessageRow) -> Result<(), Error>
+    /// Stores a message. Returns `false` when the sender already has a message under the same
+    /// `client_message_id`, in which case nothing was written.
+    pub async fn insert_message<'e, E>(&self, exec: E, message: &MessageRow) -> Result<bool, Error>
     where
         E: sqlx::Executor<'e, Database = Postgres>,
     {
-        sqlx::query!(
+        let result = sqlx::query(
             r#"
-            INSERT INTO chat_message (message_id, chat_room_id, sender_id, msg_body, msg_type, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO chat_message (message_id, chat_room_id, sender_id, msg_body, msg_type, created_at, thread_root_id, client_message_id, forwarded_from)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (sender_id, client_message_id) WHERE client_message_id IS NOT NULL DO NOTHING
             "#,
-            message.message_id,
-            message.chat_room_id,
-            message.sender_id,
-            message.msg_body.clone() as sqlx::types::Json<MessageBodyJson>,
-            message.msg_type.clone() as MsgType,
-            message.created_at
         )
+        .bind(message.message_id)
+        .bind(message.chat_room_id)
+        .bind(message.sender_id)
+        .bind(&message.msg_body)
+        .bind(message.msg_type)
+        .bind(message.created_at)
+        .bind(message.thread_root_id)
+        .bind(&message.client_message_id)
+        .bind(&message.forwarded_from)
         .execute(exec)
         .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    pub async fn fetch_messages(&self, room_id: Uuid, before: DateTime<Utc>) -> Result<Vec<MessageRow>, Error> {
-        let messages = sqlx::query_as!(
-            MessageRow,
+    /// The message `sender_id` stored under their own `client_message_id`, in whichever room.
+    pub async fn fetch_message_by_client_id(&self, sender_id: &Uuid, client_message_id: &str) -> Result<Option<MessageRow>, Error> {
+        let message = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
             r#"
-            SELECT
-                message_id,
-                chat_room_id,
-                sender_id,
-                msg_body AS "msg_body: sqlx::types::Json<MessageBodyJson>",
-                msg_type AS "msg_type: MsgType",
-                created_at
             FROM chat_message
-            WHERE chat_room_id = $1 AND created_at < $2
-            ORDER BY created_at DESC
-            LIMIT 25
-            "#,
-            room_id,
-            before
-        )
+            WHERE sender_id = $1 AND client_message_id = $2
+            "#
+        ))
+        .bind(sender_id)
+        .bind(client_message_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+        Ok(message)
+    }
+
+    /// Up to `limit` main-timeline messages before the `(created_at, message_id)` position, newest
+    /// first — or the newest of all without one. Everything created at or before `expired_until`
+    /// is left out.
+    pub async fn fetch_messages(
+        &self,
+        room_id: &Uuid,
+        before_created_at: Option<DateTime<Utc>>,
+        before_message_id: Option<Uuid>,
+        expired_until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<MessageRow>, Error> {
+        let messages = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#"
+            FROM chat_message
+            WHERE chat_room_id = $1
+              AND ($2::timestamptz IS NULL OR (created_at, message_id) < ($2, $3))
+              AND ($4::timestamptz IS NULL OR created_at > $4)
+              AND thread_root_id IS NULL
+            ORDER BY created_at DESC, message_id DESC
+            LIMIT $5
+            "#
+        ))
+        .bind(room_id)
+        .bind(before_created_at)
+        .bind(before_message_id)
+        .bind(expired_until)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(messages)
+    }
+
+    /// Up to `limit` main-timeline messages after the `(created_at, message_id)` position, oldest
+    /// first — or the oldest still visible without one. The counterpart of [`Self::fetch_messages`],
+    /// with the same `expired_until`.
+    pub async fn fetch_messages_after(
+        &self,
+        room_id: &Uuid,
+        after_created_at: Option<DateTime<Utc>>,
+        after_message_id: Option<Uuid>,
+        expired_until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<MessageRow>, Error> {
+        let messages = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#"
+            FROM chat_message
+            WHERE chat_room_id = $1
+              AND ($2::timestamptz IS NULL OR (created_at, message_id) > ($2, $3))
+              AND ($4::timestamptz IS NULL OR created_at > $4)
+              AND thread_root_id IS NULL
+            ORDER BY created_at ASC, message_id ASC
+            LIMIT $5
+            "#
+        ))
+        .bind(room_id)
+        .bind(after_created_at)
+        .bind(after_message_id)
+        .bind(expired_until)
+        .bind(limit)
         .fetch_all(self.db.pool())
         .await?;
         Ok(messages)
     }
 
     pub async fn fetch_message_by_id(&self, message_id: &Uuid, room_id: &Uuid) -> Result<MessageRow, Error> {
-        let message = sqlx::query_as!(
-            MessageRow,
+        let message = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
             r#"
-            SELECT
-                message_id,
-                chat_room_id,
-                sender_id,
-                msg_body AS "msg_body: sqlx::types::Json<MessageBodyJson>",
-                msg_type AS "msg_type: MsgType",
-                created_at
             FROM chat_message
             WHERE message_id = $1 AND chat_room_id = $2
-            "#,
-            message_id,
-            room_id
-        )
+            "#
+        ))
+        .bind(message_id)
+        .bind(room_id)
         .fetch_one(self.db.pool())
         .await?;
         Ok(message)
     }
 
+    /// [`Self::fetch_message_by_id`], locking the row until `tx` ends. What keeps a vote from
+    /// landing on a poll that is being closed at the same moment, and two reactions from counting
+    /// each other's emoji at once.
+    pub async fn lock_message(&self, tx: &mut PgConnection, message_id: &Uuid, room_id: &Uuid) -> Result<MessageRow, Error> {
+        let message = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#"
+            FROM chat_message
+            WHERE message_id = $1 AND chat_room_id = $2
+            FOR UPDATE
+            "#
+        ))
+        .bind(message_id)
+        .bind(room_id)
+        .fetch_one(tx)
+        .await?;
+        Ok(message)
+    }
+
+    /// One page of a thread, oldest first, strictly after the `(created_at, message_id)` position
+    /// of the cursor — a thread is read top-down, unlike the main timeline. Replies created at or
+    /// before `expired_until` are left out.
+    pub async fn fetch_thread(
+        &self,
+        room_id: &Uuid,
+        root_id: &Uuid,
+        after_created_at: Option<DateTime<Utc>>,
+        after_message_id: Option<Uuid>,
+        expired_until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<MessageRow>, Error> {
+        let messages = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#"
+            FROM chat_message
+            WHERE chat_room_id = $1 AND thread_root_id = $2
+              AND (
+                  $3::timestamptz IS NULL
+                  OR created_at > $3
+                  OR (created_at = $3 AND message_id > $4)
+              )
+              AND ($5::timestamptz IS NULL OR created_at > $5)
+            ORDER BY created_at ASC, message_id ASC
+            LIMIT $6
+            "#
+        ))
+        .bind(room_id)
+        .bind(root_id)
+        .bind(after_created_at)
+        .bind(after_message_id)
+        .bind(expired_until)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(messages)
+    }
+
+    /// One page of full-text hits for `filter`, best match first, over every room `user_id` is
+    /// currently in. Deleted messages and those past their room's retention are never found,
+    /// `default_retention_secs` standing in for rooms without a setting of their own.
+    ///
+    /// The snippets are computed in the outer query, after the `LIMIT`: `ts_headline` re-parses
+    /// the whole text, which is too expensive to spend on rows that are about to be discarded.
+    /// The text is HTML-escaped before it goes in, so the `<mark>` markers are the only markup
+    /// in a snippet.
+    pub async fn search_messages(
+        &self,
+        user_id: &Uuid,
+        filter: &MessageSearchFilter,
+        default_retention_secs: i32,
+        cursor: MessageSearchCursor,
+        limit: i64,
+    ) -> Result<Vec<MessageSearchRow>, Error> {
+        let hits = query_as::<_, MessageSearchRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#",
+                ts_headline(
+                    'simple',
+                    replace(
+                        replace(
+                            replace(chat_message_search_text(msg_type, msg_body), '&', '&amp;'),
+                            '<', '&lt;'
+                        ),
+                        '>', '&gt;'
+                    ),
+                    search.query,
+                    'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MinWords=5, MaxWords=20'
+                ) AS snippet,
+                rank
+            FROM (
+                SELECT *
+                FROM (
+                    SELECT message.*, ts_rank(message.search_vector, search.query) AS rank
+                    FROM chat_message message
+                    JOIN chat_room_participant participant ON participant.room_id = message.chat_room_id AND participant.user_id = $1
+                    JOIN chat_room room ON room.id = message.chat_room_id
+                    CROSS JOIN (SELECT websearch_to_tsquery('simple', $2) AS query) search
+                    WHERE message.search_vector @@ search.query
+                      AND message.deleted_at IS NULL
+                      AND ($3::uuid IS NULL OR message.chat_room_id = $3)
+                      AND ($4::uuid IS NULL OR message.sender_id = $4)
+                      AND ($5::msg_type IS NULL OR message.msg_type = $5)
+                      AND ($6::timestamptz IS NULL OR message.created_at >= $6)
+                      AND ($7::timestamptz IS NULL OR message.created_at < $7)
+                      AND (
+                          COALESCE(room.retention_secs, $8) = 0
+                          OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $8))
+                      )
+                ) hit
+                WHERE $9::real IS NULL OR (rank, created_at, message_id) < ($9, $10, $11)
+                ORDER BY rank DESC, created_at DESC, message_id DESC
+                LIMIT $12
+            ) page
+            CROSS JOIN (SELECT websearch_to_tsquery('simple', $2) AS query) search
+            ORDER BY rank DESC, created_at DESC, message_id DESC
+            "#
+        ))
+        .bind(user_id)
+        .bind(&filter.text)
+        .bind(filter.room_id)
+        .bind(filter.sender_id)
+        .bind(filter.msg_type)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(default_retention_secs)
+        .bind(cursor.last_rank)
+        .bind(cursor.last_created_at)
+        .bind(cursor.last_message_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(hits)
+    }
+
+    /// Indexes the mentions of `message` for the mentions feed; an `@room` mention is stored with
+    /// no user.
+    pub async fn insert_mentions<'e, E>(&self, exec: E, message: &MessageRow, mentions: &[MentionJson]) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        if mentions.is_empty() {
+            return Ok(());
+        }
+        let mentioned: Vec<Option<Uuid>> = mentions
+            .iter()
+            .map(|mention| match mention {
+                MentionJson::User { user_id, .. } => Some(*user_id),
+                MentionJson::Room { .. } => None,
+            })
+            .collect();
+        sqlx::query(
+            r#"
+            INSERT INTO message_mention (message_id, chat_room_id, user_id, created_at)
+            SELECT $1, $2, mentioned, $4
+            FROM UNNEST($3::uuid[]) AS mentioned
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(message.message_id)
+        .bind(message.chat_room_id)
+        .bind(mentioned)
+        .bind(message.created_at)
+        .execute(exec)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_mentions<'e, E>(&self, exec: E, message_id: &Uuid) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM message_mention WHERE message_id = $1")
+            .bind(message_id)
+            .execute(exec)
+            .await?;
+        Ok(())
+    }
+
+    /// Messages mentioning `user_id` by name or through `@room`, newest first, in the rooms they
+    /// are in now. Their own messages, deleted ones and expired ones are left out.
+    pub async fn fetch_mentions(&self, user_id: &Uuid, default_retention_secs: i32, cursor: MentionCursor, limit: i64) -> Result<Vec<MessageRow>, Error> {
+        let messages = query_as::<_, MessageRow>(concat!(
+            "SELECT ",
+            message_columns!(),
+            r#"
+            FROM chat_message message
+            WHERE message.message_id IN (
+                    SELECT mention.message_id
+                    FROM message_mention mention
+                    JOIN chat_room_participant participant ON participant.room_id = mention.chat_room_id AND participant.user_id = $1
+                    WHERE mention.user_id = $1 OR mention.user_id IS NULL
+                )
+              AND message.sender_id <> $1
+              AND message.deleted_at IS NULL
+              AND (
+                  SELECT COALESCE(room.retention_secs, $2) = 0
+                      OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $2))
+                  FROM chat_room room
+                  WHERE room.id = message.chat_room_id
+              )
+              AND ($3::timestamptz IS NULL OR (message.created_at, message.message_id) < ($3, $4))
+            ORDER BY message.created_at DESC, message.message_id DESC
+            LIMIT $5
+            "#
+        ))
+        .bind(user_id)
+        .bind(default_retention_secs)
+        .bind(cursor.last_created_at)
+        .bind(cursor.last_message_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(messages)
+    }
+
+    /// Counts a new reply on its thread root. Returns the root's counter after the increment.
+    pub async fn record_thread_reply<'e, E>(&self, exec: E, root_id: &Uuid, replied_at: DateTime<Utc>) -> Result<i32, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let reply_count = sqlx::query_scalar(
+            r#"
+            UPDATE chat_message
+            SET reply_count = reply_count + 1,
+                last_reply_at = GREATEST(last_reply_at, $2)
+            WHERE message_id = $1
+            RETURNING reply_count
+            "#,
+        )
+        .bind(root_id)
+        .bind(replied_at)
+        .fetch_one(exec)
+        .await?;
+        Ok(reply_count)
+    }
+
+    /// Recounts a thread root's replies and its latest reply time from the replies that are not
+    /// deleted, for when one of them is.
+    pub async fn recount_thread_replies<'e, E>(&self, exec: E, root_id: &Uuid) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE chat_message AS root
+            SET reply_count = replies.reply_count,
+                last_reply_at = replies.last_reply_at
+            FROM (
+                SELECT COUNT(*)::INTEGER AS reply_count, MAX(created_at) AS last_reply_at
+                FROM chat_message
+                WHERE thread_root_id = $1 AND deleted_at IS NULL
+            ) AS replies
+            WHERE root.message_id = $1
+            "#,
+        )
+        .bind(root_id)
+        .execute(exec)
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the body of an existing message and stamps `edited_at`.
+    pub async fn update_message_body<'e, E>(&self, exec: E, message_id: &Uuid, msg_body: &MessageBodyJson, edited_at: DateTime<Utc>) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query("UPDATE chat_message SET msg_body = $2, edited_at = $3 WHERE message_id = $1")
+            .bind(message_id)
+            .bind(Json(msg_body))
+            .bind(edited_at)
+            .execute(exec)
+            .await?;
+        Ok(())
+    }
+
+    /// Turns a message into a tombstone: the body is wiped, `msg_type` becomes `Text` so it keeps
+    /// agreeing with the wiped body, and `deleted_at` is stamped.
+    pub async fn tombstone_message<'e, E>(&self, exec: E, message_id: &Uuid, deleted_at: DateTime<Utc>) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query("UPDATE chat_message SET msg_body = $2, msg_type = $3, deleted_at = $4 WHERE message_id = $1")
+            .bind(message_id)
+            .bind(Json(MessageRow::tombstone_body()))
+            .bind(MsgType::Text)
+            .bind(deleted_at)
+            .execute(exec)
+            .await?;
+        Ok(())
+    }
+
     pub async fn delete_room_messages<'e, E>(&self, exec: E, room_id: &Uuid) -> Result<(), Error>
     where
         E: sqlx::Executor<'e, Database = Postgres>,
@@ -93,4 +475,475 @@ impl ChatRepository {
         sqlx::query!("DELETE FROM chat_message WHERE chat_room_id = $1", room_id).execute(exec).await?;
         Ok(())
     }
+
+    /// Deletes up to `limit` messages that outlived their room's retention, `default_secs` standing
+    /// in for rooms without a setting of their own. Reactions and thread replies go with their
+    /// message through `ON DELETE CASCADE`, beyond the limit. The uploads attached by any of them
+    /// lose their `room_media` row in the same statement.
+    ///
+    /// `SKIP LOCKED` lets several instances reap side by side without waiting on each other's
+    /// batches.
+    pub async fn delete_expired_messages(&self, conn: &mut PgConnection, default_secs: i32, limit: i64) -> Result<Vec<ExpiredMessagesRow>, Error> {
+        let expired = query_as::<_, ExpiredMessagesRow>(
+            r#"
+            WITH expired AS (
+                SELECT message.message_id
+                FROM chat_message message
+                JOIN chat_room room ON room.id = message.chat_room_id
+                WHERE COALESCE(room.retention_secs, $1) > 0
+                  AND message.created_at <= NOW() - make_interval(secs => COALESCE(room.retention_secs, $1))
+                LIMIT $2
+                FOR UPDATE OF message SKIP LOCKED
+            ),
+            deleted AS (
+                DELETE FROM chat_message message
+                USING expired
+                WHERE message.message_id = expired.message_id
+                RETURNING message.chat_room_id, message.created_at
+            ),
+            -- Every statement of the query reads the same snapshot, so the replies the cascade
+            -- is about to take are still visible here.
+            media AS (
+                DELETE FROM room_media media
+                USING chat_message message
+                WHERE media.message_id = message.message_id
+                  AND (
+                      message.message_id IN (SELECT message_id FROM expired)
+                      OR message.thread_root_id IN (SELECT message_id FROM expired)
+                  )
+                RETURNING media.room_id, media.object_key
+            )
+            SELECT
+                chat_room_id,
+                MAX(created_at) AS until,
+                COUNT(*) AS count,
+                ARRAY(SELECT media.object_key FROM media WHERE media.room_id = deleted.chat_room_id) AS media_keys
+            FROM deleted
+            GROUP BY chat_room_id
+            "#,
+        )
+        .bind(default_secs)
+        .bind(limit)
+        .fetch_all(conn)
+        .await?;
+        Ok(expired)
+    }
+
+    /// Adds a reaction. Returns `false` when the user had already reacted with this emoji, so the
+    /// caller can skip broadcasting a change that did not happen.
+    pub async fn insert_reaction<'e, E>(&self, exec: E, message_id: &Uuid, user_id: &Uuid, emoji: &str) -> Result<bool, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO message_reaction (message_id, user_id, emoji, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(exec)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes a reaction. Returns `false` when there was nothing to remove.
+    pub async fn delete_reaction<'e, E>(&self, exec: E, message_id: &Uuid, user_id: &Uuid, emoji: &str) -> Result<bool, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let result = sqlx::query("DELETE FROM message_reaction WHERE message_id = $1 AND user_id = $2 AND emoji = $3")
+            .bind(message_id)
+            .bind(user_id)
+            .bind(emoji)
+            .execute(exec)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn count_reactions<'e, E>(&self, exec: E, message_id: &Uuid, emoji: &str) -> Result<i64, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM message_reaction WHERE message_id = $1 AND emoji = $2")
+            .bind(message_id)
+            .bind(emoji)
+            .fetch_one(exec)
+            .await?;
+        Ok(count)
+    }
+
+    /// Removes every reaction on a message, for when it is deleted.
+    pub async fn delete_reactions<'e, E>(&self, exec: E, message_id: &Uuid) -> Result<(), Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM message_reaction WHERE message_id = $1")
+            .bind(message_id)
+            .execute(exec)
+            .await?;
+        Ok(())
+    }
+
+    /// Reaction counts for a page of messages, one row per (message, emoji).
+    ///
+    /// Ordered by when each emoji was first used on the message, so a client rendering the list
+    /// does not see reactions jump around as counts change.
+    pub async fn fetch_reaction_counts(&self, message_ids: &[Uuid], viewer_id: &Uuid) -> Result<Vec<ReactionCountRow>, Error> {
+        let rows = query_as::<_, ReactionCountRow>(
+            r#"
+            SELECT
+                message_id,
+                emoji,
+                COUNT(*) AS count,
+                BOOL_OR(user_id = $2) AS reacted_by_me
+            FROM message_reaction
+            WHERE message_id = ANY($1)
+            GROUP BY message_id, emoji
+            ORDER BY message_id, MIN(created_at)
+            "#,
+        )
+        .bind(message_ids)
+        .bind(viewer_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    /// Replaces a user's vote on a poll with `option_indexes`.
+    pub async fn replace_poll_vote(&self, tx: &mut PgConnection, message_id: &Uuid, user_id: &Uuid, option_indexes: &[i16]) -> Result<(), Error> {
+        self.delete_poll_vote(&mut *tx, message_id, user_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO poll_vote (message_id, user_id, option_index, created_at)
+            SELECT $1, $2, option_index, NOW()
+            FROM UNNEST($3::SMALLINT[]) AS option_index
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(option_indexes)
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a user's vote on a poll. Returns `false` when they had not voted.
+    pub async fn delete_poll_vote<'e, E>(&self, exec: E, message_id: &Uuid, user_id: &Uuid) -> Result<bool, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let result = sqlx::query("DELETE FROM poll_vote WHERE message_id = $1 AND user_id = $2")
+            .bind(message_id)
+            .bind(user_id)
+            .execute(exec)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Rewrites a poll's body — to close it — without marking the message edited.
+    pub async fn update_poll_body(&self, tx: &mut PgConnection, message_id: &Uuid, msg_body: &MessageBodyJson) -> Result<(), Error> {
+        sqlx::query("UPDATE chat_message SET msg_body = $2 WHERE message_id = $1")
+            .bind(message_id)
+            .bind(Json(msg_body))
+            .execute(tx)
+            .await?;
+        Ok(())
+    }
+
+    /// Vote counts for a page of polls: per option, and — in the row without an option — per poll,
+    /// counting each voter once however many options they picked.
+    pub async fn fetch_poll_vote_counts(&self, message_ids: &[Uuid], viewer_id: &Uuid) -> Result<Vec<PollVoteCountRow>, Error> {
+        let rows = query_as::<_, PollVoteCountRow>(
+            r#"
+            SELECT
+                message_id,
+                option_index,
+                COUNT(DISTINCT user_id) AS count,
+                BOOL_OR(user_id = $2) AS voted_by_me
+            FROM poll_vote
+            WHERE message_id = ANY($1)
+            GROUP BY GROUPING SETS ((message_id, option_index), (message_id))
+            "#,
+        )
+        .bind(message_ids)
+        .bind(viewer_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    pub async fn insert_media(&self, media: &RoomMediaRow) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO room_media (media_id, room_id, object_key, uploaded_by, mime_type, size_bytes, created_at, completed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(media.media_id)
+        .bind(media.room_id)
+        .bind(&media.object_key)
+        .bind(media.uploaded_by)
+        .bind(&media.mime_type)
+        .bind(media.size_bytes)
+        .bind(media.created_at)
+        .bind(media.completed_at)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_media(&self, room_id: &Uuid, media_id: &Uuid) -> Result<Option<RoomMediaRow>, Error> {
+        let media = query_as::<_, RoomMediaRow>(
+            r#"
+            SELECT media_id, room_id, object_key, uploaded_by, mime_type, size_bytes, created_at, completed_at, message_id
+            FROM room_media
+            WHERE room_id = $1 AND media_id = $2
+            "#,
+        )
+        .bind(room_id)
+        .bind(media_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+        Ok(media)
+    }
+
+    pub async fn fetch_media_by_key(&self, object_key: &str) -> Result<Option<RoomMediaRow>, Error> {
+        let media = query_as::<_, RoomMediaRow>(
+            r#"
+            SELECT media_id, room_id, object_key, uploaded_by, mime_type, size_bytes, created_at, completed_at, message_id
+            FROM room_media
+            WHERE object_key = $1
+            "#,
+        )
+        .bind(object_key)
+        .fetch_optional(self.db.pool())
+        .await?;
+        Ok(media)
+    }
+
+    /// The object keys of every upload in a room, for deleting the bytes once the room is gone.
+    /// The rows themselves go with the room through `ON DELETE CASCADE`.
+    pub async fn select_room_media_keys(&self, room_id: &Uuid) -> Result<Vec<String>, Error> {
+        let keys = sqlx::query_scalar::<_, String>("SELECT object_key FROM room_media WHERE room_id = $1")
+            .bind(room_id)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(keys)
+    }
+
+    /// Marks a reserved upload as checked and usable. `false` when it is no longer reserved — the
+    /// sweep got to it first.
+    pub async fn complete_media(&self, media_id: &Uuid, completed_at: DateTime<Utc>) -> Result<bool, Error> {
+        let result = sqlx::query("UPDATE room_media SET completed_at = $2 WHERE media_id = $1 AND completed_at IS NULL")
+            .bind(media_id)
+            .bind(completed_at)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Attaches the upload stored under `object_key` to `message_id`. `false` when it is already
+    /// attached to a message, which an upload can be only once.
+    pub async fn attach_media<'e, E>(&self, exec: E, object_key: &str, message_id: &Uuid) -> Result<bool, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let result = sqlx::query("UPDATE room_media SET message_id = $2 WHERE object_key = $1 AND message_id IS NULL")
+            .bind(object_key)
+            .bind(message_id)
+            .execute(exec)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes the upload attached to `message_id`, if any, returning it so the bytes can be
+    /// removed from storage once the deletion is committed.
+    pub async fn delete_message_media<'e, E>(&self, exec: E, message_id: &Uuid) -> Result<Option<RoomMediaRow>, Error>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let media = query_as::<_, RoomMediaRow>(
+            r#"
+            DELETE FROM room_media
+            WHERE message_id = $1
+            RETURNING media_id, room_id, object_key, uploaded_by, mime_type, size_bytes, created_at, completed_at, message_id
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(exec)
+        .await?;
+        Ok(media)
+    }
+
+    pub async fn delete_media(&self, media_id: &Uuid) -> Result<(), Error> {
+        sqlx::query("DELETE FROM room_media WHERE media_id = $1")
+            .bind(media_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes reservations made before `reserved_before` that were never completed, returning
+    /// them so whatever did arrive can be removed from storage too.
+    pub async fn delete_stale_media_reservations(&self, reserved_before: DateTime<Utc>) -> Result<Vec<RoomMediaRow>, Error> {
+        let reservations = query_as::<_, RoomMediaRow>(
+            r#"
+            DELETE FROM room_media
+            WHERE completed_at IS NULL AND created_at < $1
+            RETURNING media_id, room_id, object_key, uploaded_by, mime_type, size_bytes, created_at, completed_at, message_id
+            "#,
+        )
+        .bind(reserved_before)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(reservations)
+    }
+
+    /// Deletes uploads completed before `completed_before` that no message attached and no
+    /// scheduled message is waiting to attach, returning them.
+    pub async fn delete_unattached_media(&self, completed_before: DateTime<Utc>) -> Result<Vec<RoomMediaRow>, Error> {
+        let uploads = query_as::<_, RoomMediaRow>(
+            r#"
+            DELETE FROM room_media media
+            WHERE media.message_id IS NULL
+              AND media.completed_at < $1
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM scheduled_message scheduled
+                  WHERE scheduled.msg_body ->> 'mediaKey' = media.object_key
+                    AND scheduled.failed_at IS NULL
+              )
+            RETURNING media.media_id, media.room_id, media.object_key, media.uploaded_by, media.mime_type, media.size_bytes,
+                      media.created_at, media.completed_at, media.message_id
+            "#,
+        )
+        .bind(completed_before)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(uploads)
+    }
+
+    pub async fn insert_scheduled_message(&self, scheduled: &ScheduledMessageRow) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO scheduled_message (scheduled_message_id, chat_room_id, sender_id, msg_body, msg_type, thread_root_id, send_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(scheduled.scheduled_message_id)
+        .bind(scheduled.chat_room_id)
+        .bind(scheduled.sender_id)
+        .bind(&scheduled.msg_body)
+        .bind(scheduled.msg_type)
+        .bind(scheduled.thread_root_id)
+        .bind(scheduled.send_at)
+        .bind(scheduled.created_at)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// How many messages `sender_id` has scheduled, failed ones included.
+    pub async fn count_scheduled_messages(&self, sender_id: &Uuid) -> Result<i64, Error> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM scheduled_message WHERE sender_id = $1")
+            .bind(sender_id)
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(count)
+    }
+
+    /// Everything `sender_id` has scheduled, optionally for one room only, soonest first.
+    pub async fn select_scheduled_messages(&self, sender_id: &Uuid, room_id: Option<Uuid>) -> Result<Vec<ScheduledMessageRow>, Error> {
+        let rows = query_as::<_, ScheduledMessageRow>(
+            r#"
+            SELECT scheduled_message_id, chat_room_id, sender_id, msg_body, msg_type, thread_root_id, send_at, created_at, failed_at, failure
+            FROM scheduled_message
+            WHERE sender_id = $1 AND ($2::uuid IS NULL OR chat_room_id = $2)
+            ORDER BY send_at ASC, scheduled_message_id ASC
+            "#,
+        )
+        .bind(sender_id)
+        .bind(room_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    /// Deletes one of `sender_id`'s scheduled messages, unless a dispatcher holds it: that one may
+    /// be sent right now, so `false` also means it may have been.
+    pub async fn delete_scheduled_message(&self, sender_id: &Uuid, scheduled_message_id: &Uuid) -> Result<bool, Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM scheduled_message
+            WHERE scheduled_message_id = $1 AND sender_id = $2 AND (claimed_until IS NULL OR claimed_until <= NOW())
+            "#,
+        )
+        .bind(scheduled_message_id)
+        .bind(sender_id)
+        .execute(self.db.pool())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Claims up to `limit` scheduled messages that are due, not failed and not claimed by another
+    /// dispatcher, soonest first, until `claimed_until`. The statement commits on its own, so no
+    /// lock outlives it; the lease is what keeps other dispatchers off the rows while they are
+    /// sent, and what hands them to another one if this one dies first.
+    pub async fn claim_due_scheduled_messages(&self, now: DateTime<Utc>, claimed_until: DateTime<Utc>, limit: i64) -> Result<Vec<ScheduledMessageRow>, Error> {
+        let rows = query_as::<_, ScheduledMessageRow>(
+            r#"
+            UPDATE scheduled_message
+            SET claimed_until = $2
+            WHERE scheduled_message_id IN (
+                SELECT scheduled_message_id
+                FROM scheduled_message
+                WHERE send_at <= $1 AND failed_at IS NULL AND (claimed_until IS NULL OR claimed_until <= $1)
+                ORDER BY send_at ASC
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING scheduled_message_id, chat_room_id, sender_id, msg_body, msg_type, thread_root_id, send_at, created_at, failed_at, failure
+            "#,
+        )
+        .bind(now)
+        .bind(claimed_until)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    /// Removes a scheduled message whose message was sent.
+    pub async fn finish_scheduled_message(&self, scheduled_message_id: &Uuid) -> Result<(), Error> {
+        sqlx::query("DELETE FROM scheduled_message WHERE scheduled_message_id = $1")
+            .bind(scheduled_message_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Gives up the claim on a scheduled message that could not be sent this time, so the next run
+    /// tries again.
+    pub async fn release_scheduled_message(&self, scheduled_message_id: &Uuid) -> Result<(), Error> {
+        sqlx::query("UPDATE scheduled_message SET claimed_until = NULL WHERE scheduled_message_id = $1")
+            .bind(scheduled_message_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Records why a scheduled message could not be sent; the dispatcher leaves it alone after.
+    pub async fn fail_scheduled_message(&self, scheduled_message_id: &Uuid, failure: &str, failed_at: DateTime<Utc>) -> Result<(), Error> {
+        sqlx::query("UPDATE scheduled_message SET failed_at = $2, failure = $3, claimed_until = NULL WHERE scheduled_message_id = $1")
+            .bind(scheduled_message_id)
+            .bind(failed_at)
+            .bind(failure)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
 }
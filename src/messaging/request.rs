@@ -7,8 +7,12 @@
 //! the server resolved. One type could not honestly do both jobs.
 
 use crate::core::ApiRequest;
-use crate::messaging::entity::{MediaJson, MessageBodyJson, TextJson};
-use crate::messaging::model::MsgType;
+use crate::core::cursor::PageSize;
+use crate::core::errors::AppError;
+use crate::messaging::entity::{MediaJson, MessageBodyJson, PollJson, ScheduledBodyJson, TextJson};
+use crate::messaging::model::{MessageSearchFilter, MsgType};
+use crate::rooms::request::MarkReadQuery;
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
 use validator::{Validate, ValidationError, ValidationErrors};
@@ -25,6 +29,16 @@ pub struct SendMessageRequest {
     /// against the body rather than trusted — see [`check_msg_type_matches_body`] — and then
     /// discarded: `MessageRow::new` derives the stored `msg_type` from the body itself.
     pub msg_type: MsgType,
+    /// Posts the message as a reply into this message's thread instead of the main timeline. Must
+    /// name a root: threads do not nest.
+    #[serde(default)]
+    pub thread_root_id: Option<Uuid>,
+    /// The client's own id for this message, typically a UUID it generated. Sending again with the
+    /// same id returns the message stored the first time instead of storing a duplicate, which is
+    /// what makes retrying after a timeout safe.
+    #[serde(default)]
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters long."))]
+    pub client_message_id: Option<String>,
 }
 
 impl ApiRequest for SendMessageRequest {}
@@ -35,12 +49,7 @@ impl ApiRequest for SendMessageRequest {}
 /// downstream trusted the field, but it was echoed back in the response and cached in the
 /// notification stream, which meant one client's mislabelling became every client's problem.
 fn check_msg_type_matches_body(request: &SendMessageRequest) -> Result<(), ValidationError> {
-    let implied = match request.msg_body {
-        SendMessageBodyRequest::Text(_) => MsgType::Text,
-        SendMessageBodyRequest::Media(_) => MsgType::Media,
-        SendMessageBodyRequest::Reply(_) => MsgType::Reply,
-    };
-    if implied == request.msg_type {
+    if request.msg_body.msg_type() == request.msg_type {
         Ok(())
     } else {
         Err(ValidationError::new("msg_type_does_not_match_msg_body"))
@@ -56,6 +65,21 @@ pub enum SendMessageBodyRequest {
     Text(TextBodyRequest),
     Media(MediaBodyRequest),
     Reply(ReplyBodyRequest),
+    Poll(PollBodyRequest),
+    /// Last, because it is the loosest shape: `mediaKey` is its only required field.
+    UploadedMedia(UploadedMediaBodyRequest),
+}
+
+impl SendMessageBodyRequest {
+    /// The `msg_type` a message with this body is stored as.
+    pub fn msg_type(&self) -> MsgType {
+        match self {
+            SendMessageBodyRequest::Text(_) => MsgType::Text,
+            SendMessageBodyRequest::Media(_) | SendMessageBodyRequest::UploadedMedia(_) => MsgType::Media,
+            SendMessageBodyRequest::Reply(_) => MsgType::Reply,
+            SendMessageBodyRequest::Poll(_) => MsgType::Poll,
+        }
+    }
 }
 
 /// Hand-written because `#[derive(Validate)]` does not cover enums; it forwards to whichever
@@ -66,27 +90,41 @@ impl Validate for SendMessageBodyRequest {
             SendMessageBodyRequest::Text(body) => body.validate(),
             SendMessageBodyRequest::Media(body) => body.validate(),
             SendMessageBodyRequest::Reply(body) => body.validate(),
+            SendMessageBodyRequest::Poll(body) => body.validate(),
+            SendMessageBodyRequest::UploadedMedia(body) => body.validate(),
         }
     }
 }
 
-impl From<SendMessageBodyRequest> for MessageBodyJson {
-    /// Only total for `Text` and `Media`. A `Reply` needs the quoted message resolved from the
-    /// database first, so `MessageService::create_reply_message` builds that variant instead — the
-    /// reason this returns `MessageBodyJson` for two of three cases and the service handles the
-    /// third.
-    fn from(request: SendMessageBodyRequest) -> Self {
+impl TryFrom<SendMessageBodyRequest> for MessageBodyJson {
+    type Error = AppError;
+
+    /// Only defined for `Text`, `Media` and `Poll`. A `Reply` needs the quoted message resolved
+    /// from the database first, and an `UploadedMedia` the upload it names, so `MessageService`
+    /// builds those variants itself and they are refused here.
+    fn try_from(request: SendMessageBodyRequest) -> Result<Self, Self::Error> {
         match request {
-            SendMessageBodyRequest::Text(body) => MessageBodyJson::Text(TextJson { text: body.text }),
-            SendMessageBodyRequest::Media(body) => MessageBodyJson::Media(MediaJson {
+            SendMessageBodyRequest::Text(body) => Ok(MessageBodyJson::Text(TextJson {
+                text: body.text,
+                mentions: Vec::new(),
+            })),
+            SendMessageBodyRequest::Media(body) => Ok(MessageBodyJson::Media(MediaJson {
                 media_url: body.media_url,
                 media_type: body.media_type,
                 mime_type: body.mime_type,
                 alt_text: body.alt_text,
-            }),
-            // Unreachable in practice: the service intercepts `Reply` before this conversion. The
-            // fallback stores the text alone rather than panicking on a path that cannot be hit.
-            SendMessageBodyRequest::Reply(body) => MessageBodyJson::Text(TextJson { text: body.reply_text }),
+                media_key: None,
+            })),
+            SendMessageBodyRequest::Poll(body) => Ok(MessageBodyJson::Poll(PollJson {
+                question: body.question,
+                options: body.options,
+                multiple_choice: body.multiple_choice,
+                closes_at: body.closes_at,
+                closed_at: None,
+            })),
+            SendMessageBodyRequest::Reply(_) | SendMessageBodyRequest::UploadedMedia(_) => Err(AppError::Processing(
+                "Replies and uploaded media need resolving before they can be stored.".to_string(),
+            )),
         }
     }
 }
@@ -113,6 +151,17 @@ pub struct MediaBodyRequest {
     pub alt_text: Option<String>,
 }
 
+/// Media uploaded through `POST /api/v1/rooms/{room_id}/media`, attached by the `mediaKey` the
+/// upload returned. URL, type and MIME type come from the upload, not from the client.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedMediaBodyRequest {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters long."))]
+    pub media_key: String,
+    #[validate(length(max = 1000, message = "must be at most 1000 characters long."))]
+    pub alt_text: Option<String>,
+}
+
 /// What a client sends to reply: the message being replied to, and the reply text. Everything else
 /// on the stored [`ReplyJson`](crate::messaging::entity::ReplyJson) is resolved server-side.
 #[derive(Debug, Deserialize, Clone, Validate)]
@@ -123,6 +172,303 @@ pub struct ReplyBodyRequest {
     pub reply_text: String,
 }
 
+/// A poll: a question and the options to vote on, 2 to 10 distinct ones. Whether it is still open
+/// at `closes_at` is checked when it is sent, so a scheduled poll cannot arrive already closed.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PollBodyRequest {
+    #[validate(length(min = 1, max = 300, message = "must be between 1 and 300 characters long."))]
+    pub question: String,
+    #[validate(length(min = 2, max = 10, message = "must contain between 2 and 10 options."))]
+    #[validate(custom(function = "check_poll_options"))]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub multiple_choice: bool,
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+fn check_poll_options(options: &[String]) -> Result<(), ValidationError> {
+    if options.iter().any(|option| option.trim().is_empty() || option.chars().count() > 100) {
+        return Err(ValidationError::new("poll_option_must_be_between_1_and_100_characters"));
+    }
+    if options.iter().enumerate().any(|(index, option)| options[..index].contains(option)) {
+        return Err(ValidationError::new("poll_options_must_be_distinct"));
+    }
+    Ok(())
+}
+
+/// Body of `PATCH /api/v1/rooms/{room_id}/messages/{message_id}`.
+///
+/// Only the text can change. For a reply that is the reply's own text; the quoted message stays
+/// the snapshot it was when the reply was sent.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct EditMessageRequest {
+    #[validate(length(min = 1, max = 4000, message = "must be between 1 and 4000 characters long."))]
+    pub text: String,
+}
+
+impl ApiRequest for EditMessageRequest {}
+
+/// Body of `POST` and query of `DELETE /api/v1/rooms/{room_id}/messages/{message_id}/reactions`.
+///
+/// The emoji is stored as the client sent it. Checking it against a list of "real" emojis would tie
+/// the server to a Unicode version; the bounds only keep it short and free of whitespace, which is
+/// all the storage and the UI need.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionRequest {
+    #[validate(length(min = 1, max = 16, message = "must be between 1 and 16 characters long."))]
+    #[validate(custom(function = "check_emoji"))]
+    pub emoji: String,
+}
+
+impl ApiRequest for ReactionRequest {}
+
+/// Body of `POST /api/v1/rooms/{room_id}/messages/{message_id}/poll/votes`: the indexes of the
+/// options the caller votes for, replacing any earlier vote. Exactly one for a single-choice poll.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PollVoteRequest {
+    #[validate(length(min = 1, max = 10, message = "must contain between 1 and 10 options."))]
+    pub options: Vec<u32>,
+}
+
+impl ApiRequest for PollVoteRequest {}
+
+fn check_emoji(emoji: &str) -> Result<(), ValidationError> {
+    if emoji.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(ValidationError::new("emoji_contains_whitespace"));
+    }
+    Ok(())
+}
+
+/// How far ahead a message may be scheduled.
+const MAX_SCHEDULE_DAYS: i64 = 365;
+
+/// Body of `POST /api/v1/scheduled-messages`: a message as for `POST /api/v1/send-msg`, and when to
+/// send it.
+///
+/// No `clientMessageId`: the scheduled message's own id takes its place once it is sent.
+#[derive(Debug, Deserialize, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "check_schedule", skip_on_field_errors = true))]
+pub struct ScheduleMessageRequest {
+    pub chat_room_id: Uuid,
+    #[validate(nested)]
+    pub msg_body: SendMessageBodyRequest,
+    /// Checked against `msg_body` like on [`SendMessageRequest`].
+    pub msg_type: MsgType,
+    #[serde(default)]
+    pub thread_root_id: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+}
+
+impl ApiRequest for ScheduleMessageRequest {}
+
+fn check_schedule(request: &ScheduleMessageRequest) -> Result<(), ValidationError> {
+    if request.msg_body.msg_type() != request.msg_type {
+        return Err(ValidationError::new("msg_type_does_not_match_msg_body"));
+    }
+    let now = Utc::now();
+    if request.send_at <= now {
+        return Err(ValidationError::new("send_at_must_be_in_the_future"));
+    }
+    if request.send_at > now + TimeDelta::days(MAX_SCHEDULE_DAYS) {
+        return Err(ValidationError::new("send_at_is_too_far_ahead"));
+    }
+    Ok(())
+}
+
+impl From<SendMessageBodyRequest> for ScheduledBodyJson {
+    fn from(request: SendMessageBodyRequest) -> Self {
+        match request {
+            SendMessageBodyRequest::Text(body) => ScheduledBodyJson::Text { text: body.text },
+            SendMessageBodyRequest::Media(body) => ScheduledBodyJson::Media {
+                media_url: body.media_url,
+                media_type: body.media_type,
+                mime_type: body.mime_type,
+                alt_text: body.alt_text,
+            },
+            SendMessageBodyRequest::Reply(body) => ScheduledBodyJson::Reply {
+                reply_msg_id: body.reply_msg_id,
+                reply_text: body.reply_text,
+            },
+            SendMessageBodyRequest::UploadedMedia(body) => ScheduledBodyJson::UploadedMedia {
+                media_key: body.media_key,
+                alt_text: body.alt_text,
+            },
+            SendMessageBodyRequest::Poll(body) => ScheduledBodyJson::Poll {
+                question: body.question,
+                options: body.options,
+                multiple_choice: body.multiple_choice,
+                closes_at: body.closes_at,
+            },
+        }
+    }
+}
+
+/// Turns a stored body back into the request it was scheduled as, for the send.
+impl From<ScheduledBodyJson> for SendMessageBodyRequest {
+    fn from(stored: ScheduledBodyJson) -> Self {
+        match stored {
+            ScheduledBodyJson::Text { text } => SendMessageBodyRequest::Text(TextBodyRequest { text }),
+            ScheduledBodyJson::Media {
+                media_url,
+                media_type,
+                mime_type,
+                alt_text,
+            } => SendMessageBodyRequest::Media(MediaBodyRequest {
+                media_url,
+                media_type,
+                mime_type,
+                alt_text,
+            }),
+            ScheduledBodyJson::Reply { reply_msg_id, reply_text } => SendMessageBodyRequest::Reply(ReplyBodyRequest { reply_msg_id, reply_text }),
+            ScheduledBodyJson::UploadedMedia { media_key, alt_text } => SendMessageBodyRequest::UploadedMedia(UploadedMediaBodyRequest { media_key, alt_text }),
+            ScheduledBodyJson::Poll {
+                question,
+                options,
+                multiple_choice,
+                closes_at,
+            } => SendMessageBodyRequest::Poll(PollBodyRequest {
+                question,
+                options,
+                multiple_choice,
+                closes_at,
+            }),
+        }
+    }
+}
+
+/// Query params for `GET /api/v1/scheduled-messages`.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledMessagesQuery {
+    /// Only the caller's scheduled messages for this room.
+    pub room_id: Option<Uuid>,
+}
+
+impl ApiRequest for ScheduledMessagesQuery {}
+
+/// Body of `POST /api/v1/rooms/{room_id}/media/uploads`: what the client is about to upload.
+///
+/// Both values are promises the completion step holds the client to; the limit for `size_bytes`
+/// depends on the kind of `mime_type`, so the service checks it.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaUploadUrlRequest {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters long."))]
+    pub mime_type: String,
+    #[validate(range(min = 1, message = "must be at least 1."))]
+    pub size_bytes: i64,
+}
+
+impl ApiRequest for MediaUploadUrlRequest {}
+
+/// Body of `POST /api/v1/rooms/{room_id}/messages/{message_id}/forward`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardMessageRequest {
+    pub targets: Vec<ForwardTargetRequest>,
+}
+
+impl ApiRequest for ForwardMessageRequest {}
+
+/// Hand-written because the derived `length` check reports the offending value back, which would
+/// need the targets to be `Serialize` — and no request is.
+impl Validate for ForwardMessageRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if self.targets.is_empty() || self.targets.len() > 20 {
+            let mut error = ValidationError::new("length");
+            error.message = Some("must contain between 1 and 20 targets.".into());
+            errors.add("targets", error);
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Where to forward to — the `target` of a share target, as `GET /api/v1/rooms/share-targets`
+/// returned it. Clients pass those through unchanged, so the shape is the same, snake_case fields
+/// included; a room target's `room_type` is accepted and ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ForwardTargetRequest {
+    Room {
+        room_id: Uuid,
+    },
+    /// A friend; their 1-1 room is created first if there is none yet.
+    User {
+        user_id: Uuid,
+    },
+}
+
+/// Body of `POST /api/v1/rooms/{room_id}/typing`.
+///
+/// Clients send `active: true` while the user types — as often as they like, the server throttles
+/// — and `active: false` when the input is cleared or sent. A client that just goes quiet is
+/// cleared by the server after a few seconds.
+#[derive(Debug, Deserialize, Validate)]
+pub struct TypingRequest {
+    #[serde(default = "default_typing_active")]
+    pub active: bool,
+}
+
+impl ApiRequest for TypingRequest {}
+
+fn default_typing_active() -> bool {
+    true
+}
+
+/// Version of the WebSocket command protocol. A frame naming any other version is answered with an
+/// error rather than guessed at.
+pub const COMMAND_PROTOCOL_VERSION: u8 = 1;
+
+/// A command a client sends up an open WebSocket: the same operations as the REST endpoints of the
+/// same name, without a round-trip of their own.
+///
+/// Arrives inside a frame that also carries the protocol version `v` and an optional correlation
+/// `id`; see `messaging::socket` for the envelope and the replies. Tagged by `type`, like the
+/// events coming down the socket.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum ClientCommand {
+    /// `POST /rooms/{room_id}/typing`.
+    Typing {
+        room_id: Uuid,
+        #[serde(default = "default_typing_active")]
+        active: bool,
+    },
+    /// `POST /send-msg`.
+    SendMessage(SendMessageRequest),
+    /// `POST /rooms/{room_id}/mark-read`.
+    MarkRead {
+        room_id: Uuid,
+        #[serde(flatten)]
+        position: MarkReadQuery,
+    },
+    /// The client holds every event up to `seq`. Nothing at or below it is sent again on this
+    /// socket, and the reply carries the server's current cursor so the client can tell whether it
+    /// is behind.
+    Ack { seq: u64 },
+    /// `GET /notifications?last_seq=`: the durable events after `last_seq`.
+    Replay { last_seq: u64 },
+}
+
+impl ApiRequest for ClientCommand {}
+
+impl Validate for ClientCommand {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            ClientCommand::SendMessage(request) => request.validate(),
+            ClientCommand::MarkRead { position, .. } => position.validate(),
+            ClientCommand::Typing { .. } | ClientCommand::Ack { .. } | ClientCommand::Replay { .. } => Ok(()),
+        }
+    }
+}
+
 /// Body of the optional first message that can be sent together with a new room.
 ///
 /// A brand-new room has no prior messages, so a `Reply` is impossible here — only `Text` and
@@ -146,12 +492,16 @@ impl Validate for FirstMessageRequest {
 impl From<FirstMessageRequest> for MessageBodyJson {
     fn from(request: FirstMessageRequest) -> Self {
         match request {
-            FirstMessageRequest::Text(body) => MessageBodyJson::Text(TextJson { text: body.text }),
+            FirstMessageRequest::Text(body) => MessageBodyJson::Text(TextJson {
+                text: body.text,
+                mentions: Vec::new(),
+            }),
             FirstMessageRequest::Media(body) => MessageBodyJson::Media(MediaJson {
                 media_url: body.media_url,
                 media_type: body.media_type,
                 mime_type: body.mime_type,
                 alt_text: body.alt_text,
+                media_key: None,
             }),
         }
     }
@@ -176,3 +526,55 @@ pub struct NotificationBacklogQuery {
 }
 
 impl ApiRequest for NotificationBacklogQuery {}
+
+/// Query params for `GET /api/v1/messages/search`. See [`MessageSearchFilter`] for what the
+/// filters mean.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "check_search_window"))]
+pub struct MessageSearchQuery {
+    #[validate(length(min = 1, max = 200, message = "must be between 1 and 200 characters long."))]
+    pub q: String,
+    pub room_id: Option<Uuid>,
+    pub sender_id: Option<Uuid>,
+    pub msg_type: Option<MsgType>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: PageSize,
+}
+
+impl ApiRequest for MessageSearchQuery {}
+
+impl MessageSearchQuery {
+    pub fn filter(&self) -> MessageSearchFilter {
+        MessageSearchFilter {
+            text: self.q.clone(),
+            room_id: self.room_id,
+            sender_id: self.sender_id,
+            msg_type: self.msg_type,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+/// Query params for `GET /api/v1/mentions`.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionFeedQuery {
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: PageSize,
+}
+
+impl ApiRequest for MentionFeedQuery {}
+
+/// An empty date range cannot match anything, and is far more likely a swapped pair than intent.
+fn check_search_window(query: &MessageSearchQuery) -> Result<(), ValidationError> {
+    match (query.from, query.to) {
+        (Some(from), Some(to)) if from >= to => Err(ValidationError::new("from_must_be_before_to")),
+        _ => Ok(()),
+    }
+}
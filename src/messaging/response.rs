@@ -5,10 +5,16 @@
 //! a field here is an API change with a migration note, renaming it there makes existing rows
 //! undecodable.
 
+use crate::broadcast::Notification;
 use crate::core::ApiResponse;
-use crate::messaging::entity::{MediaJson, MessageBodyJson, MessageRow, RepliedMessageJson, ReplyJson, RoomChangeJson, TextJson};
+use crate::core::errors::ErrorResponse;
+use crate::messaging::entity::{
+    ForwardedFromJson, MediaJson, MentionJson, MessageBodyJson, MessageRow, MessageSearchRow, PollJson, PollVoteCountRow, ReactionCountRow, RepliedMessageJson,
+    ReplyJson, RoomChangeJson, ScheduledBodyJson, ScheduledMessageRow, TextJson,
+};
 use crate::messaging::model::MsgType;
 use crate::rooms::entity::RoomMemberSnapshotJson;
+use crate::rooms::model::RoomRole;
 use crate::rooms::response::RoomMemberResponse;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -27,6 +33,39 @@ pub struct MessageResponse {
     pub msg_body: MessageBodyResponse,
     pub msg_type: MsgType,
     pub created_at: DateTime<Utc>,
+    /// Omitted rather than `null` while unset, so a message that was never edited or deleted
+    /// serializes exactly as it did before either was possible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Aggregated reactions, filled in by the timeline. Empty — and then omitted — everywhere
+    /// else, including live events: those carry reaction changes as `ReactionChanged` instead.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<ReactionResponse>,
+    /// The votes so far, on polls only; filled in by the timeline like `reactions`, and by the
+    /// poll endpoints. Live events carry vote changes as `PollUpdated` instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_results: Option<PollResultsResponse>,
+    /// Set on thread replies only. The thread fields follow the same omit-while-unset rule as
+    /// `edited_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_root_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reply_count: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reply_at: Option<DateTime<Utc>>,
+    /// The id the sender's client gave the message, echoed so their other devices can match it to
+    /// the optimistic entry they show while it is sent. Omitted when the client sent none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_message_id: Option<String>,
+    /// Set on forwards only, and omitted otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<ForwardedFromResponse>,
+}
+
+fn is_zero(count: &i32) -> bool {
+    *count == 0
 }
 
 impl ApiResponse for MessageResponse {}
@@ -40,10 +79,125 @@ impl From<MessageRow> for MessageResponse {
             msg_body: MessageBodyResponse::from(row.msg_body.0),
             msg_type: row.msg_type,
             created_at: row.created_at,
+            edited_at: row.edited_at,
+            deleted_at: row.deleted_at,
+            reactions: Vec::new(),
+            poll_results: None,
+            thread_root_id: row.thread_root_id,
+            reply_count: row.reply_count,
+            last_reply_at: row.last_reply_at,
+            client_message_id: row.client_message_id,
+            forwarded_from: row.forwarded_from.map(|stored| ForwardedFromResponse::from(stored.0)),
+        }
+    }
+}
+
+/// What became of one target of a forward: the message posted there, or the error that target
+/// failed with — the same body, including `errorCode`, that a request failing on it alone would
+/// have carried.
+#[derive(Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum ForwardOutcome {
+    Forwarded { message: Box<MessageResponse> },
+    ForwardFailed { error: ErrorResponse },
+}
+
+/// The message a forward copies; see [`ForwardedFromJson`]. Only the original's sender is worth
+/// resolving — the message itself sits in a room the reader may not be in.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardedFromResponse {
+    pub message_id: Uuid,
+    pub sender_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiResponse for ForwardedFromResponse {}
+
+impl From<ForwardedFromJson> for ForwardedFromResponse {
+    fn from(stored: ForwardedFromJson) -> Self {
+        ForwardedFromResponse {
+            message_id: stored.message_id,
+            sender_id: stored.sender_id,
+            created_at: stored.created_at,
+        }
+    }
+}
+
+/// The state of a thread right after a reply was posted into it, carried on `ChatMessage` so
+/// clients can update the root's "3 replies" badge without loading the thread.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadSummaryResponse {
+    pub root_message_id: Uuid,
+    pub reply_count: i32,
+    pub last_reply_at: DateTime<Utc>,
+}
+
+impl ApiResponse for ThreadSummaryResponse {}
+
+/// How many users reacted to a message with one emoji, and whether the caller is among them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionResponse {
+    pub emoji: String,
+    pub count: i64,
+    pub reacted_by_me: bool,
+}
+
+impl ApiResponse for ReactionResponse {}
+
+impl From<ReactionCountRow> for ReactionResponse {
+    fn from(row: ReactionCountRow) -> Self {
+        ReactionResponse {
+            emoji: row.emoji,
+            count: row.count,
+            reacted_by_me: row.reacted_by_me,
         }
     }
 }
 
+/// The votes on a poll: how many voters picked each option, in the order of the poll's `options`,
+/// and how many voted at all — fewer than the sum for a multiple-choice poll.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PollResultsResponse {
+    pub votes: Vec<i64>,
+    pub voter_count: i64,
+    /// The caller's own choices. Empty — and then omitted — when they have not voted, and always
+    /// in `PollUpdated`, which every member receives alike.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub my_votes: Vec<u32>,
+}
+
+impl ApiResponse for PollResultsResponse {}
+
+impl PollResultsResponse {
+    /// Tallies one poll with `option_count` options from its [`PollVoteCountRow`]s. Votes for an
+    /// index the poll does not have are ignored.
+    pub fn tally(option_count: usize, rows: impl IntoIterator<Item = PollVoteCountRow>) -> Self {
+        let mut results = PollResultsResponse {
+            votes: vec![0; option_count],
+            voter_count: 0,
+            my_votes: Vec::new(),
+        };
+        for row in rows {
+            match row.option_index.and_then(|index| usize::try_from(index).ok()) {
+                None => results.voter_count = row.count,
+                Some(index) if index < option_count => {
+                    results.votes[index] = row.count;
+                    if row.voted_by_me {
+                        results.my_votes.push(index as u32);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        results.my_votes.sort_unstable();
+        results
+    }
+}
+
 /// The body of a message. `untagged`, matching the stored representation — clients discriminate on
 /// the sibling `msgType` field.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -53,6 +207,7 @@ pub enum MessageBodyResponse {
     Media(MediaBodyResponse),
     Reply(ReplyBodyResponse),
     RoomChange(RoomChangeResponse),
+    Poll(PollBodyResponse),
 }
 
 impl ApiResponse for MessageBodyResponse {}
@@ -64,6 +219,7 @@ impl From<MessageBodyJson> for MessageBodyResponse {
             MessageBodyJson::Media(body) => MessageBodyResponse::Media(body.into()),
             MessageBodyJson::Reply(body) => MessageBodyResponse::Reply(body.into()),
             MessageBodyJson::RoomChange(body) => MessageBodyResponse::RoomChange(body.into()),
+            MessageBodyJson::Poll(body) => MessageBodyResponse::Poll(body.into()),
         }
     }
 }
@@ -72,11 +228,34 @@ impl From<MessageBodyJson> for MessageBodyResponse {
 #[serde(rename_all = "camelCase")]
 pub struct TextBodyResponse {
     pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<MentionResponse>,
 }
 
 impl From<TextJson> for TextBodyResponse {
     fn from(stored: TextJson) -> Self {
-        TextBodyResponse { text: stored.text }
+        TextBodyResponse {
+            text: stored.text,
+            mentions: stored.mentions.into_iter().map(MentionResponse::from).collect(),
+        }
+    }
+}
+
+/// A mention inside a message's text; see [`MentionJson`] for the syntax and what the offsets
+/// count.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum MentionResponse {
+    User { user_id: Uuid, offset: u32, length: u32 },
+    Room { offset: u32, length: u32 },
+}
+
+impl From<MentionJson> for MentionResponse {
+    fn from(stored: MentionJson) -> Self {
+        match stored {
+            MentionJson::User { user_id, offset, length } => MentionResponse::User { user_id, offset, length },
+            MentionJson::Room { offset, length } => MentionResponse::Room { offset, length },
+        }
     }
 }
 
@@ -87,6 +266,8 @@ pub struct MediaBodyResponse {
     pub media_type: String,
     pub mime_type: Option<String>,
     pub alt_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_key: Option<String>,
 }
 
 impl From<MediaJson> for MediaBodyResponse {
@@ -96,6 +277,7 @@ impl From<MediaJson> for MediaBodyResponse {
             media_type: stored.media_type,
             mime_type: stored.mime_type,
             alt_text: stored.alt_text,
+            media_key: stored.media_key,
         }
     }
 }
@@ -109,6 +291,8 @@ pub struct ReplyBodyResponse {
     pub reply_created_at: DateTime<Utc>,
     pub reply_msg_details: RepliedMessageResponse,
     pub reply_text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<MentionResponse>,
 }
 
 impl From<ReplyJson> for ReplyBodyResponse {
@@ -120,6 +304,31 @@ impl From<ReplyJson> for ReplyBodyResponse {
             reply_created_at: stored.reply_created_at,
             reply_msg_details: RepliedMessageResponse::from(stored.reply_msg_details),
             reply_text: stored.reply_text,
+            mentions: stored.mentions.into_iter().map(MentionResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PollBodyResponse {
+    pub question: String,
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closes_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl From<PollJson> for PollBodyResponse {
+    fn from(stored: PollJson) -> Self {
+        PollBodyResponse {
+            question: stored.question,
+            options: stored.options,
+            multiple_choice: stored.multiple_choice,
+            closes_at: stored.closes_at,
+            closed_at: stored.closed_at,
         }
     }
 }
@@ -130,6 +339,7 @@ pub enum RepliedMessageResponse {
     Text(TextBodyResponse),
     Media(MediaBodyResponse),
     Reply { reply_text: String },
+    Poll { question: String },
 }
 
 impl From<RepliedMessageJson> for RepliedMessageResponse {
@@ -138,11 +348,12 @@ impl From<RepliedMessageJson> for RepliedMessageResponse {
             RepliedMessageJson::Text(body) => RepliedMessageResponse::Text(body.into()),
             RepliedMessageJson::Media(body) => RepliedMessageResponse::Media(body.into()),
             RepliedMessageJson::Reply { reply_text } => RepliedMessageResponse::Reply { reply_text },
+            RepliedMessageJson::Poll { question } => RepliedMessageResponse::Poll { question },
         }
     }
 }
 
-/// A membership change as rendered in the timeline.
+/// A membership or metadata change as rendered in the timeline.
 ///
 /// `related_user` stays a [`RoomMemberSnapshotJson`] rather than becoming a `RoomMemberResponse`:
 /// what the client shows here is who joined *at that moment*, which is exactly the frozen value the
@@ -153,6 +364,12 @@ pub enum RoomChangeResponse {
     UserJoined { related_user: RoomMemberSnapshotJson },
     UserLeft { related_user: RoomMemberSnapshotJson },
     UserInvited { related_user: RoomMemberSnapshotJson },
+    UserKicked { related_user: RoomMemberSnapshotJson },
+    UserRoleChanged { related_user: RoomMemberSnapshotJson, role: RoomRole },
+    RoomRenamed { room_name: String },
+    ImageChanged { image_url: String },
+    TopicChanged { topic: Option<String> },
+    RetentionChanged { retention_secs: Option<i32> },
 }
 
 impl From<RoomChangeJson> for RoomChangeResponse {
@@ -161,11 +378,17 @@ impl From<RoomChangeJson> for RoomChangeResponse {
             RoomChangeJson::UserJoined { related_user } => RoomChangeResponse::UserJoined { related_user },
             RoomChangeJson::UserLeft { related_user } => RoomChangeResponse::UserLeft { related_user },
             RoomChangeJson::UserInvited { related_user } => RoomChangeResponse::UserInvited { related_user },
+            RoomChangeJson::UserKicked { related_user } => RoomChangeResponse::UserKicked { related_user },
+            RoomChangeJson::UserRoleChanged { related_user, role } => RoomChangeResponse::UserRoleChanged { related_user, role },
+            RoomChangeJson::RoomRenamed { room_name } => RoomChangeResponse::RoomRenamed { room_name },
+            RoomChangeJson::ImageChanged { image_url } => RoomChangeResponse::ImageChanged { image_url },
+            RoomChangeJson::TopicChanged { topic } => RoomChangeResponse::TopicChanged { topic },
+            RoomChangeJson::RetentionChanged { retention_secs } => RoomChangeResponse::RetentionChanged { retention_secs },
         }
     }
 }
 
-/// A page of the chat timeline: the messages plus the deduplicated profiles of every user that
+/// A page of the chat timeline, newest message first: the messages plus the deduplicated profiles of every user that
 /// authored a message in this page, or is the original author quoted by a reply.
 ///
 /// Senders resolve even if they have since left the room, so the client can render every message
@@ -175,10 +398,185 @@ impl From<RoomChangeJson> for RoomChangeResponse {
 pub struct TimelinePageResponse {
     pub messages: Vec<MessageResponse>,
     pub senders: Vec<RoomMemberResponse>,
+    /// Pass as `before` for the older messages; `None` at the start of the visible history.
+    pub before_cursor: Option<String>,
+    /// Pass as `after` for the newer messages; `None` once the page reaches the newest one.
+    pub after_cursor: Option<String>,
 }
 
 impl ApiResponse for TimelinePageResponse {}
 
+/// A page of a thread, oldest reply first. `root` is repeated on every page so a client opening a
+/// thread from a notification does not need the main timeline to render its header; `senders`
+/// follows the same rules as on [`TimelinePageResponse`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadPageResponse {
+    pub root: MessageResponse,
+    pub cursor: Option<String>,
+    pub messages: Vec<MessageResponse>,
+    pub senders: Vec<RoomMemberResponse>,
+}
+
+impl ApiResponse for ThreadPageResponse {}
+
+/// One search hit. `snippet` is an excerpt of the matched text with each match wrapped in
+/// `<mark>…</mark>`; everything around the markers is HTML-escaped, so the snippet can be
+/// rendered as markup as is.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchHitResponse {
+    pub message: MessageResponse,
+    pub snippet: String,
+}
+
+impl ApiResponse for MessageSearchHitResponse {}
+
+impl From<MessageSearchRow> for MessageSearchHitResponse {
+    fn from(row: MessageSearchRow) -> Self {
+        MessageSearchHitResponse {
+            message: MessageResponse::from(row.message),
+            snippet: row.snippet,
+        }
+    }
+}
+
+/// A stored upload. A message attaches it by sending `mediaKey` back; `mediaUrl` is where room
+/// members download it from.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaUploadResponse {
+    pub media_key: String,
+    pub media_url: String,
+    pub media_type: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+}
+
+impl ApiResponse for MediaUploadResponse {}
+
+/// A reserved upload. The file goes to `uploadUrl` with `PUT`, carrying the announced
+/// `Content-Type`, before `expiresAt`; completing the upload afterwards makes `mediaKey` usable.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaUploadUrlResponse {
+    pub media_id: Uuid,
+    pub media_key: String,
+    pub upload_url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ApiResponse for MediaUploadUrlResponse {}
+
+/// A short-lived link that downloads an upload straight from object storage.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDownloadUrlResponse {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ApiResponse for MediaDownloadUrlResponse {}
+
+/// A message the caller scheduled. Leaves the list once it is sent; one that could not be sent
+/// stays, with `failedAt` and `failure` saying why, until the caller cancels it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledMessageResponse {
+    pub scheduled_message_id: Uuid,
+    pub chat_room_id: Uuid,
+    pub msg_body: ScheduledBodyResponse,
+    pub msg_type: MsgType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_root_id: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl ApiResponse for ScheduledMessageResponse {}
+
+impl From<ScheduledMessageRow> for ScheduledMessageResponse {
+    fn from(row: ScheduledMessageRow) -> Self {
+        ScheduledMessageResponse {
+            scheduled_message_id: row.scheduled_message_id,
+            chat_room_id: row.chat_room_id,
+            msg_body: ScheduledBodyResponse::from(row.msg_body.0),
+            msg_type: row.msg_type,
+            thread_root_id: row.thread_root_id,
+            send_at: row.send_at,
+            created_at: row.created_at,
+            failed_at: row.failed_at,
+            failure: row.failure,
+        }
+    }
+}
+
+/// A scheduled body, in the shape the client sent it: untagged like the `msgBody` of
+/// `POST /send-msg`, not tagged like the stored [`ScheduledBodyJson`].
+#[derive(Debug, Serialize)]
+#[serde(untagged, rename_all_fields = "camelCase")]
+pub enum ScheduledBodyResponse {
+    Text {
+        text: String,
+    },
+    Media {
+        media_url: String,
+        media_type: String,
+        mime_type: Option<String>,
+        alt_text: Option<String>,
+    },
+    Reply {
+        reply_msg_id: Uuid,
+        reply_text: String,
+    },
+    UploadedMedia {
+        media_key: String,
+        alt_text: Option<String>,
+    },
+    Poll {
+        question: String,
+        options: Vec<String>,
+        multiple_choice: bool,
+        closes_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl From<ScheduledBodyJson> for ScheduledBodyResponse {
+    fn from(stored: ScheduledBodyJson) -> Self {
+        match stored {
+            ScheduledBodyJson::Text { text } => ScheduledBodyResponse::Text { text },
+            ScheduledBodyJson::Media {
+                media_url,
+                media_type,
+                mime_type,
+                alt_text,
+            } => ScheduledBodyResponse::Media {
+                media_url,
+                media_type,
+                mime_type,
+                alt_text,
+            },
+            ScheduledBodyJson::Reply { reply_msg_id, reply_text } => ScheduledBodyResponse::Reply { reply_msg_id, reply_text },
+            ScheduledBodyJson::UploadedMedia { media_key, alt_text } => ScheduledBodyResponse::UploadedMedia { media_key, alt_text },
+            ScheduledBodyJson::Poll {
+                question,
+                options,
+                multiple_choice,
+                closes_at,
+            } => ScheduledBodyResponse::Poll {
+                question,
+                options,
+                multiple_choice,
+                closes_at,
+            },
+        }
+    }
+}
+
 /// The caller's current position in their notification stream, for a client deciding whether it
 /// needs to replay.
 #[derive(Debug, Serialize)]
@@ -188,3 +586,42 @@ pub struct NotificationCursorResponse {
 }
 
 impl ApiResponse for NotificationCursorResponse {}
+
+/// The answer to one WebSocket command, sent down the same socket as the events.
+///
+/// `type` is `CommandOk` or `CommandError`, names no [`NotificationEvent`](crate::broadcast::NotificationEvent)
+/// uses, so a client tells replies and events apart by the discriminator it already switches on.
+/// `id` echoes the command's correlation id; a command sent without one gets no `CommandOk`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandReplyFrame {
+    pub v: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub outcome: CommandOutcome,
+}
+
+impl ApiResponse for CommandReplyFrame {}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum CommandOutcome {
+    /// `result` is what the matching REST endpoint returns, and is omitted where that is empty.
+    CommandOk {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        result: Option<CommandResult>,
+    },
+    /// The same body, including `errorCode`, that the matching REST endpoint would have failed
+    /// with.
+    CommandError { error: ErrorResponse },
+}
+
+/// The payload of a `CommandOk`, one shape per command that returns something.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum CommandResult {
+    Message(Box<MessageResponse>),
+    Cursor(NotificationCursorResponse),
+    Events(Vec<Notification>),
+}
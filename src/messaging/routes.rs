@@ -1,9 +1,21 @@
 use crate::core::AppState;
-use crate::messaging::handler::{get_latest_notification_events, get_notification_cursor, handle_send_message, stream_server_events, websocket_server_events};
+use crate::messaging::handler::{
+    get_latest_notification_events, get_notification_cursor, handle_add_reaction, handle_cancel_scheduled_message, handle_close_poll,
+    handle_complete_media_upload, handle_delete_message, handle_download_media, handle_edit_message, handle_forward_message, handle_list_scheduled_messages,
+    handle_media_download_url, handle_mentions, handle_remove_reaction, handle_reserve_media_upload, handle_retract_poll_vote, handle_schedule_message,
+    handle_search_messages, handle_send_message, handle_typing, handle_upload_media, handle_vote_poll, stream_server_events, websocket_server_events,
+};
+use crate::messaging::model::MediaKind;
 use axum::Router;
-use axum::routing::{any, get, post};
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{any, delete, get, patch, post};
 use std::sync::Arc;
 
+/// Body limit for media uploads: the largest file any kind allows, plus room for the multipart
+/// framing. Replaces the global limit for this route only; the per-kind limit is checked once the
+/// upload has been sniffed.
+const MEDIA_UPLOAD_BODY_LIMIT: usize = MediaKind::Video.max_size() + 64 * 1024;
+
 pub fn create_messaging_routes() -> Router<Arc<AppState>> {
     Router::new() //add new routes here
         .route("/notifications", get(get_latest_notification_events))
@@ -11,4 +23,31 @@ pub fn create_messaging_routes() -> Router<Arc<AppState>> {
         .route("/sse", get(stream_server_events))
         .route("/wss", any(websocket_server_events))
         .route("/send-msg", post(handle_send_message))
+        .route("/scheduled-messages", post(handle_schedule_message).get(handle_list_scheduled_messages))
+        .route("/scheduled-messages/{scheduled_message_id}", delete(handle_cancel_scheduled_message))
+        .route("/messages/search", get(handle_search_messages))
+        .route("/mentions", get(handle_mentions))
+        .route(
+            "/rooms/{room_id}/messages/{message_id}",
+            patch(handle_edit_message).delete(handle_delete_message),
+        )
+        .route(
+            "/rooms/{room_id}/messages/{message_id}/reactions",
+            post(handle_add_reaction).delete(handle_remove_reaction),
+        )
+        .route(
+            "/rooms/{room_id}/messages/{message_id}/poll/votes",
+            post(handle_vote_poll).delete(handle_retract_poll_vote),
+        )
+        .route("/rooms/{room_id}/messages/{message_id}/poll/close", post(handle_close_poll))
+        .route("/rooms/{room_id}/messages/{message_id}/forward", post(handle_forward_message))
+        .route("/rooms/{room_id}/typing", post(handle_typing))
+        .route(
+            "/rooms/{room_id}/media",
+            post(handle_upload_media).layer(DefaultBodyLimit::max(MEDIA_UPLOAD_BODY_LIMIT)),
+        )
+        .route("/rooms/{room_id}/media/uploads", post(handle_reserve_media_upload))
+        .route("/rooms/{room_id}/media/{media_id}", get(handle_download_media))
+        .route("/rooms/{room_id}/media/{media_id}/complete", post(handle_complete_media_upload))
+        .route("/rooms/{room_id}/media/{media_id}/url", get(handle_media_download_url))
 }
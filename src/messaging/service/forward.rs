@@ -0,0 +1,229 @@
+//! Forwarding a message into other rooms — the send side of the share sheet that
+//! [`ShareService`](crate::rooms::ShareService) lists the targets for.
+
+use crate::core::Service;
+use crate::core::errors::{AppError, ErrorResponse};
+use crate::messaging::ChatRepository;
+use crate::messaging::MessageService;
+use crate::messaging::entity::{ForwardedFromJson, MessageBodyJson, MessageRow, TextJson};
+use crate::messaging::request::ForwardTargetRequest;
+use crate::messaging::response::ForwardOutcome;
+use crate::rooms::model::{RetentionPolicy, RoomType};
+use crate::rooms::request::NewRoomRequest;
+use crate::rooms::{RoomNotifier, RoomRepository, RoomService};
+use crate::users::UserRepository;
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Copies a message into the rooms a user picked from their share targets.
+///
+/// Depends on [`RoomService`] and [`MessageService`] for the reason given on
+/// [`AdminService`](crate::admin::AdminService): creating a 1-1 room and posting into a room are
+/// use cases with their own transactions and broadcasts, and forwarding must not grow a second copy
+/// of either. See [`crate::core::Service`].
+#[derive(Clone)]
+pub struct ForwardService {
+    rooms: RoomRepository,
+    chats: ChatRepository,
+    users: UserRepository,
+    notifier: RoomNotifier,
+    room_service: RoomService,
+    message_service: MessageService,
+    retention: RetentionPolicy,
+}
+
+impl Service for ForwardService {
+    const NAME: &'static str = "ForwardService";
+}
+
+impl ForwardService {
+    pub fn new(
+        rooms: RoomRepository,
+        chats: ChatRepository,
+        users: UserRepository,
+        notifier: RoomNotifier,
+        room_service: RoomService,
+        message_service: MessageService,
+        retention: RetentionPolicy,
+    ) -> Self {
+        Self {
+            rooms,
+            chats,
+            users,
+            notifier,
+            room_service,
+            message_service,
+            retention,
+        }
+    }
+
+    /// Forwards a message of `room_id` into every target, returning what became of each target in
+    /// request order.
+    ///
+    /// Every target is checked before anything is posted, so one the caller may not use fails the
+    /// request with nothing sent: the caller must be in each room target, and each user target must
+    /// be a friend. A friend without a 1-1 room — an "inactive" share target — gets one created
+    /// first, exactly as `POST /rooms/create-room` would. Creating that room and posting into each
+    /// room are separate transactions, so a failure there fails only the targets it concerns and
+    /// the rest are still forwarded. Targets that resolve to the same room are posted into once and
+    /// share its outcome.
+    pub async fn forward_message(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        message_id: Uuid,
+        targets: Vec<ForwardTargetRequest>,
+    ) -> Result<Vec<ForwardOutcome>, AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        if context.find_member(&client_id).is_none() {
+            return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+        }
+        let original = self.chats.fetch_message_by_id(&message_id, &room_id).await?;
+        // Like the timeline, treat a message past the room's retention as gone even before the
+        // reaper took it.
+        let retention_secs = self.rooms.select_room_retention(&room_id).await?;
+        if self
+            .retention
+            .cutoff(retention_secs, Utc::now())
+            .is_some_and(|until| original.created_at <= until)
+        {
+            return Err(AppError::NotFound("Message has expired.".to_string()));
+        }
+        let msg_body = forwarded_body(&original)?;
+        let forwarded_from = forwarded_from(&original);
+
+        let mut resolved: Vec<ResolvedTarget> = Vec::with_capacity(targets.len());
+        for target in targets {
+            match target {
+                ForwardTargetRequest::Room { room_id } => {
+                    let context = self.notifier.room_context(&room_id).await?;
+                    if context.find_member(&client_id).is_none() {
+                        return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+                    }
+                    resolved.push(ResolvedTarget::Room(room_id));
+                }
+                ForwardTargetRequest::User { user_id } => match self.room_service.find_existing_single_room(&client_id, &user_id).await? {
+                    Some(room_id) => resolved.push(ResolvedTarget::Room(room_id)),
+                    None => {
+                        if user_id == client_id || !self.users.are_friends(&client_id, &user_id).await? {
+                            return Err(AppError::Forbidden("Messages can only be forwarded to friends.".to_string()));
+                        }
+                        resolved.push(ResolvedTarget::NewSingleRoom(user_id));
+                    }
+                },
+            }
+        }
+
+        let mut created: HashMap<Uuid, Result<Uuid, ErrorResponse>> = HashMap::new();
+        let mut posted: HashMap<Uuid, ForwardOutcome> = HashMap::new();
+        let mut outcomes = Vec::with_capacity(resolved.len());
+        for target in resolved {
+            let target_room_id = match target {
+                ResolvedTarget::Room(room_id) => room_id,
+                ResolvedTarget::NewSingleRoom(user_id) => {
+                    let room = match created.get(&user_id) {
+                        Some(room) => room.clone(),
+                        None => {
+                            let room = self.create_single_room(client_id, user_id).await;
+                            created.insert(user_id, room.clone());
+                            room
+                        }
+                    };
+                    match room {
+                        Ok(room_id) => room_id,
+                        Err(error) => {
+                            outcomes.push(ForwardOutcome::ForwardFailed { error });
+                            continue;
+                        }
+                    }
+                }
+            };
+            let outcome = match posted.get(&target_room_id) {
+                Some(outcome) => outcome.clone(),
+                None => {
+                    let outcome = match self
+                        .message_service
+                        .forward_message(client_id, target_room_id, msg_body.clone(), forwarded_from.clone())
+                        .await
+                    {
+                        Ok(message) => ForwardOutcome::Forwarded { message: Box::new(message) },
+                        Err(err) => ForwardOutcome::ForwardFailed {
+                            error: err.into_error_response().1,
+                        },
+                    };
+                    posted.insert(target_room_id, outcome.clone());
+                    outcome
+                }
+            };
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
+    }
+
+    /// Creates the 1-1 room of `client_id` and `user_id` for a forward, as its outcome would report
+    /// a failure.
+    async fn create_single_room(&self, client_id: Uuid, user_id: Uuid) -> Result<Uuid, ErrorResponse> {
+        self.room_service
+            .create_room(
+                client_id,
+                NewRoomRequest {
+                    room_type: RoomType::Single,
+                    room_name: None,
+                    invited_users: vec![client_id, user_id],
+                    first_message: None,
+                },
+            )
+            .await
+            .map(|room| room.id)
+            .map_err(|err| err.into_error_response().1)
+    }
+}
+
+/// A forward target once checked: a room the caller is in, or a friend a 1-1 room has to be
+/// created with first.
+enum ResolvedTarget {
+    Room(Uuid),
+    NewSingleRoom(Uuid),
+}
+
+/// The body a forward of `original` gets.
+///
+/// Mentions are dropped, since they named members of the original's room. A reply loses its quote
+/// for the same reason and is forwarded as its text. Uploads belong to the room they were sent to
+/// and cannot be read from any other, so media is only forwarded when it is linked by URL. A poll's
+/// votes belong to its room too, and a copy without them would be a different poll.
+fn forwarded_body(original: &MessageRow) -> Result<MessageBodyJson, AppError> {
+    if original.deleted_at.is_some() {
+        return Err(AppError::NotFound("Message was deleted.".to_string()));
+    }
+    match &original.msg_body.0 {
+        MessageBodyJson::Text(text) => Ok(MessageBodyJson::Text(TextJson {
+            text: text.text.clone(),
+            mentions: Vec::new(),
+        })),
+        MessageBodyJson::Media(media) if media.media_key.is_none() => Ok(MessageBodyJson::Media(media.clone())),
+        MessageBodyJson::Media(_) => Err(AppError::Validation(
+            "Uploaded media cannot be forwarded; upload it to the target room instead.".to_string(),
+        )),
+        MessageBodyJson::Reply(reply) => Ok(MessageBodyJson::Text(TextJson {
+            text: reply.reply_text.clone(),
+            mentions: Vec::new(),
+        })),
+        MessageBodyJson::RoomChange(_) => Err(AppError::Validation("Room changes cannot be forwarded.".to_string())),
+        MessageBodyJson::Poll(_) => Err(AppError::Validation("Polls cannot be forwarded.".to_string())),
+    }
+}
+
+/// What a forward of `original` points back to: the original itself, or — when that is a forward
+/// too — the message it was forwarded from, so a chain of forwards still credits the author.
+fn forwarded_from(original: &MessageRow) -> ForwardedFromJson {
+    match &original.forwarded_from {
+        Some(forwarded_from) => forwarded_from.0.clone(),
+        None => ForwardedFromJson {
+            message_id: original.message_id,
+            sender_id: original.sender_id,
+            created_at: original.created_at,
+        },
+    }
+}
@@ -0,0 +1,331 @@
+//! Files uploaded into a room.
+//!
+//! An upload belongs to the room it was sent to: it is stored under that room's prefix, only the
+//! room's current members can download it, and only its uploader can attach it to a message —
+//! in that room and no other, which `MessageService` checks when the message is sent.
+//!
+//! Files too large to stream through the API go to object storage directly, through presigned
+//! URLs. The server never sees those bytes arrive, so such an upload is only *reserved* until the
+//! client completes it, and completing it means checking what actually arrived against what was
+//! announced — existence, size, stored content type and the content itself. The file is sent to
+//! an upload key of its own and copied to its `mediaKey` on completion, and it is the copy that is
+//! checked and served: the upload URL stays valid for a while after completion, but nothing it
+//! writes is ever used.
+//!
+//! An upload that no message attaches is deleted after a day, unless a scheduled message is still
+//! waiting to attach it.
+
+use crate::core::Service;
+use crate::core::errors::AppError;
+use crate::messaging::ChatRepository;
+use crate::messaging::entity::RoomMediaRow;
+use crate::messaging::model::{MediaContent, MediaKind, SNIFF_LENGTH, media_download_path, sniff_media};
+use crate::messaging::request::MediaUploadUrlRequest;
+use crate::messaging::response::{MediaDownloadUrlResponse, MediaUploadResponse, MediaUploadUrlResponse};
+use crate::object_storage::ObjectStorage;
+use crate::rooms::RoomNotifier;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::Method;
+use std::time::Duration;
+use tokio::time;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// How long a presigned upload URL accepts the file.
+const UPLOAD_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// How long a presigned download URL works. Short, because whoever holds it can download without
+/// being a member.
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long a reservation may stay incomplete before the sweep deletes it. Well past
+/// [`UPLOAD_URL_TTL`], so an upload that started just before its URL expired can still finish.
+const RESERVATION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a completed upload may wait for a message to attach it before the sweep deletes it.
+const ATTACHMENT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often abandoned reservations and unattached uploads are looked for.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Storing uploads and serving them back to the room.
+#[derive(Clone)]
+pub struct MediaService {
+    chats: ChatRepository,
+    notifier: RoomNotifier,
+    storage: ObjectStorage,
+}
+
+impl Service for MediaService {
+    const NAME: &'static str = "MediaService";
+}
+
+impl MediaService {
+    pub fn new(chats: ChatRepository, notifier: RoomNotifier, storage: ObjectStorage) -> Self {
+        Self { chats, notifier, storage }
+    }
+
+    /// Stores `content` for the room after checking what it actually is. The type the client
+    /// declared for the multipart field is never consulted; the limit is the one for the sniffed
+    /// kind.
+    pub async fn upload_media(&self, client_id: Uuid, room_id: Uuid, content: Bytes) -> Result<MediaUploadResponse, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let (kind, mime_type) = sniff_media(&content).ok_or_else(|| AppError::Validation("Unsupported media format.".to_string()))?;
+        check_size(kind, content.len())?;
+
+        let mut media = new_media(client_id, room_id, mime_type.to_string(), content.len() as i64);
+        media.completed_at = Some(media.created_at);
+
+        if let Err(err) = self.storage.insert_object(&media.object_key, content, mime_type).await {
+            error!(%room_id, error = %err, "Unable to store media");
+            return Err(AppError::S3("Unable to store the upload.".to_string()));
+        }
+        if let Err(err) = self.chats.insert_media(&media).await {
+            // Without its row the object is unreachable; removing it is only tidying up.
+            if let Err(err) = self.storage.delete_object(&media.object_key).await {
+                error!(%room_id, error = %err, "Unable to delete unrecorded media");
+            }
+            return Err(err.into());
+        }
+
+        Ok(uploaded(media))
+    }
+
+    /// Reserves an upload that the client sends to object storage itself, and returns the URL to
+    /// send it to. The announced type must be one a direct upload would be accepted as, and the
+    /// announced size within that type's limit.
+    pub async fn reserve_upload(&self, client_id: Uuid, room_id: Uuid, request: MediaUploadUrlRequest) -> Result<MediaUploadUrlResponse, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let kind = MediaKind::of_accepted_mime_type(&request.mime_type).ok_or_else(|| AppError::Validation("Unsupported media format.".to_string()))?;
+        check_size(kind, request.size_bytes as usize)?;
+
+        let media = new_media(client_id, room_id, request.mime_type, request.size_bytes);
+        let upload_url = self
+            .storage
+            .presigned_url(&media.upload_key(), Method::PUT, UPLOAD_URL_TTL)
+            .await
+            .map_err(|err| {
+                error!(%room_id, error = %err, "Unable to presign an upload");
+                AppError::S3("Unable to prepare the upload.".to_string())
+            })?;
+        self.chats.insert_media(&media).await?;
+
+        Ok(MediaUploadUrlResponse {
+            media_id: media.media_id,
+            media_key: media.object_key,
+            upload_url,
+            expires_at: media.created_at + UPLOAD_URL_TTL,
+        })
+    }
+
+    /// Checks a reserved upload against what arrived in object storage and makes it usable.
+    ///
+    /// What arrived is first copied from the upload key to the `mediaKey`, and only the copy is
+    /// checked, so a `PUT` through the upload URL racing with this or coming after it cannot swap
+    /// the file. An upload that has not arrived yet can be completed again later. One that
+    /// arrived but breaks its announcement — other size, other stored type, or content that is
+    /// not what it claims to be — is deleted, reservation and all; the client starts over.
+    /// Completing twice returns the same answer.
+    pub async fn complete_upload(&self, client_id: Uuid, room_id: Uuid, media_id: Uuid) -> Result<MediaUploadResponse, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let mut media = self
+            .chats
+            .fetch_media(&room_id, &media_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Media not found.".to_string()))?;
+        if media.uploaded_by != client_id {
+            return Err(AppError::Forbidden("Only the uploader can complete an upload.".to_string()));
+        }
+        if media.completed_at.is_some() {
+            return Ok(uploaded(media));
+        }
+
+        // A completion that failed after the copy finds the upload key gone and the copy in place.
+        let upload_key = media.upload_key();
+        self.storage.copy_object(&upload_key, &media.object_key).await.map_err(|err| {
+            error!(%room_id, %media_id, error = %err, "Unable to copy an upload");
+            AppError::S3("Unable to check the upload.".to_string())
+        })?;
+        if let Err(err) = self.storage.delete_object(&upload_key).await {
+            warn!(%room_id, %media_id, error = %err, "Unable to delete a copied upload");
+        }
+
+        let stat = self
+            .storage
+            .stat_object(&media.object_key)
+            .await
+            .map_err(|err| {
+                error!(%room_id, %media_id, error = %err, "Unable to inspect an upload");
+                AppError::S3("Unable to check the upload.".to_string())
+            })?
+            .ok_or_else(|| AppError::Validation("The upload has not arrived yet.".to_string()))?;
+
+        if stat.size != media.size_bytes as u64 {
+            return self.reject_upload(&media, "The upload does not have the announced size.").await;
+        }
+        if stat.content_type.as_deref() != Some(media.mime_type.as_str()) {
+            return self.reject_upload(&media, "The upload was not stored with the announced content type.").await;
+        }
+        let prefix = self.storage.get_object_prefix(&media.object_key, SNIFF_LENGTH as u64).await.map_err(|err| {
+            error!(%room_id, %media_id, error = %err, "Unable to read an upload");
+            AppError::S3("Unable to check the upload.".to_string())
+        })?;
+        if sniff_media(&prefix).map(|(_, mime_type)| mime_type) != Some(media.mime_type.as_str()) {
+            return self.reject_upload(&media, "The upload's content does not match the announced type.").await;
+        }
+
+        let completed_at = Utc::now();
+        if !self.chats.complete_media(&media_id, completed_at).await? {
+            return Err(AppError::NotFound("The upload reservation has expired.".to_string()));
+        }
+        media.completed_at = Some(completed_at);
+        Ok(uploaded(media))
+    }
+
+    /// Deletes an upload that failed completion, then reports why.
+    async fn reject_upload(&self, media: &RoomMediaRow, reason: &str) -> Result<MediaUploadResponse, AppError> {
+        self.chats.delete_media(&media.media_id).await?;
+        if let Err(err) = self.storage.delete_object(&media.object_key).await {
+            error!(media_id = %media.media_id, error = %err, "Unable to delete a rejected upload");
+        }
+        Err(AppError::Validation(reason.to_string()))
+    }
+
+    /// An upload of the room, for a caller who is in it now. Former members lose access together
+    /// with the rest of the room's history.
+    pub async fn download_media(&self, client_id: Uuid, room_id: Uuid, media_id: Uuid) -> Result<MediaContent, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let media = self.completed_media(&room_id, &media_id).await?;
+
+        let content = self.storage.get_object(&media.object_key).await.map_err(|err| {
+            error!(%room_id, %media_id, error = %err, "Unable to load media");
+            AppError::S3("Unable to load the upload.".to_string())
+        })?;
+
+        Ok(MediaContent {
+            mime_type: media.mime_type,
+            content: content.to_bytes(),
+        })
+    }
+
+    /// A link that downloads an upload straight from object storage, for the same callers
+    /// [`Self::download_media`] serves.
+    pub async fn download_url(&self, client_id: Uuid, room_id: Uuid, media_id: Uuid) -> Result<MediaDownloadUrlResponse, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+        let media = self.completed_media(&room_id, &media_id).await?;
+
+        let url = self
+            .storage
+            .presigned_url(&media.object_key, Method::GET, DOWNLOAD_URL_TTL)
+            .await
+            .map_err(|err| {
+                error!(%room_id, %media_id, error = %err, "Unable to presign a download");
+                AppError::S3("Unable to prepare the download.".to_string())
+            })?;
+        Ok(MediaDownloadUrlResponse {
+            url,
+            expires_at: Utc::now() + DOWNLOAD_URL_TTL,
+        })
+    }
+
+    /// Deletes reservations that were never completed and completed uploads that no message
+    /// attached in time, and whatever was stored for them.
+    pub async fn sweep_uploads(&self) -> Result<(), AppError> {
+        let reserved_before: DateTime<Utc> = Utc::now() - RESERVATION_TTL;
+        let reservations = self.chats.delete_stale_media_reservations(reserved_before).await?;
+        if !reservations.is_empty() {
+            info!(uploads = reservations.len(), "Deleted abandoned upload reservations");
+        }
+
+        let completed_before: DateTime<Utc> = Utc::now() - ATTACHMENT_TTL;
+        let unattached = self.chats.delete_unattached_media(completed_before).await?;
+        if !unattached.is_empty() {
+            info!(uploads = unattached.len(), "Deleted uploads no message attached");
+        }
+
+        // Either key may hold a file: a reservation's, depending on how far a completion got, and
+        // a completed upload's, when the client `PUT` again before its URL expired.
+        let keys = reservations
+            .into_iter()
+            .chain(unattached)
+            .flat_map(|media| [media.upload_key(), media.object_key]);
+        for key in keys {
+            if let Err(err) = self.storage.delete_object(&key).await {
+                warn!(object_key = %key, error = %err, "Unable to delete an abandoned upload");
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs [`Self::sweep_uploads`] forever. Spawned once by the builder, which keeps the
+    /// handle so shutdown can abort it.
+    pub async fn run_sweeper(self) {
+        let mut interval = time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.sweep_uploads().await {
+                warn!(error = %err, "Sweeping uploads failed");
+            }
+        }
+    }
+
+    /// An upload of the room that is ready for use. A reservation still waiting for its file
+    /// reads as missing.
+    async fn completed_media(&self, room_id: &Uuid, media_id: &Uuid) -> Result<RoomMediaRow, AppError> {
+        self.chats
+            .fetch_media(room_id, media_id)
+            .await?
+            .filter(|media| media.completed_at.is_some())
+            .ok_or_else(|| AppError::NotFound("Media not found.".to_string()))
+    }
+
+    async fn ensure_member(&self, client_id: &Uuid, room_id: &Uuid) -> Result<(), AppError> {
+        let context = self.notifier.room_context(room_id).await?;
+        if context.find_member(client_id).is_none() {
+            return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A new upload of `room_id` by `uploaded_by`, not yet complete.
+fn new_media(uploaded_by: Uuid, room_id: Uuid, mime_type: String, size_bytes: i64) -> RoomMediaRow {
+    let media_id = Uuid::new_v4();
+    RoomMediaRow {
+        media_id,
+        room_id,
+        object_key: format!("rooms/{room_id}/media/{media_id}"),
+        uploaded_by,
+        mime_type,
+        size_bytes,
+        created_at: Utc::now(),
+        completed_at: None,
+        message_id: None,
+    }
+}
+
+fn check_size(kind: MediaKind, size: usize) -> Result<(), AppError> {
+    if size > kind.max_size() {
+        return Err(AppError::Validation(format!(
+            "Uploads of type {} are limited to {} MiB.",
+            kind.as_str(),
+            kind.max_size() / (1024 * 1024)
+        )));
+    }
+    Ok(())
+}
+
+fn uploaded(media: RoomMediaRow) -> MediaUploadResponse {
+    MediaUploadResponse {
+        media_url: media_download_path(&media.room_id, &media.media_id),
+        media_type: MediaKind::of_mime_type(&media.mime_type).as_str().to_string(),
+        media_key: media.object_key,
+        mime_type: media.mime_type,
+        size_bytes: media.size_bytes,
+    }
+}
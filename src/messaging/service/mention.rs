@@ -0,0 +1,45 @@
+use crate::core::Service;
+use crate::core::cursor::{CursorResults, next_cursor};
+use crate::core::errors::AppError;
+use crate::messaging::ChatRepository;
+use crate::messaging::model::MentionCursor;
+use crate::messaging::response::MessageResponse;
+use crate::rooms::model::RetentionPolicy;
+use uuid::Uuid;
+
+/// The messages that mention the caller, across their rooms.
+#[derive(Clone)]
+pub struct MentionService {
+    chats: ChatRepository,
+    retention: RetentionPolicy,
+}
+
+impl Service for MentionService {
+    const NAME: &'static str = "MentionService";
+}
+
+impl MentionService {
+    pub fn new(chats: ChatRepository, retention: RetentionPolicy) -> Self {
+        Self { chats, retention }
+    }
+
+    /// One page of the caller's mentions, newest first. `@room` counts as a mention of everyone
+    /// in the room. As with search, a room the caller has left drops out of the feed.
+    pub async fn mentions(&self, client_id: Uuid, cursor: MentionCursor, page_size: usize) -> Result<CursorResults<MessageResponse>, AppError> {
+        let mut messages = self
+            .chats
+            .fetch_mentions(&client_id, self.retention.default_secs(), cursor, (page_size + 1) as i64)
+            .await?;
+
+        let cursor = next_cursor(&mut messages, page_size, |last| MentionCursor {
+            last_created_at: Some(last.created_at),
+            last_message_id: Some(last.message_id),
+        })
+        .map_err(|e| AppError::Processing(format!("Cursor encoding failed: {}", e)))?;
+
+        Ok(CursorResults {
+            cursor,
+            content: messages.into_iter().map(MessageResponse::from).collect(),
+        })
+    }
+}
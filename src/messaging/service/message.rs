@@ -1,16 +1,23 @@
-use crate::broadcast::NotificationEvent::ChatMessage;
+use crate::broadcast::NotificationEvent::{ChatMessage, MessageDeleted, MessageEdited, ReactionChanged};
 use crate::core::errors::AppError;
 use crate::core::{Database, Service};
 use crate::messaging::ChatRepository;
-use crate::messaging::entity::{MessageBodyJson, MessageRow, RepliedMessageJson, ReplyJson};
-use crate::messaging::request::{ReplyBodyRequest, SendMessageBodyRequest, SendMessageRequest};
-use crate::messaging::response::MessageResponse;
+use crate::messaging::entity::{ForwardedFromJson, MediaJson, MentionJson, MessageBodyJson, MessageRow, RepliedMessageJson, ReplyJson, TextJson};
+use crate::messaging::model::{MediaKind, MsgType, media_download_path, parse_mentions};
+use crate::messaging::request::{EditMessageRequest, ReplyBodyRequest, SendMessageBodyRequest, SendMessageRequest, UploadedMediaBodyRequest};
+use crate::messaging::response::{MessageResponse, ThreadSummaryResponse};
+use crate::notify_room;
+use crate::object_storage::ObjectStorage;
 use crate::rooms::entity::LastMessagePreviewJson;
-use crate::rooms::response::LastMessagePreviewResponse;
+use crate::rooms::model::{Mentioned, RetentionPolicy, RoomContext};
+use crate::rooms::response::{LastMessagePreviewResponse, RoomMemberResponse, UnreadCountResponse};
 use crate::rooms::{RoomNotifier, RoomRepository};
+use chrono::Utc;
+use std::collections::HashMap;
+use tracing::{error, warn};
 use uuid::Uuid;
 
-/// Sending chat messages.
+/// Sending, editing and deleting chat messages, and reacting to them.
 #[derive(Clone)]
 pub struct MessageService {
     /// Present because the message insert and the room's preview-text update must be one
@@ -19,6 +26,10 @@ pub struct MessageService {
     rooms: RoomRepository,
     chats: ChatRepository,
     notifier: RoomNotifier,
+    /// Where the uploads attached to messages are, so deleting a message can delete its upload.
+    storage: ObjectStorage,
+    /// For the unread counters sent with each message, which leave out expired messages.
+    retention: RetentionPolicy,
 }
 
 impl Service for MessageService {
@@ -26,8 +37,15 @@ impl Service for MessageService {
 }
 
 impl MessageService {
-    pub fn new(db: Database, rooms: RoomRepository, chats: ChatRepository, notifier: RoomNotifier) -> Self {
-        Self { db, rooms, chats, notifier }
+    pub fn new(db: Database, rooms: RoomRepository, chats: ChatRepository, notifier: RoomNotifier, storage: ObjectStorage, retention: RetentionPolicy) -> Self {
+        Self {
+            db,
+            rooms,
+            chats,
+            notifier,
+            storage,
+            retention,
+        }
     }
 
     pub async fn send_message(&self, message: SendMessageRequest, client_id: Uuid) -> Result<MessageResponse, AppError> {
@@ -42,26 +60,75 @@ impl MessageService {
         let sender_display_name = sender.display_name.clone();
         let sender_member = sender.clone();
 
+        // 2b. Idempotency — a retry of a send that already went through gets the stored message
+        //     back, and nobody is notified a second time.
+        if let Some(client_message_id) = &message.client_message_id
+            && let Some(stored) = self.chats.fetch_message_by_client_id(&client_id, client_message_id).await?
+        {
+            return already_sent(stored, &message.chat_room_id);
+        }
+
         // 3. Build message body
-        let msg_body = match message.msg_body.clone() {
-            SendMessageBodyRequest::Text(_) | SendMessageBodyRequest::Media(_) => MessageBodyJson::from(message.msg_body.clone()),
-            SendMessageBodyRequest::Reply(reply) => {
-                let reply = self
-                    .create_reply_message(&reply, &message.chat_room_id)
-                    .await
-                    .map_err(|err| AppError::Processing(format!("Can't create reply message: {}", err)))?;
-                MessageBodyJson::Reply(reply)
+        let mut msg_body = match message.msg_body.clone() {
+            SendMessageBodyRequest::Text(_) | SendMessageBodyRequest::Media(_) => MessageBodyJson::try_from(message.msg_body.clone())?,
+            SendMessageBodyRequest::Poll(poll) => {
+                // Checked here rather than on the request, so a scheduled poll is held to it when
+                // it is sent.
+                if poll.closes_at.is_some_and(|closes_at| closes_at <= Utc::now()) {
+                    return Err(AppError::Validation("A poll must close in the future.".to_string()));
+                }
+                MessageBodyJson::try_from(message.msg_body.clone())?
             }
+            SendMessageBodyRequest::Reply(reply) => MessageBodyJson::Reply(self.create_reply_message(&reply, &message.chat_room_id).await?),
+            SendMessageBodyRequest::UploadedMedia(upload) => MessageBodyJson::Media(self.attach_upload(&upload, client_id, &message.chat_room_id).await?),
         };
 
-        let entity = MessageRow::new(message.chat_room_id, client_id, msg_body);
+        // 3b. Mentions — found in the text, and only of people who are in the room
+        let mentions = resolve_mentions(&mut msg_body, &context)?;
+        let mentioned = mentioned_members(&mentions, &client_id);
+
+        let mut entity = MessageRow::new(message.chat_room_id, client_id, msg_body);
+        entity.client_message_id = message.client_message_id.clone();
+        if let Some(root_id) = message.thread_root_id {
+            self.ensure_thread_root(&root_id, &message.chat_room_id).await?;
+            entity.thread_root_id = Some(root_id);
+        }
 
         // 4. Generate preview text — display name from context, no DB call
-        let room_preview_text = generate_room_preview_text(&message, sender_display_name);
+        let room_preview_text = generate_room_preview_text(&entity.msg_body.0, sender_display_name);
 
         // 5. Single atomic transaction: insert message + update room state in one CTE round-trip
         let mut tx = self.db.begin().await?;
-        self.chats.insert_message(&mut *tx, &entity).await?;
+        if !self.chats.insert_message(&mut *tx, &entity).await? {
+            // A concurrent retry stored it between the check above and this insert.
+            tx.rollback().await?;
+            if let Some(client_message_id) = &entity.client_message_id
+                && let Some(stored) = self.chats.fetch_message_by_client_id(&client_id, client_message_id).await?
+            {
+                return already_sent(stored, &message.chat_room_id);
+            }
+            return Err(AppError::Processing("Message was neither stored nor found.".to_string()));
+        }
+        if let MessageBodyJson::Media(MediaJson {
+            media_key: Some(media_key), ..
+        }) = &entity.msg_body.0
+            && !self.chats.attach_media(&mut *tx, media_key, &entity.message_id).await?
+        {
+            tx.rollback().await?;
+            return Err(AppError::Validation("This upload is already attached to a message.".to_string()));
+        }
+        self.chats.insert_mentions(&mut *tx, &entity, &mentions).await?;
+        let thread = match entity.thread_root_id {
+            Some(root_id) => {
+                let reply_count = self.chats.record_thread_reply(&mut *tx, &root_id, entity.created_at).await?;
+                Some(ThreadSummaryResponse {
+                    root_message_id: root_id,
+                    reply_count,
+                    last_reply_at: entity.created_at,
+                })
+            }
+            None => None,
+        };
         self.rooms
             .apply_message_to_room(&mut tx, &message.chat_room_id, &room_preview_text, &entity.sender_id, entity.created_at)
             .await?;
@@ -69,27 +136,328 @@ impl MessageService {
 
         // 6. Broadcast to all room members
         let dto = MessageResponse::from(entity);
+        self.broadcast_message(&context, dto.clone(), room_preview_text, sender_member, thread, &mentioned)
+            .await;
+        Ok(dto)
+    }
+
+    /// Posts `msg_body` into `room_id` as the caller's forward of the message `forwarded_from`
+    /// describes. The caller must be in the target room; what may be forwarded, and whether they
+    /// could read the original, is for the caller of this to have checked.
+    ///
+    /// A forward carries no mentions — they named members of the original's room — so it only
+    /// pushes whoever the room's settings say.
+    pub async fn forward_message(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        msg_body: MessageBodyJson,
+        forwarded_from: ForwardedFromJson,
+    ) -> Result<MessageResponse, AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let sender = context
+            .find_member(&client_id)
+            .ok_or_else(|| AppError::Forbidden("User hasn't access to this room.".to_string()))?
+            .clone();
+
+        let mut entity = MessageRow::new(room_id, client_id, msg_body);
+        entity.forwarded_from = Some(sqlx::types::Json(forwarded_from));
+        let room_preview_text = generate_room_preview_text(&entity.msg_body.0, sender.display_name.clone());
+
+        let mut tx = self.db.begin().await?;
+        if !self.chats.insert_message(&mut *tx, &entity).await? {
+            // Only a taken `client_message_id` keeps a message out, and a forward has none.
+            tx.rollback().await?;
+            return Err(AppError::Processing("Forwarded message was not stored.".to_string()));
+        }
+        self.rooms
+            .apply_message_to_room(&mut tx, &room_id, &room_preview_text, &entity.sender_id, entity.created_at)
+            .await?;
+        tx.commit().await?;
+
+        let dto = MessageResponse::from(entity);
+        self.broadcast_message(&context, dto.clone(), room_preview_text, sender, None, &Mentioned::default())
+            .await;
+        Ok(dto)
+    }
+
+    /// Sends `ChatMessage` for a message just committed to the room, each member with their own
+    /// unread count in the room. Failing to count only costs the badges, not the delivery.
+    async fn broadcast_message(
+        &self,
+        context: &RoomContext,
+        message: MessageResponse,
+        room_preview_text: LastMessagePreviewJson,
+        sender: RoomMemberResponse,
+        thread: Option<ThreadSummaryResponse>,
+        mentioned: &Mentioned,
+    ) {
+        let room_id = message.chat_room_id;
+        let member_ids = context.member_ids();
+        let unread = match self.rooms.select_room_unread_counts(&room_id, &member_ids, self.retention.default_secs()).await {
+            Ok(rows) => rows.into_iter().map(|row| (row.user_id, UnreadCountResponse::from(row))).collect(),
+            Err(error) => {
+                warn!(%room_id, error = %error, "Failed to count unread messages for broadcast");
+                HashMap::new()
+            }
+        };
         self.notifier
-            .notify_users(
-                context.member_ids(),
+            .notify_room_message(
+                &room_id,
+                member_ids,
                 ChatMessage {
-                    message: dto.clone(),
+                    message,
                     room_preview_text: LastMessagePreviewResponse::from(room_preview_text),
-                    sender: sender_member,
+                    sender,
+                    thread,
+                    unread: None,
                 },
+                unread,
+                mentioned,
             )
             .await;
+    }
+
+    /// Rejects a thread reply whose root is missing, deleted, a room change, or itself a reply in
+    /// another thread.
+    async fn ensure_thread_root(&self, root_id: &Uuid, room_id: &Uuid) -> Result<(), AppError> {
+        let root = self.chats.fetch_message_by_id(root_id, room_id).await?;
+        if root.deleted_at.is_some() {
+            return Err(AppError::Validation("Cannot reply in the thread of a deleted message.".to_string()));
+        }
+        if root.thread_root_id.is_some() {
+            return Err(AppError::Validation("Threads cannot be nested; reply to the thread root instead.".to_string()));
+        }
+        if matches!(root.msg_body.0, MessageBodyJson::RoomChange(_)) {
+            return Err(AppError::Validation("Room changes cannot start a thread.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Replaces the text of a message the caller sent.
+    ///
+    /// Only `Text` and `Reply` messages carry editable text; media and room changes are rejected.
+    /// When the message is still the newest in the room, the room's preview is rewritten in the
+    /// same transaction so the room list never shows the pre-edit text.
+    pub async fn edit_message(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid, request: EditMessageRequest) -> Result<MessageResponse, AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let mut message = self.own_message(&context, client_id, room_id, message_id).await?;
+
+        let (mut msg_body, preview) = match message.msg_body.0 {
+            MessageBodyJson::Text(_) => (
+                MessageBodyJson::Text(TextJson {
+                    text: request.text.clone(),
+                    mentions: Vec::new(),
+                }),
+                LastMessagePreviewJson::Text {
+                    sender_username: sender_name(&context, &client_id),
+                    text: request.text,
+                },
+            ),
+            MessageBodyJson::Reply(reply) => (
+                MessageBodyJson::Reply(ReplyJson {
+                    reply_text: request.text.clone(),
+                    mentions: Vec::new(),
+                    ..reply
+                }),
+                LastMessagePreviewJson::Reply {
+                    sender_username: sender_name(&context, &client_id),
+                    reply_text: request.text,
+                },
+            ),
+            _ => return Err(AppError::Validation("Only text messages and replies can be edited.".to_string())),
+        };
+        // Mentions follow the edited text, but nobody is pushed for one an edit added.
+        let mentions = resolve_mentions(&mut msg_body, &context)?;
+        let edited_at = Utc::now();
+        message.msg_body = sqlx::types::Json(msg_body);
+        message.edited_at = Some(edited_at);
+
+        let mut tx = self.db.begin().await?;
+        self.chats.update_message_body(&mut *tx, &message_id, &message.msg_body.0, edited_at).await?;
+        self.chats.delete_mentions(&mut *tx, &message_id).await?;
+        self.chats.insert_mentions(&mut *tx, &message, &mentions).await?;
+        let preview_changed = self.rooms.refresh_preview_if_latest(&mut tx, &room_id, message.created_at, &preview).await?;
+        tx.commit().await?;
+
+        let dto = MessageResponse::from(message);
+        notify_room!(
+            self.notifier,
+            &room_id,
+            MessageEdited {
+                message: dto.clone(),
+                room_preview_text: preview_changed.then(|| LastMessagePreviewResponse::from(preview)),
+            }
+        );
         Ok(dto)
     }
 
-    async fn create_reply_message(&self, msg: &ReplyBodyRequest, room_id: &Uuid) -> Result<ReplyJson, Box<dyn std::error::Error>> {
+    /// Deletes a message the caller sent, leaving a tombstone in the timeline.
+    pub async fn delete_message(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let message = self.own_message(&context, client_id, room_id, message_id).await?;
+        self.tombstone(&context, message).await
+    }
+
+    /// Deletes any message of a room on a moderator's behalf. Neither authorship nor membership is
+    /// checked — the caller's role is, by the admin handler in front of this.
+    pub async fn moderate_delete_message(&self, room_id: Uuid, message_id: Uuid) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let message = self.chats.fetch_message_by_id(&message_id, &room_id).await?;
+        if message.deleted_at.is_some() {
+            return Err(AppError::NotFound("Message was deleted.".to_string()));
+        }
+        self.tombstone(&context, message).await
+    }
+
+    /// Replaces `message` with its tombstone and tells the room. Shared by the author's own delete
+    /// and the moderator's. An upload the message attached is deleted with it.
+    async fn tombstone(&self, context: &RoomContext, mut message: MessageRow) -> Result<(), AppError> {
+        if matches!(message.msg_body.0, MessageBodyJson::RoomChange(_)) {
+            return Err(AppError::Validation("Room changes cannot be deleted.".to_string()));
+        }
+
+        let (room_id, message_id) = (message.chat_room_id, message.message_id);
+        let preview = LastMessagePreviewJson::Deleted {
+            sender_username: sender_name(context, &message.sender_id),
+        };
+        let deleted_at = Utc::now();
+
+        let mut tx = self.db.begin().await?;
+        self.chats.tombstone_message(&mut *tx, &message_id, deleted_at).await?;
+        self.chats.delete_reactions(&mut *tx, &message_id).await?;
+        if let Some(root_id) = &message.thread_root_id {
+            self.chats.recount_thread_replies(&mut *tx, root_id).await?;
+        }
+        let media = self.chats.delete_message_media(&mut *tx, &message_id).await?;
+        let preview_changed = self.rooms.refresh_preview_if_latest(&mut tx, &room_id, message.created_at, &preview).await?;
+        tx.commit().await?;
+
+        for key in media.into_iter().flat_map(|media| [media.upload_key(), media.object_key]) {
+            if let Err(err) = self.storage.delete_object(&key).await {
+                error!(%room_id, %message_id, error = %err, "Unable to delete the media of a deleted message");
+            }
+        }
+
+        message.msg_body = sqlx::types::Json(MessageRow::tombstone_body());
+        message.msg_type = MsgType::Text;
+        message.deleted_at = Some(deleted_at);
+        notify_room!(
+            self.notifier,
+            &room_id,
+            MessageDeleted {
+                message: MessageResponse::from(message),
+                room_preview_text: preview_changed.then(|| LastMessagePreviewResponse::from(preview)),
+            }
+        );
+        Ok(())
+    }
+
+    /// Adds the caller's reaction to a message. Reacting twice with the same emoji is a no-op.
+    ///
+    /// The message stays locked until the count is taken, so the count broadcast is the one this
+    /// reaction produced, and a message being deleted meanwhile does not keep the reaction.
+    pub async fn add_reaction(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid, emoji: String) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let mut tx = self.db.begin().await?;
+        let message = self.chats.lock_message(&mut tx, &message_id, &room_id).await?;
+        ensure_visible(&context, client_id, &message)?;
+        let changed = self.chats.insert_reaction(&mut *tx, &message_id, &client_id, &emoji).await?;
+        let count = self.chats.count_reactions(&mut *tx, &message_id, &emoji).await?;
+        tx.commit().await?;
+        if changed {
+            self.broadcast_reaction(client_id, room_id, message_id, emoji, true, count).await;
+        }
+        Ok(())
+    }
+
+    /// Removes the caller's reaction from a message. Removing one that does not exist is a no-op.
+    /// Locked like [`Self::add_reaction`].
+    pub async fn remove_reaction(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid, emoji: String) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        let mut tx = self.db.begin().await?;
+        let message = self.chats.lock_message(&mut tx, &message_id, &room_id).await?;
+        ensure_visible(&context, client_id, &message)?;
+        let changed = self.chats.delete_reaction(&mut *tx, &message_id, &client_id, &emoji).await?;
+        let count = self.chats.count_reactions(&mut *tx, &message_id, &emoji).await?;
+        tx.commit().await?;
+        if changed {
+            self.broadcast_reaction(client_id, room_id, message_id, emoji, false, count).await;
+        }
+        Ok(())
+    }
+
+    async fn broadcast_reaction(&self, user_id: Uuid, room_id: Uuid, message_id: Uuid, emoji: String, reacted: bool, count: i64) {
+        notify_room!(
+            self.notifier,
+            &room_id,
+            ReactionChanged {
+                room_id,
+                message_id,
+                user_id,
+                emoji,
+                reacted,
+                count,
+            }
+        );
+    }
+
+    /// Checks that the caller is in the room and that the message is there and not deleted — the
+    /// precondition for interacting with someone else's message.
+    async fn visible_message(&self, context: &RoomContext, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<MessageRow, AppError> {
+        if context.find_member(&client_id).is_none() {
+            return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+        }
+        let message = self.chats.fetch_message_by_id(&message_id, &room_id).await?;
+        if message.deleted_at.is_some() {
+            return Err(AppError::NotFound("Message was deleted.".to_string()));
+        }
+        Ok(message)
+    }
+
+    /// [`Self::visible_message`], plus the caller being the message's author.
+    async fn own_message(&self, context: &RoomContext, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<MessageRow, AppError> {
+        let message = self.visible_message(context, client_id, room_id, message_id).await?;
+        if message.sender_id != client_id {
+            return Err(AppError::Forbidden("Only the sender can change this message.".to_string()));
+        }
+        Ok(message)
+    }
+
+    /// The stored body for a message attaching an upload. Only the uploader may attach it, only
+    /// once it is complete, only in the room it was uploaded to — the room is what decides who
+    /// can download it — and only to one message, which takes the upload with it when deleted.
+    async fn attach_upload(&self, upload: &UploadedMediaBodyRequest, client_id: Uuid, room_id: &Uuid) -> Result<MediaJson, AppError> {
+        let media = self
+            .chats
+            .fetch_media_by_key(&upload.media_key)
+            .await?
+            .filter(|media| &media.room_id == room_id && media.uploaded_by == client_id && media.completed_at.is_some())
+            .ok_or_else(|| AppError::Validation("Unknown media key for this room.".to_string()))?;
+        if media.message_id.is_some() {
+            return Err(AppError::Validation("This upload is already attached to a message.".to_string()));
+        }
+        Ok(MediaJson {
+            media_url: media_download_path(&media.room_id, &media.media_id),
+            media_type: MediaKind::of_mime_type(&media.mime_type).as_str().to_string(),
+            mime_type: Some(media.mime_type),
+            alt_text: upload.alt_text.clone(),
+            media_key: Some(media.object_key),
+        })
+    }
+
+    async fn create_reply_message(&self, msg: &ReplyBodyRequest, room_id: &Uuid) -> Result<ReplyJson, AppError> {
         let replied_to = self.chats.fetch_message_by_id(&msg.reply_msg_id, room_id).await?;
+        if replied_to.deleted_at.is_some() {
+            return Err(AppError::NotFound("Cannot reply to a deleted message.".to_string()));
+        }
 
         let details = match replied_to.msg_body.0 {
             MessageBodyJson::Text(text) => RepliedMessageJson::Text(text),
             MessageBodyJson::Media(media) => RepliedMessageJson::Media(media),
             MessageBodyJson::Reply(reply) => RepliedMessageJson::Reply { reply_text: reply.reply_text },
-            _ => return Err(Box::from("Cannot reply to a room change event")),
+            MessageBodyJson::Poll(poll) => RepliedMessageJson::Poll { question: poll.question },
+            _ => return Err(AppError::Validation("Cannot reply to a room change event.".to_string())),
         };
 
         Ok(ReplyJson {
@@ -99,23 +467,96 @@ impl MessageService {
             reply_created_at: replied_to.created_at,
             reply_msg_details: details,
             reply_text: msg.reply_text.clone(),
+            mentions: Vec::new(),
         })
     }
 }
 
-fn generate_room_preview_text(msg: &SendMessageRequest, username: String) -> LastMessagePreviewJson {
-    match &msg.msg_body {
-        SendMessageBodyRequest::Text(body) => LastMessagePreviewJson::Text {
+/// [`MessageService::visible_message`]'s checks, for a message the caller already loaded.
+fn ensure_visible(context: &RoomContext, client_id: Uuid, message: &MessageRow) -> Result<(), AppError> {
+    if context.find_member(&client_id).is_none() {
+        return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+    }
+    if message.deleted_at.is_some() {
+        return Err(AppError::NotFound("Message was deleted.".to_string()));
+    }
+    Ok(())
+}
+
+/// A sender's display name in the room. Empty for a sender who has since left, which only a
+/// moderator deleting their message can run into.
+fn sender_name(context: &RoomContext, sender_id: &Uuid) -> String {
+    context.find_member(sender_id).map(|member| member.display_name.clone()).unwrap_or_default()
+}
+
+/// Parses the mentions in a text or reply body into it, and returns them. A mention of someone who
+/// is not in the room is rejected rather than dropped: the sender would otherwise believe they had
+/// notified that person.
+fn resolve_mentions(body: &mut MessageBodyJson, context: &RoomContext) -> Result<Vec<MentionJson>, AppError> {
+    let (text, mentions) = match body {
+        MessageBodyJson::Text(body) => (&body.text, &mut body.mentions),
+        MessageBodyJson::Reply(body) => (&body.reply_text, &mut body.mentions),
+        _ => return Ok(Vec::new()),
+    };
+    let found = parse_mentions(text);
+    for mention in &found {
+        if let MentionJson::User { user_id, .. } = mention
+            && context.find_member(user_id).is_none()
+        {
+            return Err(AppError::Validation(format!("Mentioned user {user_id} is not a member of this room.")));
+        }
+    }
+    *mentions = found.clone();
+    Ok(found)
+}
+
+/// Who `mentions` reach, the sender aside: the members named one by one, and everyone through
+/// `@room`.
+fn mentioned_members(mentions: &[MentionJson], sender_id: &Uuid) -> Mentioned {
+    let mut mentioned = Mentioned::default();
+    for mention in mentions {
+        match mention {
+            MentionJson::User { user_id, .. } if user_id != sender_id => {
+                mentioned.users.insert(*user_id);
+            }
+            MentionJson::User { .. } => {}
+            MentionJson::Room { .. } => mentioned.room = true,
+        }
+    }
+    mentioned
+}
+
+/// The response to a send whose `client_message_id` is already taken. A key reused for a different
+/// room is a client bug rather than a retry, and answering it with a message from elsewhere would
+/// only hide that.
+fn already_sent(stored: MessageRow, room_id: &Uuid) -> Result<MessageResponse, AppError> {
+    if &stored.chat_room_id != room_id {
+        return Err(AppError::Validation(
+            "Client message id was already used for a message in another room.".to_string(),
+        ));
+    }
+    Ok(MessageResponse::from(stored))
+}
+
+fn generate_room_preview_text(body: &MessageBodyJson, username: String) -> LastMessagePreviewJson {
+    match body {
+        MessageBodyJson::Text(body) => LastMessagePreviewJson::Text {
             sender_username: username,
             text: body.text.clone(),
         },
-        SendMessageBodyRequest::Media(body) => LastMessagePreviewJson::Media {
+        MessageBodyJson::Media(body) => LastMessagePreviewJson::Media {
             sender_username: username,
             media_type: body.media_type.clone(),
         },
-        SendMessageBodyRequest::Reply(body) => LastMessagePreviewJson::Reply {
+        MessageBodyJson::Reply(body) => LastMessagePreviewJson::Reply {
             sender_username: username,
             reply_text: body.reply_text.clone(),
         },
+        MessageBodyJson::Poll(body) => LastMessagePreviewJson::Poll {
+            sender_username: username,
+            question: body.question.clone(),
+        },
+        // Unreachable: clients cannot send room changes, so `send_message` never builds one.
+        MessageBodyJson::RoomChange(_) => LastMessagePreviewJson::New,
     }
 }
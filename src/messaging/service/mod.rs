@@ -1,7 +1,23 @@
 //! Business logic for the messaging domain.
 
+mod forward;
+mod media;
+mod mention;
 mod message;
 mod notification;
+mod poll;
+mod retention;
+mod schedule;
+mod search;
+mod typing;
 
+pub use forward::ForwardService;
+pub use media::MediaService;
+pub use mention::MentionService;
 pub use message::MessageService;
 pub use notification::{ConnectionGuard, NotificationService};
+pub use poll::PollService;
+pub use retention::RetentionService;
+pub use schedule::ScheduleService;
+pub use search::SearchService;
+pub use typing::TypingService;
@@ -0,0 +1,177 @@
+//! Voting on polls, and closing them.
+//!
+//! A poll is sent like any other message, through [`MessageService`](crate::messaging::MessageService);
+//! what happens to it afterwards is here. Every change is broadcast as `PollUpdated` with the new
+//! totals, so clients set their counts instead of counting along.
+
+use crate::broadcast::NotificationEvent::PollUpdated;
+use crate::core::errors::AppError;
+use crate::core::{Database, Service};
+use crate::messaging::ChatRepository;
+use crate::messaging::entity::{MessageBodyJson, MessageRow, PollJson};
+use crate::messaging::response::{MessageResponse, PollResultsResponse};
+use crate::notify_room;
+use crate::rooms::RoomNotifier;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Casting and withdrawing votes on polls, and closing them.
+#[derive(Clone)]
+pub struct PollService {
+    /// Present because a vote is only taken while the poll's row is locked against closing.
+    db: Database,
+    chats: ChatRepository,
+    notifier: RoomNotifier,
+}
+
+impl Service for PollService {
+    const NAME: &'static str = "PollService";
+}
+
+impl PollService {
+    pub fn new(db: Database, chats: ChatRepository, notifier: RoomNotifier) -> Self {
+        Self { db, chats, notifier }
+    }
+
+    /// Records the caller's vote for `options`, replacing the one they cast before. The poll must
+    /// still be open, the options must exist, and a single-choice poll takes exactly one.
+    pub async fn vote(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid, mut options: Vec<u32>) -> Result<PollResultsResponse, AppError> {
+        self.ensure_member(client_id, room_id).await?;
+        options.sort_unstable();
+        options.dedup();
+
+        let mut tx = self.db.begin().await?;
+        let (_, poll) = self.lock_poll(&mut tx, room_id, message_id).await?;
+        if !poll.is_open(Utc::now()) {
+            return Err(AppError::Validation("Poll is closed.".to_string()));
+        }
+        if options.iter().any(|&option| option as usize >= poll.options.len()) {
+            return Err(AppError::Validation("Unknown poll option.".to_string()));
+        }
+        if !poll.multiple_choice && options.len() != 1 {
+            return Err(AppError::Validation("Only one option can be picked in this poll.".to_string()));
+        }
+        let option_indexes: Vec<i16> = options.iter().map(|&option| option as i16).collect();
+        self.chats.replace_poll_vote(&mut tx, &message_id, &client_id, &option_indexes).await?;
+        tx.commit().await?;
+
+        self.broadcast_votes(client_id, room_id, message_id, &poll, options).await
+    }
+
+    /// Withdraws the caller's vote. Withdrawing when they have not voted is a no-op; either way the
+    /// poll must still be open.
+    pub async fn retract_vote(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<PollResultsResponse, AppError> {
+        self.ensure_member(client_id, room_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let (_, poll) = self.lock_poll(&mut tx, room_id, message_id).await?;
+        if !poll.is_open(Utc::now()) {
+            return Err(AppError::Validation("Poll is closed.".to_string()));
+        }
+        let changed = self.chats.delete_poll_vote(&mut *tx, &message_id, &client_id).await?;
+        tx.commit().await?;
+
+        if !changed {
+            return self.results(&message_id, &poll, &client_id).await;
+        }
+        self.broadcast_votes(client_id, room_id, message_id, &poll, Vec::new()).await
+    }
+
+    /// Closes a poll the caller sent, before — or without — its `closes_at`. The votes stay, and
+    /// no more are taken.
+    pub async fn close_poll(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<MessageResponse, AppError> {
+        self.ensure_member(client_id, room_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let (mut message, mut poll) = self.lock_poll(&mut tx, room_id, message_id).await?;
+        if message.sender_id != client_id {
+            return Err(AppError::Forbidden("Only the sender can close this poll.".to_string()));
+        }
+        let closed_at = Utc::now();
+        if !poll.is_open(closed_at) {
+            return Err(AppError::Validation("Poll is closed.".to_string()));
+        }
+        poll.closed_at = Some(closed_at);
+        message.msg_body = sqlx::types::Json(MessageBodyJson::Poll(poll.clone()));
+        self.chats.update_poll_body(&mut tx, &message_id, &message.msg_body.0).await?;
+        tx.commit().await?;
+
+        let results = self.results(&message_id, &poll, &client_id).await?;
+        self.broadcast(room_id, message_id, &results, None, None, Some(closed_at)).await;
+        let mut dto = MessageResponse::from(message);
+        dto.poll_results = Some(results);
+        Ok(dto)
+    }
+
+    async fn ensure_member(&self, client_id: Uuid, room_id: Uuid) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        if context.find_member(&client_id).is_none() {
+            return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Locks a poll of the room for the rest of `tx`, rejecting messages that are deleted or not
+    /// polls.
+    async fn lock_poll(&self, tx: &mut PgConnection, room_id: Uuid, message_id: Uuid) -> Result<(MessageRow, PollJson), AppError> {
+        let message = self.chats.lock_message(tx, &message_id, &room_id).await?;
+        if message.deleted_at.is_some() {
+            return Err(AppError::NotFound("Message was deleted.".to_string()));
+        }
+        match &message.msg_body.0 {
+            MessageBodyJson::Poll(poll) => {
+                let poll = poll.clone();
+                Ok((message, poll))
+            }
+            _ => Err(AppError::Validation("Message is not a poll.".to_string())),
+        }
+    }
+
+    /// The poll's totals, with `viewer_id`'s own choices.
+    async fn results(&self, message_id: &Uuid, poll: &PollJson, viewer_id: &Uuid) -> Result<PollResultsResponse, AppError> {
+        let rows = self.chats.fetch_poll_vote_counts(&[*message_id], viewer_id).await?;
+        Ok(PollResultsResponse::tally(poll.options.len(), rows))
+    }
+
+    /// Tells the room about a voter's new choices and returns the totals, with those choices, to
+    /// the voter.
+    async fn broadcast_votes(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        message_id: Uuid,
+        poll: &PollJson,
+        choices: Vec<u32>,
+    ) -> Result<PollResultsResponse, AppError> {
+        let results = self.results(&message_id, poll, &client_id).await?;
+        self.broadcast(room_id, message_id, &results, Some(client_id), Some(choices), None).await;
+        Ok(results)
+    }
+
+    async fn broadcast(
+        &self,
+        room_id: Uuid,
+        message_id: Uuid,
+        results: &PollResultsResponse,
+        user_id: Option<Uuid>,
+        choices: Option<Vec<u32>>,
+        closed_at: Option<DateTime<Utc>>,
+    ) {
+        notify_room!(
+            self.notifier,
+            &room_id,
+            PollUpdated {
+                room_id,
+                message_id,
+                results: PollResultsResponse {
+                    my_votes: Vec::new(),
+                    ..results.clone()
+                },
+                user_id,
+                choices,
+                closed_at,
+            }
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Disappearing messages.
+//!
+//! Expiry itself needs no background work: the timeline resolves each room's cut-off on every read
+//! through [`RetentionPolicy`], so a message is gone for clients the moment it ages out. The reaper
+//! here only deletes what has already disappeared from view — uploads included — and tells the
+//! rooms concerned so clients can drop their local copies too.
+
+use crate::broadcast::NotificationEvent;
+use crate::core::errors::AppError;
+use crate::core::{Database, Service};
+use crate::messaging::ChatRepository;
+use crate::messaging::entity::ExpiredMessagesRow;
+use crate::object_storage::ObjectStorage;
+use crate::rooms::model::RetentionPolicy;
+use crate::rooms::{RoomNotifier, RoomRepository};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How often expired messages are looked for.
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Messages deleted per transaction. Small enough that a backlog — the first run after a room
+/// shortened its retention — never holds locks for long.
+const REAP_BATCH: i64 = 500;
+
+/// Deletes messages that outlived their room's retention.
+#[derive(Clone)]
+pub struct RetentionService {
+    /// Present because a batch and the previews it invalidates must be one transaction across two
+    /// repositories.
+    db: Database,
+    rooms: RoomRepository,
+    chats: ChatRepository,
+    notifier: RoomNotifier,
+    storage: ObjectStorage,
+    policy: RetentionPolicy,
+}
+
+impl Service for RetentionService {
+    const NAME: &'static str = "RetentionService";
+}
+
+impl RetentionService {
+    pub fn new(db: Database, rooms: RoomRepository, chats: ChatRepository, notifier: RoomNotifier, storage: ObjectStorage, policy: RetentionPolicy) -> Self {
+        Self {
+            db,
+            rooms,
+            chats,
+            notifier,
+            storage,
+            policy,
+        }
+    }
+
+    /// Deletes every expired message, batch by batch, then sends each room concerned one
+    /// `MessagesExpired` covering all of its batches.
+    pub async fn reap_expired(&self) -> Result<(), AppError> {
+        let mut expired: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+        let mut total = 0;
+        let outcome = loop {
+            let batch = match self.reap_batch().await {
+                Ok(batch) => batch,
+                Err(err) => break Err(err),
+            };
+            let deleted: i64 = batch.iter().map(|row| row.count).sum();
+            for row in batch {
+                let until = row.until;
+                expired
+                    .entry(row.chat_room_id)
+                    .and_modify(|known| *known = (*known).max(until))
+                    .or_insert(until);
+                self.delete_media(&row.chat_room_id, row.media_keys).await;
+            }
+            total += deleted;
+            if deleted < REAP_BATCH {
+                break Ok(());
+            }
+        };
+
+        // Whatever was committed before a failing batch is gone for good; its rooms still hear it.
+        if total > 0 {
+            info!(messages = total, rooms = expired.len(), "Deleted expired messages");
+        }
+        for (room_id, until) in expired {
+            if let Err(err) = self.notifier.notify_room(&room_id, NotificationEvent::MessagesExpired { room_id, until }).await {
+                warn!(%room_id, error = %err, "Could not announce expired messages");
+            }
+        }
+        outcome
+    }
+
+    /// One batch, with the previews that now quote a deleted message emptied in the same
+    /// transaction. Returns what went, per room.
+    async fn reap_batch(&self) -> Result<Vec<ExpiredMessagesRow>, AppError> {
+        let mut tx = self.db.begin().await?;
+        let rows = self.chats.delete_expired_messages(&mut tx, self.policy.default_secs(), REAP_BATCH).await?;
+        for row in &rows {
+            self.rooms.clear_expired_preview(&mut tx, &row.chat_room_id, row.until).await?;
+        }
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Deletes the uploads of reaped messages from object storage. Best-effort: their rows are
+    /// already gone, so nobody can download them any more.
+    async fn delete_media(&self, room_id: &Uuid, media_keys: Vec<String>) {
+        for key in media_keys {
+            if let Err(err) = self.storage.delete_object(&key).await {
+                warn!(%room_id, object_key = %key, error = %err, "Unable to delete expired media");
+            }
+        }
+    }
+
+    /// Runs [`Self::reap_expired`] forever. Spawned once by the builder, which keeps the handle so
+    /// shutdown can abort it.
+    pub async fn run_reaper(self) {
+        let mut interval = time::interval(REAP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.reap_expired().await {
+                warn!(error = %err, "Reaping expired messages failed");
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Messages composed now and sent later.
+//!
+//! A scheduled message is sent by the dispatcher through [`MessageService::send_message`], like
+//! any other, once it is due. Each instance runs a dispatcher; they share the work by claiming due
+//! messages for a lease, and a message is sent with a `client_message_id` derived from its
+//! schedule, so a send repeated after a crash between sending and recording it — or by another
+//! dispatcher once a lease ran out mid-send — is recognised as a retry rather than stored twice.
+//! Together that makes every scheduled message arrive exactly once.
+
+use crate::core::Service;
+use crate::core::errors::AppError;
+use crate::messaging::ChatRepository;
+use crate::messaging::MessageService;
+use crate::messaging::entity::{ScheduledBodyJson, ScheduledMessageRow};
+use crate::messaging::request::{ScheduleMessageRequest, SendMessageRequest};
+use crate::messaging::response::ScheduledMessageResponse;
+use crate::rooms::{RoomNotifier, RoomRepository};
+use chrono::Utc;
+use std::time::Duration;
+use tokio::time;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How often due messages are looked for — and so how late one can arrive.
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Messages claimed at once.
+const DISPATCH_BATCH: i64 = 50;
+
+/// How long a claim keeps other dispatchers off a batch. Comfortably longer than sending one takes;
+/// a batch outliving it is picked up again, which the `client_message_id` makes harmless.
+const CLAIM_LEASE: Duration = Duration::from_secs(2 * 60);
+
+/// How many messages one user may have scheduled at once, failed ones included.
+const MAX_SCHEDULED_PER_USER: i64 = 100;
+
+/// Scheduling messages, and sending them when they are due.
+#[derive(Clone)]
+pub struct ScheduleService {
+    rooms: RoomRepository,
+    chats: ChatRepository,
+    notifier: RoomNotifier,
+    message_service: MessageService,
+}
+
+impl Service for ScheduleService {
+    const NAME: &'static str = "ScheduleService";
+}
+
+impl ScheduleService {
+    pub fn new(rooms: RoomRepository, chats: ChatRepository, notifier: RoomNotifier, message_service: MessageService) -> Self {
+        Self {
+            rooms,
+            chats,
+            notifier,
+            message_service,
+        }
+    }
+
+    /// Stores a message to be sent at `send_at`. Only membership is checked now; everything that
+    /// depends on the room's state at sending time — the message replied to, the upload attached —
+    /// is checked when the message is sent.
+    pub async fn schedule_message(&self, client_id: Uuid, request: ScheduleMessageRequest) -> Result<ScheduledMessageResponse, AppError> {
+        let context = self.notifier.room_context(&request.chat_room_id).await?;
+        if context.find_member(&client_id).is_none() {
+            return Err(AppError::Forbidden("User hasn't access to this room.".to_string()));
+        }
+        if self.chats.count_scheduled_messages(&client_id).await? >= MAX_SCHEDULED_PER_USER {
+            return Err(AppError::Validation(format!(
+                "At most {MAX_SCHEDULED_PER_USER} messages can be scheduled at once."
+            )));
+        }
+
+        let scheduled = ScheduledMessageRow {
+            scheduled_message_id: Uuid::new_v4(),
+            chat_room_id: request.chat_room_id,
+            sender_id: client_id,
+            msg_body: sqlx::types::Json(ScheduledBodyJson::from(request.msg_body)),
+            msg_type: request.msg_type,
+            thread_root_id: request.thread_root_id,
+            send_at: request.send_at,
+            created_at: Utc::now(),
+            failed_at: None,
+            failure: None,
+        };
+        self.chats.insert_scheduled_message(&scheduled).await?;
+        Ok(ScheduledMessageResponse::from(scheduled))
+    }
+
+    /// The caller's scheduled messages, soonest first.
+    pub async fn scheduled_messages(&self, client_id: Uuid, room_id: Option<Uuid>) -> Result<Vec<ScheduledMessageResponse>, AppError> {
+        let rows = self.chats.select_scheduled_messages(&client_id, room_id).await?;
+        Ok(rows.into_iter().map(ScheduledMessageResponse::from).collect())
+    }
+
+    /// Cancels a scheduled message, or dismisses one that failed. Too late once a dispatcher has
+    /// claimed it for sending.
+    pub async fn cancel_scheduled_message(&self, client_id: Uuid, scheduled_message_id: Uuid) -> Result<(), AppError> {
+        if !self.chats.delete_scheduled_message(&client_id, &scheduled_message_id).await? {
+            return Err(AppError::NotFound("Scheduled message not found; it may have been sent already.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Sends every message that is due, batch by batch.
+    ///
+    /// A batch is claimed in one short statement and each message is then sent and recorded on its
+    /// own, so no lock is held while sending: sent ones are deleted, refused ones marked failed. A
+    /// message that failed for a reason that may pass — the database, the cache — is released for
+    /// the next run.
+    pub async fn dispatch_due(&self) -> Result<(), AppError> {
+        let mut sent = 0;
+        loop {
+            let now = Utc::now();
+            let due = self.chats.claim_due_scheduled_messages(now, now + CLAIM_LEASE, DISPATCH_BATCH).await?;
+            let mut retry_later = false;
+            for scheduled in &due {
+                match self.send(scheduled).await {
+                    Ok(()) => {
+                        self.chats.finish_scheduled_message(&scheduled.scheduled_message_id).await?;
+                        sent += 1;
+                    }
+                    Err(err) => match refusal(&err) {
+                        Some(failure) => {
+                            self.chats.fail_scheduled_message(&scheduled.scheduled_message_id, &failure, Utc::now()).await?;
+                        }
+                        None => {
+                            warn!(scheduled_message_id = %scheduled.scheduled_message_id, error = %err, "Sending a scheduled message failed; retrying later");
+                            self.chats.release_scheduled_message(&scheduled.scheduled_message_id).await?;
+                            retry_later = true;
+                        }
+                    },
+                }
+            }
+            // A released message would be claimed again straight away.
+            if (due.len() as i64) < DISPATCH_BATCH || retry_later {
+                break;
+            }
+        }
+        if sent > 0 {
+            info!(messages = sent, "Sent scheduled messages");
+        }
+        Ok(())
+    }
+
+    /// Sends one scheduled message as its sender, if they are still in the room.
+    async fn send(&self, scheduled: &ScheduledMessageRow) -> Result<(), AppError> {
+        // Checked against the database rather than the cached room context: the sender may have
+        // left in the months since they scheduled this.
+        if !self.rooms.is_user_in_room(&scheduled.sender_id, &scheduled.chat_room_id).await? {
+            return Err(AppError::Forbidden("Sender is no longer a member of this room.".to_string()));
+        }
+        let request = SendMessageRequest {
+            chat_room_id: scheduled.chat_room_id,
+            msg_body: scheduled.msg_body.0.clone().into(),
+            msg_type: scheduled.msg_type,
+            thread_root_id: scheduled.thread_root_id,
+            client_message_id: Some(scheduled_client_message_id(&scheduled.scheduled_message_id)),
+        };
+        self.message_service.send_message(request, scheduled.sender_id).await?;
+        Ok(())
+    }
+
+    /// Runs [`Self::dispatch_due`] forever. Spawned once by the builder, which keeps the handle
+    /// so shutdown can abort it.
+    pub async fn run_dispatcher(self) {
+        let mut interval = time::interval(DISPATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.dispatch_due().await {
+                warn!(error = %err, "Dispatching scheduled messages failed");
+            }
+        }
+    }
+}
+
+/// The `client_message_id` a scheduled message is sent with. Echoed on the sent message, so the
+/// sender's clients can replace the scheduled entry they show with it.
+fn scheduled_client_message_id(scheduled_message_id: &Uuid) -> String {
+    format!("scheduled-{scheduled_message_id}")
+}
+
+/// Why sending was refused for good, in words for the sender; `None` when it may work next time.
+fn refusal(err: &AppError) -> Option<String> {
+    match err {
+        AppError::Validation(reason) | AppError::NotFound(reason) | AppError::Forbidden(reason) => Some(reason.clone()),
+        AppError::Database(sqlx::Error::RowNotFound) => Some("The message it refers to no longer exists.".to_string()),
+        _ => None,
+    }
+}
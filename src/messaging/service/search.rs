@@ -0,0 +1,55 @@
+use crate::core::Service;
+use crate::core::cursor::{CursorResults, next_cursor};
+use crate::core::errors::AppError;
+use crate::messaging::ChatRepository;
+use crate::messaging::model::{MessageSearchCursor, MessageSearchFilter};
+use crate::messaging::response::MessageSearchHitResponse;
+use crate::rooms::model::RetentionPolicy;
+use uuid::Uuid;
+
+/// Full-text search over the messages of the caller's rooms.
+#[derive(Clone)]
+pub struct SearchService {
+    chats: ChatRepository,
+    retention: RetentionPolicy,
+}
+
+impl Service for SearchService {
+    const NAME: &'static str = "SearchService";
+}
+
+impl SearchService {
+    pub fn new(chats: ChatRepository, retention: RetentionPolicy) -> Self {
+        Self { chats, retention }
+    }
+
+    /// One page of hits, best match first.
+    ///
+    /// Only rooms the caller is in *now* are searched: leaving a room takes its history out of
+    /// their results, the same as it takes it out of their room list. Naming a room the caller is
+    /// not in is therefore not an error, just a search without hits.
+    pub async fn search_messages(
+        &self,
+        client_id: Uuid,
+        filter: MessageSearchFilter,
+        cursor: MessageSearchCursor,
+        page_size: usize,
+    ) -> Result<CursorResults<MessageSearchHitResponse>, AppError> {
+        let mut hits = self
+            .chats
+            .search_messages(&client_id, &filter, self.retention.default_secs(), cursor, (page_size + 1) as i64)
+            .await?;
+
+        let cursor = next_cursor(&mut hits, page_size, |last| MessageSearchCursor {
+            last_rank: Some(last.rank),
+            last_created_at: Some(last.message.created_at),
+            last_message_id: Some(last.message.message_id),
+        })
+        .map_err(|e| AppError::Processing(format!("Cursor encoding failed: {}", e)))?;
+
+        Ok(CursorResults {
+            cursor,
+            content: hits.into_iter().map(MessageSearchHitResponse::from).collect(),
+        })
+    }
+}
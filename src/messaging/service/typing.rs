@@ -0,0 +1,235 @@
+//! "Is typing…" indicators.
+//!
+//! Typing state is the one piece of room state that is allowed to be lost: it lives in memory on
+//! the instance that received it, is never written to PostgreSQL or the cache, and every event it
+//! produces is ephemeral. What it must not do is flood a room or get stuck, which is what the
+//! throttle and the expiry below are for.
+
+use crate::broadcast::NotificationEvent;
+use crate::core::Service;
+use crate::core::errors::AppError;
+use crate::rooms::RoomNotifier;
+use crate::rooms::model::RoomContext;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tracing::warn;
+use uuid::Uuid;
+
+/// A member who keeps typing is re-announced at most this often. Clients typically report on every
+/// keystroke; the room only needs to hear that someone is still at it.
+const TYPING_THROTTLE: Duration = Duration::from_secs(3);
+
+/// How long a typing state lives without a refresh before the server clears it.
+const TYPING_TTL: Duration = Duration::from_secs(8);
+
+/// How often expired typing states are looked for.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Who is typing where, on this instance.
+///
+/// A member typing through two instances at once (two devices behind a load balancer) is tracked
+/// on both, and each expires its own entry. At worst the room sees a stop that the other
+/// instance's next announcement corrects within [`TYPING_THROTTLE`].
+#[derive(Clone)]
+pub struct TypingService {
+    notifier: RoomNotifier,
+    typists: Arc<Mutex<TypingRegistry>>,
+}
+
+impl Service for TypingService {
+    const NAME: &'static str = "TypingService";
+}
+
+impl TypingService {
+    pub fn new(notifier: RoomNotifier) -> Self {
+        Self {
+            notifier,
+            typists: Arc::new(Mutex::new(TypingRegistry::default())),
+        }
+    }
+
+    /// Records that `user_id` started or stopped typing in `room_id`, and tells the rest of the
+    /// room when that changes what they should show.
+    pub async fn set_typing(&self, user_id: Uuid, room_id: Uuid, active: bool) -> Result<(), AppError> {
+        let context = self.notifier.room_context(&room_id).await?;
+        if context.find_member(&user_id).is_none() {
+            return Err(AppError::Forbidden("Invalid permissions to interact with this room".to_string()));
+        }
+
+        let changed = {
+            let mut typists = self.typists.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if active {
+                typists.start((room_id, user_id), Instant::now())
+            } else {
+                typists.stop(&(room_id, user_id))
+            }
+        };
+        if changed {
+            self.announce(&context, room_id, user_id, active).await;
+        }
+        Ok(())
+    }
+
+    /// Ends `user_id`'s typing state in `room_id` because they just sent a message there, so the
+    /// room does not keep showing them as typing until the TTL runs out. The send already checked
+    /// membership.
+    pub async fn message_sent(&self, user_id: Uuid, room_id: Uuid) {
+        let was_typing = self.typists.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stop(&(room_id, user_id));
+        if !was_typing {
+            return;
+        }
+        match self.notifier.room_context(&room_id).await {
+            Ok(context) => self.announce(&context, room_id, user_id, false).await,
+            Err(err) => warn!(%room_id, %user_id, error = %err, "Could not announce a typing state ended by a send"),
+        }
+    }
+
+    /// Clears every typing state whose TTL ran out and tells the rooms concerned.
+    pub async fn expire_idle(&self) {
+        let expired = self
+            .typists
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take_expired(Instant::now());
+
+        for (room_id, user_id) in expired {
+            match self.notifier.room_context(&room_id).await {
+                Ok(context) => self.announce(&context, room_id, user_id, false).await,
+                Err(err) => warn!(%room_id, %user_id, error = %err, "Could not announce an expired typing state"),
+            }
+        }
+    }
+
+    /// Tells everyone in the room but the typist; their own devices know what they are doing.
+    async fn announce(&self, context: &RoomContext, room_id: Uuid, user_id: Uuid, active: bool) {
+        let others = context.member_ids().into_iter().filter(|id| *id != user_id).collect();
+        self.notifier.notify_users(others, NotificationEvent::Typing { room_id, user_id, active }).await;
+    }
+
+    /// Runs [`Self::expire_idle`] forever. Spawned once by the builder, which keeps the handle so
+    /// shutdown can abort it.
+    pub async fn run_expiry(self) {
+        let mut interval = time::interval(EXPIRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.expire_idle().await;
+        }
+    }
+}
+
+/// `(room_id, user_id)` of one typing member.
+type TypistKey = (Uuid, Uuid);
+
+/// The throttle and expiry bookkeeping, free of any I/O so it can be tested on its own.
+#[derive(Default)]
+struct TypingRegistry {
+    typists: HashMap<TypistKey, Typist>,
+}
+
+struct Typist {
+    announced_at: Instant,
+    expires_at: Instant,
+}
+
+impl TypingRegistry {
+    /// Starts or refreshes a typing state. Returns whether it has to be announced: always for a
+    /// new one, and for a refresh only once [`TYPING_THROTTLE`] has passed since the last time.
+    fn start(&mut self, key: TypistKey, now: Instant) -> bool {
+        let expires_at = now + TYPING_TTL;
+        match self.typists.get_mut(&key) {
+            Some(typist) => {
+                typist.expires_at = expires_at;
+                if now.duration_since(typist.announced_at) < TYPING_THROTTLE {
+                    return false;
+                }
+                typist.announced_at = now;
+                true
+            }
+            None => {
+                self.typists.insert(key, Typist { announced_at: now, expires_at });
+                true
+            }
+        }
+    }
+
+    /// Ends a typing state. Returns whether there was one to end.
+    fn stop(&mut self, key: &TypistKey) -> bool {
+        self.typists.remove(key).is_some()
+    }
+
+    /// Removes and returns every typing state that expired at or before `now`.
+    fn take_expired(&mut self, now: Instant) -> Vec<TypistKey> {
+        let expired: Vec<TypistKey> = self
+            .typists
+            .iter()
+            .filter(|(_, typist)| typist.expires_at <= now)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &expired {
+            self.typists.remove(key);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> TypistKey {
+        (Uuid::new_v4(), Uuid::new_v4())
+    }
+
+    #[test]
+    fn keystrokes_within_the_throttle_are_announced_once() {
+        let mut registry = TypingRegistry::default();
+        let typist = key();
+        let now = Instant::now();
+
+        assert!(registry.start(typist, now));
+        assert!(!registry.start(typist, now + Duration::from_millis(500)));
+        assert!(!registry.start(typist, now + TYPING_THROTTLE - Duration::from_millis(1)));
+        assert!(registry.start(typist, now + TYPING_THROTTLE));
+    }
+
+    #[test]
+    fn refreshing_pushes_the_expiry_back() {
+        let mut registry = TypingRegistry::default();
+        let typist = key();
+        let now = Instant::now();
+
+        registry.start(typist, now);
+        registry.start(typist, now + Duration::from_secs(5));
+
+        assert!(
+            registry.take_expired(now + TYPING_TTL).is_empty(),
+            "a refreshed state expired on its first deadline"
+        );
+        assert_eq!(registry.take_expired(now + Duration::from_secs(5) + TYPING_TTL), vec![typist]);
+    }
+
+    #[test]
+    fn expired_states_are_returned_once() {
+        let mut registry = TypingRegistry::default();
+        let typist = key();
+        let now = Instant::now();
+
+        registry.start(typist, now);
+
+        assert_eq!(registry.take_expired(now + TYPING_TTL), vec![typist]);
+        assert!(registry.take_expired(now + TYPING_TTL * 2).is_empty());
+    }
+
+    #[test]
+    fn stopping_is_only_announced_when_typing() {
+        let mut registry = TypingRegistry::default();
+        let typist = key();
+
+        assert!(!registry.stop(&typist));
+        registry.start(typist, Instant::now());
+        assert!(registry.stop(&typist));
+        assert!(!registry.stop(&typist));
+    }
+}
@@ -0,0 +1,221 @@
+//! The command protocol spoken *up* an open WebSocket.
+//!
+//! Down the socket flow the same notification envelopes as on `/sse`. Up it, a client may send
+//! command frames instead of making a separate HTTP call for each:
+//!
+//! ```json
+//! { "v": 1, "id": "c-17", "type": "MarkRead", "roomId": "…", "messageId": "…" }
+//! ```
+//!
+//! `type` and the remaining fields are a [`ClientCommand`]; `v` is the protocol version and `id` an
+//! optional correlation id. Each command runs through the same service method as its REST
+//! endpoint, so the two transports cannot drift apart in what they allow, and is answered with a
+//! [`CommandReplyFrame`] carrying the same `id`.
+//!
+//! Like the REST handlers, this module is transport only: it parses, dispatches and shapes the
+//! reply, and every decision about what a user may do stays in the services.
+
+use crate::core::errors::AppError;
+use crate::messaging::request::{COMMAND_PROTOCOL_VERSION, ClientCommand};
+use crate::messaging::response::{CommandOutcome, CommandReplyFrame, CommandResult, NotificationCursorResponse};
+use crate::messaging::service::{MessageService, NotificationService, TypingService};
+use crate::rooms::RoomService;
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Longest accepted correlation id. Echoed back verbatim, so it must not be allowed to be large.
+const MAX_CORRELATION_ID_LEN: usize = 64;
+
+/// The services a socket's commands dispatch into.
+#[derive(Clone)]
+pub struct SocketCommands {
+    messages: MessageService,
+    rooms: RoomService,
+    notifications: NotificationService,
+    typing: TypingService,
+}
+
+impl SocketCommands {
+    pub fn new(messages: MessageService, rooms: RoomService, notifications: NotificationService, typing: TypingService) -> Self {
+        Self {
+            messages,
+            rooms,
+            notifications,
+            typing,
+        }
+    }
+
+    /// Runs one text frame from `user_id` and returns the reply to send back, if any.
+    ///
+    /// `high_water` is the socket's deduplication mark, which `Ack` raises. A successful command
+    /// sent without an `id` gets no reply — nothing could be correlated with it — but a failed one
+    /// always does, so a client is never left guessing why nothing happened.
+    pub async fn handle(&self, user_id: Uuid, text: &str, high_water: &mut u64) -> Option<CommandReplyFrame> {
+        let (id, outcome) = match parse_frame(text) {
+            Ok((id, command)) => {
+                let outcome = self.execute(user_id, command, high_water).await;
+                (id, outcome)
+            }
+            Err((id, err)) => (id, Err(err)),
+        };
+
+        let outcome = match outcome {
+            Ok(_) if id.is_none() => return None,
+            Ok(result) => CommandOutcome::CommandOk { result },
+            Err(err) => CommandOutcome::CommandError {
+                error: err.into_error_response().1,
+            },
+        };
+        Some(CommandReplyFrame {
+            v: COMMAND_PROTOCOL_VERSION,
+            id,
+            outcome,
+        })
+    }
+
+    async fn execute(&self, user_id: Uuid, command: ClientCommand, high_water: &mut u64) -> Result<Option<CommandResult>, AppError> {
+        match command {
+            ClientCommand::Typing { room_id, active } => {
+                self.typing.set_typing(user_id, room_id, active).await?;
+                Ok(None)
+            }
+            ClientCommand::SendMessage(request) => {
+                let message = self.messages.send_message(request, user_id).await?;
+                self.typing.message_sent(user_id, message.chat_room_id).await;
+                Ok(Some(CommandResult::Message(Box::new(message))))
+            }
+            ClientCommand::MarkRead { room_id, position } => {
+                self.rooms.mark_room_as_read(user_id, room_id, position).await?;
+                Ok(None)
+            }
+            ClientCommand::Ack { seq } => {
+                let current = self.notifications.current_sequence(&user_id).await?;
+                // Capped at what was actually issued: acknowledging a sequence from the future
+                // would silently swallow the real event once it gets that number.
+                *high_water = (*high_water).max(seq.min(current));
+                Ok(Some(CommandResult::Cursor(NotificationCursorResponse { seq: current })))
+            }
+            ClientCommand::Replay { last_seq } => {
+                let events = self.notifications.events_since(&user_id, last_seq).await?;
+                Ok(Some(CommandResult::Events(events)))
+            }
+        }
+    }
+}
+
+/// The envelope fields of a command frame, read before the command itself so that a frame whose
+/// command does not parse can still be answered under its `id`.
+#[derive(Deserialize)]
+struct FrameHeader {
+    #[serde(default)]
+    v: Option<u8>,
+    #[serde(default)]
+    id: Option<String>,
+}
+
+/// Splits a frame into its correlation id and a validated command. On failure the id is returned
+/// alongside the error whenever it could be read.
+fn parse_frame(text: &str) -> Result<(Option<String>, ClientCommand), (Option<String>, AppError)> {
+    let frame: serde_json::Value = serde_json::from_str(text).map_err(|err| (None, AppError::Validation(format!("Malformed frame: {}", err))))?;
+    let header = FrameHeader::deserialize(&frame).map_err(|err| (None, AppError::Validation(format!("Malformed frame: {}", err))))?;
+
+    let id = header.id;
+    if id.as_ref().is_some_and(|id| id.is_empty() || id.len() > MAX_CORRELATION_ID_LEN) {
+        return Err((
+            None,
+            AppError::Validation(format!("The frame id must be between 1 and {} characters long.", MAX_CORRELATION_ID_LEN)),
+        ));
+    }
+    // A frame without `v` is a version-1 frame: that is how the first clients sent typing frames.
+    let version = header.v.unwrap_or(COMMAND_PROTOCOL_VERSION);
+    if version != COMMAND_PROTOCOL_VERSION {
+        return Err((id, AppError::Validation(format!("Unsupported protocol version {}.", version))));
+    }
+
+    let command = match ClientCommand::deserialize(frame) {
+        Ok(command) => command,
+        Err(err) => return Err((id, AppError::Validation(format!("Invalid command: {}", err)))),
+    };
+    if let Err(errors) = command.validate() {
+        return Err((id, AppError::from(errors)));
+    }
+    Ok((id, command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOM_ID: &str = "33333333-3333-4333-8333-333333333333";
+
+    fn validation_message(result: Result<(Option<String>, ClientCommand), (Option<String>, AppError)>) -> (Option<String>, String) {
+        match result {
+            Err((id, AppError::Validation(message))) => (id, message),
+            Err((_, other)) => panic!("expected a validation error, got {other:?}"),
+            Ok((_, command)) => panic!("expected the frame to be rejected, got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn a_full_frame_parses_with_its_id() {
+        let text = format!(r#"{{"v":1,"id":"c-1","type":"MarkRead","roomId":"{ROOM_ID}","messageId":"{ROOM_ID}"}}"#);
+        let (id, command) = parse_frame(&text).expect("valid frame");
+
+        assert_eq!(id.as_deref(), Some("c-1"));
+        match command {
+            ClientCommand::MarkRead { position, .. } => assert!(position.message_id.is_some() && position.read_at.is_none()),
+            other => panic!("parsed as {other:?}"),
+        }
+    }
+
+    /// The typing frame predates the protocol: no `v`, no `id`. It must keep working.
+    #[test]
+    fn a_bare_typing_frame_is_still_accepted() {
+        let text = format!(r#"{{"type":"Typing","roomId":"{ROOM_ID}"}}"#);
+        let (id, command) = parse_frame(&text).expect("valid frame");
+
+        assert!(id.is_none());
+        assert!(matches!(command, ClientCommand::Typing { active: true, .. }));
+    }
+
+    #[test]
+    fn an_unknown_command_is_answered_under_its_id() {
+        let (id, message) = validation_message(parse_frame(r#"{"id":"c-2","type":"LaunchRockets"}"#));
+
+        assert_eq!(id.as_deref(), Some("c-2"));
+        assert!(message.starts_with("Invalid command"), "{message}");
+    }
+
+    #[test]
+    fn another_protocol_version_is_rejected() {
+        let (id, message) = validation_message(parse_frame(r#"{"v":2,"id":"c-3","type":"Ack","seq":1}"#));
+
+        assert_eq!(id.as_deref(), Some("c-3"));
+        assert!(message.contains("version 2"), "{message}");
+    }
+
+    #[test]
+    fn request_validation_runs_on_commands() {
+        let text = format!(r#"{{"id":"c-4","type":"MarkRead","roomId":"{ROOM_ID}","messageId":"{ROOM_ID}","readAt":"2026-01-15T12:30:45Z"}}"#);
+        let (id, _) = validation_message(parse_frame(&text));
+
+        assert_eq!(id.as_deref(), Some("c-4"));
+    }
+
+    #[test]
+    fn an_oversized_id_is_not_echoed() {
+        let text = format!(r#"{{"id":"{}","type":"Ack","seq":1}}"#, "x".repeat(MAX_CORRELATION_ID_LEN + 1));
+        let (id, _) = validation_message(parse_frame(&text));
+
+        assert!(id.is_none());
+    }
+
+    #[test]
+    fn garbage_is_rejected_without_an_id() {
+        let (id, message) = validation_message(parse_frame("not json"));
+
+        assert!(id.is_none());
+        assert!(message.starts_with("Malformed frame"), "{message}");
+    }
+}
@@ -1,3 +1,3 @@
 mod object_storage;
 
-pub use object_storage::ObjectStorage;
+pub use object_storage::{ObjectStat, ObjectStorage};
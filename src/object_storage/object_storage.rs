@@ -1,16 +1,27 @@
 use crate::core::{ObjectStorageConfig, StartupError, StartupResult};
 use bytes::Bytes;
-use minio::s3::builders::ObjectContent;
+use http::{Method, header};
+use minio::s3::builders::{CopySource, ObjectContent};
 use minio::s3::creds::StaticProvider;
-use minio::s3::error::Error;
+use minio::s3::error::{Error, S3ServerError};
 use minio::s3::http::BaseUrl;
-use minio::s3::response_traits::{HasObject, HasVersion};
+use minio::s3::response_traits::{HasObject, HasS3Fields, HasVersion};
 use minio::s3::segmented_bytes::SegmentedBytes;
-use minio::s3::types::S3Api;
+use minio::s3::types::minio_error_response::MinioErrorCode;
+use minio::s3::types::{BucketName, ObjectKey, S3Api};
 use minio::s3::{MinioClient, MinioClientBuilder};
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info};
 
+/// What the storage knows about an object without downloading it.
+#[derive(Debug)]
+pub struct ObjectStat {
+    pub size: u64,
+    /// The `Content-Type` it was stored with; whoever uploaded it chose this.
+    pub content_type: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjectStorage {
     session: Arc<MinioClient>,
@@ -74,16 +85,79 @@ impl ObjectStorage {
         Ok(())
     }
 
-    pub async fn insert_object(&self, object_id: &String, content: Bytes) -> Result<(), Error> {
+    pub async fn insert_object(&self, object_id: &String, content: Bytes, content_type: &str) -> Result<(), Error> {
         let session = self.session.clone();
         let object = ObjectContent::from(content);
         let response = session
             .put_object_content(&self.config.bucket_name, object_id, object)?
-            .content_type("image/jpeg".to_string())
+            .content_type(content_type.to_string())
             .build()
             .send()
             .await?;
         debug!(object = ?response.object(), "Saved object");
         Ok(())
     }
+
+    /// The first `length` bytes of an object, for looking at its header without fetching the rest.
+    pub async fn get_object_prefix(&self, object_id: &str, length: u64) -> Result<Bytes, Box<dyn std::error::Error + Send + Sync>> {
+        let session = self.session.clone();
+        let response = session
+            .get_object(&self.config.bucket_name, object_id)?
+            .offset(0)
+            .length(length)
+            .build()
+            .send()
+            .await?;
+        let object = response.content()?.to_segmented_bytes().await?;
+        Ok(object.to_bytes())
+    }
+
+    /// Size and content type of an object, or `None` when there is no object under that key.
+    pub async fn stat_object(&self, object_id: &str) -> Result<Option<ObjectStat>, Error> {
+        let session = self.session.clone();
+        let response = match session.stat_object(&self.config.bucket_name, object_id)?.build().send().await {
+            Ok(response) => response,
+            Err(Error::S3Server(S3ServerError::S3Error(err))) if err.code() == MinioErrorCode::NoSuchKey => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Some(ObjectStat {
+            size: response.size()?,
+            content_type,
+        }))
+    }
+
+    /// Copies an object to another key within the bucket, without the bytes leaving the storage.
+    /// `false` when there is no object under `from`.
+    pub async fn copy_object(&self, from: &str, to: &str) -> Result<bool, Error> {
+        let session = self.session.clone();
+        let source = CopySource::builder()
+            .bucket(BucketName::new(&self.config.bucket_name)?)
+            .object(ObjectKey::new(from)?)
+            .build();
+        match session.copy_object(&self.config.bucket_name, to)?.source(source).build().send().await {
+            Ok(_) => Ok(true),
+            Err(Error::S3Server(S3ServerError::S3Error(err))) if err.code() == MinioErrorCode::NoSuchKey => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// A URL that lets whoever holds it `GET` or `PUT` one object directly against the storage
+    /// until `expiry` has passed.
+    ///
+    /// Signed for the configured `storage_url`, so that address must be one clients can reach.
+    pub async fn presigned_url(&self, object_id: &str, method: Method, expiry: Duration) -> Result<String, Error> {
+        let session = self.session.clone();
+        let response = session
+            .get_presigned_object_url(&self.config.bucket_name, object_id, method)?
+            .expiry_seconds(expiry.as_secs() as u32)
+            .build()
+            .send()
+            .await?;
+        Ok(response.url)
+    }
 }
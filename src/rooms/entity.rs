@@ -6,38 +6,69 @@
 //! renamed on a response is an API change, the same rename here stops every existing row decoding.
 
 use crate::core::{DbRow, JsonColumn};
-use crate::rooms::model::{RoomChangeType, RoomType};
+use crate::rooms::model::{NotificationLevel, RoomChangeType, RoomRole, RoomType};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::types::Json;
 use uuid::Uuid;
 
-/// A row of `chat_room`, plus two values computed per caller.
+/// A row of `chat_room`, plus values computed or read per caller.
 ///
 /// `room_name` / `room_image_url` are `COALESCE`d: for a `Single` room they are the *other*
 /// participant's name and avatar, for a `Group` the room's own. `unread` is derived from the
 /// caller's `last_message_read_at` and is `None` for queries made outside any caller's context
-/// (`select_room`), which is why it is an `Option` rather than a `bool`.
+/// (`select_room`), which is why it is an `Option` rather than a `bool`. `unread_count` is the
+/// exact number behind it — other members' live timeline messages after the caller's read marker,
+/// thread replies and messages past the room's retention not included — and is `None` in the
+/// same cases.
 #[derive(Debug, sqlx::FromRow)]
 pub struct ChatRoomRow {
     pub id: Uuid,
     pub room_type: RoomType,
     pub room_name: Option<String>,
     pub room_image_url: Option<String>,
+    pub room_topic: Option<String>,
+    /// `None` inherits the server-wide default; see [`RetentionPolicy`](crate::rooms::model::RetentionPolicy).
+    pub retention_secs: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub latest_message: Option<DateTime<Utc>>,
     pub latest_message_preview_text: Option<Json<LastMessagePreviewJson>>,
     pub unread: Option<bool>,
+    pub unread_count: Option<i64>,
+    /// The caller's own notification settings; `None` wherever there is no caller, like `unread`.
+    pub notification_level: Option<NotificationLevel>,
+    pub muted_until: Option<DateTime<Utc>>,
 }
 
 impl DbRow for ChatRoomRow {}
 
+/// A room as moderation sees it: the bare `chat_room` row, never resolved against a caller, plus
+/// how many members it has. `unread` / `unread_count` are always `None`. Populated by
+/// [`RoomRepository::select_all_rooms`](crate::rooms::RoomRepository::select_all_rooms).
+#[derive(Debug, sqlx::FromRow)]
+pub struct RoomOverviewRow {
+    #[sqlx(flatten)]
+    pub room: ChatRoomRow,
+    pub member_count: i64,
+}
+
+impl DbRow for RoomOverviewRow {}
+
+/// One member's unread count in the room a message was just sent to or read in.
+#[derive(Debug, sqlx::FromRow)]
+pub struct UnreadCountRow {
+    pub user_id: Uuid,
+    pub room_unread: i64,
+}
+
+impl DbRow for UnreadCountRow {}
+
 /// A room participant.
 ///
 /// A row in `chat_room_participant` always means the user is currently in the room — leaving
-/// deletes the row, so there is no membership state. `joined_at` / `last_message_read_at` are
-/// `None` for users who are no longer members but still appear as historical message authors in a
-/// timeline page, which the `LEFT JOIN` in `select_message_senders` produces.
+/// deletes the row, so there is no membership state. `joined_at` / `last_message_read_at` / `role`
+/// are `None` for users who are no longer members but still appear as historical message authors
+/// in a timeline page, which the `LEFT JOIN` in `select_message_senders` produces.
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct RoomMemberRow {
     pub id: Uuid,
@@ -45,6 +76,7 @@ pub struct RoomMemberRow {
     pub profile_picture: Option<String>,
     pub joined_at: Option<DateTime<Utc>>,
     pub last_message_read_at: Option<DateTime<Utc>>,
+    pub role: Option<RoomRole>,
 }
 
 impl DbRow for RoomMemberRow {}
@@ -113,6 +145,14 @@ pub enum LastMessagePreviewJson {
         sender_username: String,
         room_change_type: RoomChangeType,
     },
+    Poll {
+        sender_username: String,
+        question: String,
+    },
+    /// The newest message in the room was deleted.
+    Deleted {
+        sender_username: String,
+    },
     /// A room that has no messages yet.
     New,
 }
@@ -148,6 +188,35 @@ pub struct InactiveShareRow {
 
 impl DbRow for InactiveShareRow {}
 
+/// A row of `room_invite_link`: a token anyone holding it can join the room with, until it expires,
+/// runs out of uses or is revoked. `max_uses` and `expires_at` are `None` when unlimited.
+#[derive(Debug, sqlx::FromRow)]
+pub struct InviteLinkRow {
+    pub token: String,
+    pub room_id: Uuid,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+}
+
+impl DbRow for InviteLinkRow {}
+
+/// A pending invitation as its invitee lists it, with the room and the inviter resolved.
+/// Populated by [`RoomRepository::select_invitations`](crate::rooms::RoomRepository::select_invitations).
+#[derive(Debug, sqlx::FromRow)]
+pub struct RoomInvitationRow {
+    pub room_id: Uuid,
+    pub room_name: Option<String>,
+    pub room_image_url: Option<String>,
+    pub invited_by: Uuid,
+    pub invited_by_name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DbRow for RoomInvitationRow {}
+
 #[cfg(test)]
 mod convention_guards {
     //! See `core::model` for why this is written as a compile-time `impls!` assertion rather than a
@@ -159,9 +228,13 @@ mod convention_guards {
     use serde::Serialize;
 
     const _: () = assert!(!impls!(ChatRoomRow: Serialize));
+    const _: () = assert!(!impls!(RoomOverviewRow: Serialize));
+    const _: () = assert!(!impls!(UnreadCountRow: Serialize));
     const _: () = assert!(!impls!(RoomMemberRow: Serialize));
     const _: () = assert!(!impls!(ActiveShareRow: Serialize));
     const _: () = assert!(!impls!(InactiveShareRow: Serialize));
+    const _: () = assert!(!impls!(InviteLinkRow: Serialize));
+    const _: () = assert!(!impls!(RoomInvitationRow: Serialize));
 
     // The storage type must keep both halves of its serde contract, or existing rows stop decoding.
     const _: () = assert!(impls!(LastMessagePreviewJson: Serialize));
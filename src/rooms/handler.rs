@@ -2,10 +2,16 @@ use crate::auth::CurrentUser;
 use crate::core::cursor::{CursorResults, decode_cursor};
 use crate::core::errors::{AppError, AppResponse};
 use crate::core::{ValidatedJson, ValidatedQuery};
-use crate::messaging::response::TimelinePageResponse;
-use crate::rooms::model::{RoomPaginationCursor, ShareTargetCursor};
-use crate::rooms::request::{NewRoomRequest, RoomListQuery, RoomSearchQuery, TimelineQuery};
-use crate::rooms::response::{RoomDetailResponse, RoomImageUploadResponse, RoomMemberResponse, RoomResponse, ShareTargetResponse};
+use crate::messaging::response::{ThreadPageResponse, TimelinePageResponse};
+use crate::rooms::model::{RoomPaginationCursor, ShareTargetCursor, ThreadCursor, TimelineAnchor};
+use crate::rooms::request::{
+    ChangeRoleRequest, CreateInviteLinkRequest, MarkReadQuery, NewRoomRequest, RenameRoomRequest, RoomListQuery, RoomNotificationSettingsRequest,
+    RoomRetentionRequest, RoomSearchQuery, RoomTopicRequest, ThreadQuery, TimelineQuery,
+};
+use crate::rooms::response::{
+    InviteLinkPreviewResponse, InviteLinkResponse, RoomDetailResponse, RoomImageUploadResponse, RoomInvitationResponse, RoomMemberResponse,
+    RoomNotificationSettingsResponse, RoomResponse, ShareTargetResponse, UnreadTotalResponse,
+};
 use crate::rooms::{RoomService, ShareService, TimelineService};
 use axum::Json;
 use axum::extract::{Multipart, Path, State};
@@ -19,7 +25,24 @@ pub async fn handle_scroll_chat_timeline(
     Path(room_id): Path<Uuid>,
     ValidatedQuery(params): ValidatedQuery<TimelineQuery>,
 ) -> AppResponse<Json<TimelinePageResponse>> {
-    let page = timeline.scroll_chat_timeline(user.subject, room_id, params.timestamp).await?;
+    let invalid = |_| AppError::Validation("Invalid Cursor-Parameters.".to_string());
+    let anchor = match (params.before, params.after, params.around) {
+        (_, _, Some(message_id)) => TimelineAnchor::Around(message_id),
+        (_, Some(after), None) => TimelineAnchor::After(decode_cursor(Some(after)).map_err(invalid)?),
+        (before, None, None) => TimelineAnchor::Before(decode_cursor(before).map_err(invalid)?),
+    };
+    let page = timeline.scroll_chat_timeline(user.subject, room_id, anchor, params.limit.get()).await?;
+    Ok(Json(page))
+}
+
+pub async fn handle_scroll_thread(
+    user: CurrentUser,
+    State(timeline): State<TimelineService>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedQuery(params): ValidatedQuery<ThreadQuery>,
+) -> AppResponse<Json<ThreadPageResponse>> {
+    let cursor: ThreadCursor = decode_cursor(params.cursor).map_err(|_| AppError::Validation("Invalid Cursor-Parameters.".to_string()))?;
+    let page = timeline.scroll_thread(user.subject, room_id, message_id, cursor, params.limit.get()).await?;
     Ok(Json(page))
 }
 
@@ -65,8 +88,18 @@ pub async fn handle_get_room_with_details(
     Ok(Json(room))
 }
 
-pub async fn mark_room_as_read(State(rooms): State<RoomService>, user: CurrentUser, Path(room_id): Path<Uuid>) -> AppResponse<()> {
-    rooms.mark_room_as_read(user.subject, room_id).await?;
+pub async fn handle_get_unread_total(State(rooms): State<RoomService>, user: CurrentUser) -> AppResponse<Json<UnreadTotalResponse>> {
+    let total = rooms.get_unread_total(user.subject).await?;
+    Ok(Json(total))
+}
+
+pub async fn mark_room_as_read(
+    State(rooms): State<RoomService>,
+    user: CurrentUser,
+    Path(room_id): Path<Uuid>,
+    ValidatedQuery(position): ValidatedQuery<MarkReadQuery>,
+) -> AppResponse<()> {
+    rooms.mark_room_as_read(user.subject, room_id, position).await?;
     Ok(())
 }
 
@@ -104,6 +137,116 @@ pub async fn handle_invite_to_room(
     Ok(())
 }
 
+pub async fn handle_kick_member(user: CurrentUser, State(rooms): State<RoomService>, Path((room_id, user_id)): Path<(Uuid, Uuid)>) -> AppResponse<()> {
+    rooms.kick_member(user.subject, room_id, user_id).await?;
+    Ok(())
+}
+
+pub async fn handle_change_member_role(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<ChangeRoleRequest>,
+) -> AppResponse<()> {
+    rooms.change_member_role(user.subject, room_id, user_id, payload.role).await?;
+    Ok(())
+}
+
+pub async fn handle_rename_room(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<RenameRoomRequest>,
+) -> AppResponse<()> {
+    rooms.rename_room(user.subject, room_id, payload.room_name).await?;
+    Ok(())
+}
+
+pub async fn handle_set_room_topic(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<RoomTopicRequest>,
+) -> AppResponse<()> {
+    rooms.set_room_topic(user.subject, room_id, payload.topic).await?;
+    Ok(())
+}
+
+pub async fn handle_set_room_retention(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<RoomRetentionRequest>,
+) -> AppResponse<()> {
+    rooms.set_room_retention(user.subject, room_id, payload.retention_secs).await?;
+    Ok(())
+}
+
+pub async fn handle_set_notification_settings(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<RoomNotificationSettingsRequest>,
+) -> AppResponse<Json<RoomNotificationSettingsResponse>> {
+    let settings = rooms
+        .set_notification_settings(user.subject, room_id, payload.level, payload.muted_until)
+        .await?;
+    Ok(Json(settings))
+}
+
+pub async fn handle_get_invitations(user: CurrentUser, State(rooms): State<RoomService>) -> AppResponse<Json<Vec<RoomInvitationResponse>>> {
+    let invitations = rooms.get_invitations(user.subject).await?;
+    Ok(Json(invitations))
+}
+
+pub async fn handle_accept_invitation(user: CurrentUser, State(rooms): State<RoomService>, Path(room_id): Path<Uuid>) -> AppResponse<Json<RoomResponse>> {
+    let room = rooms.accept_invitation(user.subject, room_id).await?;
+    Ok(Json(room))
+}
+
+pub async fn handle_decline_invitation(user: CurrentUser, State(rooms): State<RoomService>, Path(room_id): Path<Uuid>) -> AppResponse<()> {
+    rooms.decline_invitation(user.subject, room_id).await?;
+    Ok(())
+}
+
+pub async fn handle_create_invite_link(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateInviteLinkRequest>,
+) -> AppResponse<Json<InviteLinkResponse>> {
+    let link = rooms.create_invite_link(user.subject, room_id, payload.expires_at, payload.max_uses).await?;
+    Ok(Json(link))
+}
+
+pub async fn handle_get_invite_links(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(room_id): Path<Uuid>,
+) -> AppResponse<Json<Vec<InviteLinkResponse>>> {
+    let links = rooms.get_invite_links(user.subject, room_id).await?;
+    Ok(Json(links))
+}
+
+pub async fn handle_revoke_invite_link(user: CurrentUser, State(rooms): State<RoomService>, Path((room_id, token)): Path<(Uuid, String)>) -> AppResponse<()> {
+    rooms.revoke_invite_link(user.subject, room_id, token).await?;
+    Ok(())
+}
+
+pub async fn handle_preview_invite_link(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path(token): Path<String>,
+) -> AppResponse<Json<InviteLinkPreviewResponse>> {
+    let preview = rooms.preview_invite_link(user.subject, token).await?;
+    Ok(Json(preview))
+}
+
+pub async fn handle_join_by_invite_link(user: CurrentUser, State(rooms): State<RoomService>, Path(token): Path<String>) -> AppResponse<Json<RoomResponse>> {
+    let room = rooms.join_by_invite_link(user.subject, token).await?;
+    Ok(Json(room))
+}
+
 pub async fn handle_search_existing_single_room(
     user: CurrentUser,
     State(rooms): State<RoomService>,
@@ -163,3 +306,12 @@ pub async fn handle_get_read_states(
     let read_states = rooms.get_read_states(user.subject, room_id).await?;
     Ok(Json(read_states))
 }
+
+pub async fn handle_get_message_read_states(
+    user: CurrentUser,
+    State(rooms): State<RoomService>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> AppResponse<Json<Vec<RoomMemberResponse>>> {
+    let read_states = rooms.get_message_read_states(user.subject, room_id, message_id).await?;
+    Ok(Json(read_states))
+}
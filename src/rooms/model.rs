@@ -6,18 +6,21 @@
 
 use crate::rooms::entity::RoomMemberRow;
 use crate::rooms::response::RoomMemberResponse;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::Type;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Postgres, Type};
+use std::collections::HashSet;
 use std::fmt::{self, Display, Formatter};
 use uuid::Uuid;
 
 /// Whether a room is a 1-1 conversation or a named group.
 ///
-/// Stored in `chat_room.room_type` as `varchar` with a `CHECK` constraint (not a Postgres enum,
-/// despite the `type_name` below), which is why writes bind it through [`Display`].
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, Type, PartialEq)]
-#[sqlx(type_name = "room_type")]
+/// Stored in `chat_room.room_type` as `varchar` with a `CHECK` constraint, not a Postgres enum,
+/// which is why writes bind it through [`Display`] and why its [`Type`] is implemented by hand
+/// below rather than derived.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub enum RoomType {
     Single,
     Group,
@@ -33,12 +36,177 @@ impl Display for RoomType {
     }
 }
 
-/// What happened to a room's membership, as recorded in a preview text.
+/// A derived `Type` would name a Postgres type `room_type` that does not exist. The `query_as!`
+/// macros decode without checking, so that went unnoticed; a runtime `query_as` checks, and
+/// rejects the `varchar` column. Decode-only: see [`RoomType`] for how it is written.
+impl Type<Postgres> for RoomType {
+    fn type_info() -> PgTypeInfo {
+        <str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for RoomType {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        match <&str as Decode<Postgres>>::decode(value)? {
+            "Single" => Ok(RoomType::Single),
+            "Group" => Ok(RoomType::Group),
+            other => Err(format!("invalid room_type: {other}").into()),
+        }
+    }
+}
+
+/// What a participant may do in a group.
+///
+/// Declared from least to most privileged, so the derived `Ord` is rank: `role >= RoomRole::Admin`
+/// reads as "may manage". Every group has exactly one `Owner` — enforced by a partial unique index —
+/// who is the only one able to change roles. 1-1 rooms have no owner; both sides are members.
+///
+/// Stored in `chat_room_participant.role` as `varchar` with a `CHECK` constraint, so like
+/// [`RoomType`] it is written through [`Display`] and decoded by hand. The serde names are stored
+/// too, inside `UserRoleChanged` room-change messages: renaming a variant is a data migration.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoomRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Display for RoomRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            RoomRole::Member => "MEMBER",
+            RoomRole::Admin => "ADMIN",
+            RoomRole::Owner => "OWNER",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl Type<Postgres> for RoomRole {
+    fn type_info() -> PgTypeInfo {
+        <str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for RoomRole {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        match <&str as Decode<Postgres>>::decode(value)? {
+            "MEMBER" => Ok(RoomRole::Member),
+            "ADMIN" => Ok(RoomRole::Admin),
+            "OWNER" => Ok(RoomRole::Owner),
+            other => Err(format!("invalid room role: {other}").into()),
+        }
+    }
+}
+
+/// How much of a room a participant wants pushed while offline. Live delivery and replay are never
+/// affected: a quiet room still updates on screen, it just stops buzzing.
+///
+/// `Muted` comes with an optional `muted_until`; once that has passed the room counts as `All`
+/// again without anything being written. Stored in `chat_room_participant.notification_level`,
+/// written through [`Display`] and decoded by hand like [`RoomRole`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationLevel {
+    All,
+    /// Only messages mentioning the participant.
+    Mentions,
+    Muted,
+}
+
+impl Display for NotificationLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            NotificationLevel::All => "ALL",
+            NotificationLevel::Mentions => "MENTIONS",
+            NotificationLevel::Muted => "MUTED",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl Type<Postgres> for NotificationLevel {
+    fn type_info() -> PgTypeInfo {
+        <str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for NotificationLevel {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        match <&str as Decode<Postgres>>::decode(value)? {
+            "ALL" => Ok(NotificationLevel::All),
+            "MENTIONS" => Ok(NotificationLevel::Mentions),
+            "MUTED" => Ok(NotificationLevel::Muted),
+            other => Err(format!("invalid notification level: {other}").into()),
+        }
+    }
+}
+
+/// Who a new message mentions, as far as pushing it goes. A member named in `users` is pushed
+/// whatever their [`NotificationLevel`]; `room`, set by `@room`, reaches members on `Mentions` but
+/// not past `Muted`.
+#[derive(Debug, Default)]
+pub struct Mentioned {
+    pub users: HashSet<Uuid>,
+    pub room: bool,
+}
+
+/// How long messages stay readable: a room's own `retention_secs` where it has one, the server-wide
+/// default otherwise. `0` at either level keeps messages forever.
+///
+/// The timeline applies the cut-off on every read, so an expired message is gone the moment it
+/// expires; the reaper only catches the storage up.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    default_secs: i32,
+}
+
+impl RetentionPolicy {
+    /// Defaults beyond `i32::MAX` seconds — some 68 years — are clamped to what the column holds.
+    pub fn new(default_secs: u32) -> Self {
+        RetentionPolicy {
+            default_secs: i32::try_from(default_secs).unwrap_or(i32::MAX),
+        }
+    }
+
+    /// The server-wide default, for queries that resolve rooms in bulk.
+    pub fn default_secs(&self) -> i32 {
+        self.default_secs
+    }
+
+    /// The `created_at` at or before which a room's messages count as expired at `now`, or `None`
+    /// while the room keeps everything.
+    pub fn cutoff(&self, room_secs: Option<i32>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let secs = room_secs.unwrap_or(self.default_secs);
+        (secs > 0).then(|| now - TimeDelta::seconds(i64::from(secs)))
+    }
+}
+
+/// What happened to a room's membership or metadata, as recorded in a preview text.
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub enum RoomChangeType {
     LEAVE,
     JOIN,
     INVITE,
+    KICK,
+    ROLE,
+    RENAME,
+    IMAGE,
+    TOPIC,
+    RETENTION,
 }
 
 /// Keyset cursor for the joined-rooms list. Rooms are ordered by recent activity
@@ -75,6 +243,69 @@ pub struct ShareTargetCursor {
     pub last_id: Option<Uuid>,
 }
 
+/// Keyset cursor for the moderation room list, which runs over every room newest first on
+/// `(created_at, id) DESC` — unlike a member's list, it is not ordered by activity.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomCreatedCursor {
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_room_id: Option<Uuid>,
+}
+
+/// Keyset cursor for the main timeline: a message's position in `(created_at, message_id)` order,
+/// the id breaking ties between messages stored with the same timestamp. Whether a page continues
+/// before or after it is up to the query parameter it is passed as; a cursor without a position is
+/// the newest end of the timeline for `before`, and the oldest for `after`.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineCursor {
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_message_id: Option<Uuid>,
+}
+
+impl TimelineCursor {
+    pub fn at(created_at: DateTime<Utc>, message_id: Uuid) -> Self {
+        TimelineCursor {
+            last_created_at: Some(created_at),
+            last_message_id: Some(message_id),
+        }
+    }
+
+    /// Whether the cursor is one the server could have handed out: a full position, or none at
+    /// all. Half a position names no message and cannot be resumed from.
+    pub fn is_well_formed(&self) -> bool {
+        self.last_created_at.is_some() == self.last_message_id.is_some()
+    }
+}
+
+/// Which page of the main timeline to load.
+#[derive(Debug, Clone, Copy)]
+pub enum TimelineAnchor {
+    /// The messages older than the cursor; with the default cursor, the newest page.
+    Before(TimelineCursor),
+    /// The messages newer than the cursor.
+    After(TimelineCursor),
+    /// The page centred on one message, which is part of it — how a client jumps to the message a
+    /// reply quotes.
+    Around(Uuid),
+}
+
+/// Keyset cursor for a thread. Replies are read oldest first over `(created_at, message_id) ASC`;
+/// the id breaks ties between replies stored with the same timestamp.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadCursor {
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_message_id: Option<Uuid>,
+}
+
+impl ThreadCursor {
+    /// As [`TimelineCursor::is_well_formed`].
+    pub fn is_well_formed(&self) -> bool {
+        self.last_created_at.is_some() == self.last_message_id.is_some()
+    }
+}
+
 /// Cached per-room participant snapshot used for fast broadcast fan-out.
 ///
 /// Holds [`RoomMemberResponse`] rather than a dedicated cache struct, which is a deliberate
@@ -104,3 +335,77 @@ impl RoomContext {
         self.members.iter().find(|m| &m.id == user_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_room_setting_overrides_the_default() {
+        let now = Utc::now();
+        let policy = RetentionPolicy::new(86_400);
+
+        assert_eq!(policy.cutoff(None, now), Some(now - TimeDelta::days(1)));
+        assert_eq!(policy.cutoff(Some(3_600), now), Some(now - TimeDelta::hours(1)));
+        assert_eq!(policy.cutoff(Some(0), now), None);
+    }
+
+    #[test]
+    fn a_zero_default_keeps_rooms_without_a_setting_forever() {
+        let now = Utc::now();
+        let policy = RetentionPolicy::new(0);
+
+        assert_eq!(policy.cutoff(None, now), None);
+        assert_eq!(policy.cutoff(Some(60), now), Some(now - TimeDelta::minutes(1)));
+    }
+
+    #[test]
+    fn a_timeline_cursor_needs_both_halves_of_its_position_or_neither() {
+        let now = Utc::now();
+
+        assert!(TimelineCursor::default().is_well_formed());
+        assert!(TimelineCursor::at(now, Uuid::new_v4()).is_well_formed());
+        assert!(
+            !TimelineCursor {
+                last_created_at: Some(now),
+                last_message_id: None,
+            }
+            .is_well_formed()
+        );
+        assert!(
+            !TimelineCursor {
+                last_created_at: None,
+                last_message_id: Some(Uuid::new_v4()),
+            }
+            .is_well_formed()
+        );
+    }
+
+    #[test]
+    fn a_thread_cursor_needs_both_halves_of_its_position_or_neither() {
+        let now = Utc::now();
+
+        assert!(ThreadCursor::default().is_well_formed());
+        assert!(
+            ThreadCursor {
+                last_created_at: Some(now),
+                last_message_id: Some(Uuid::new_v4()),
+            }
+            .is_well_formed()
+        );
+        assert!(
+            !ThreadCursor {
+                last_created_at: Some(now),
+                last_message_id: None,
+            }
+            .is_well_formed()
+        );
+        assert!(
+            !ThreadCursor {
+                last_created_at: None,
+                last_message_id: Some(Uuid::new_v4()),
+            }
+            .is_well_formed()
+        );
+    }
+}
@@ -10,9 +10,12 @@
 use crate::broadcast::{BroadcastChannel, NotificationEvent};
 use crate::cache::redis_cache::Cache;
 use crate::core::errors::AppError;
-use crate::rooms::model::RoomContext;
+use crate::rooms::model::{Mentioned, RoomContext};
 use crate::rooms::repository::RoomRepository;
+use crate::rooms::response::UnreadCountResponse;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use tracing::warn;
 use uuid::Uuid;
 
 /// Resolves room membership and broadcasts to it.
@@ -73,6 +76,40 @@ impl RoomNotifier {
         self.bus.notify_all(user_ids, event).await;
     }
 
+    /// Broadcasts to an explicit recipient list, attaching each recipient's own unread counters
+    /// where `unread` has an entry for them.
+    pub async fn notify_users_with_unread(&self, user_ids: Vec<Uuid>, event: NotificationEvent, unread: HashMap<Uuid, UnreadCountResponse>) {
+        self.bus.notify_all_with_unread(user_ids, event, unread, &HashSet::new()).await;
+    }
+
+    /// Like [`Self::notify_users_with_unread`], for a new message in `room_id`: members who set the
+    /// room to mentions-only or muted it are not pushed while offline — unless `mentioned` reaches
+    /// them past their setting.
+    ///
+    /// If their settings cannot be read, everyone is pushed — the same trade as the bus makes when
+    /// presence is unavailable.
+    pub async fn notify_room_message(
+        &self,
+        room_id: &Uuid,
+        user_ids: Vec<Uuid>,
+        event: NotificationEvent,
+        unread: HashMap<Uuid, UnreadCountResponse>,
+        mentioned: &Mentioned,
+    ) {
+        let quiet = match self.rooms.select_quiet_members(room_id).await {
+            Ok(members) => members
+                .into_iter()
+                .filter(|(user_id, muted)| !mentioned.users.contains(user_id) && (*muted || !mentioned.room))
+                .map(|(user_id, _)| user_id)
+                .collect(),
+            Err(error) => {
+                warn!(%room_id, error = %error, "Failed to read notification settings, pushing to every offline member");
+                HashSet::new()
+            }
+        };
+        self.bus.notify_all_with_unread(user_ids, event, unread, &quiet).await;
+    }
+
     /// Broadcasts to a single user.
     pub async fn notify_user(&self, user_id: &Uuid, event: NotificationEvent) {
         self.bus.notify(user_id, event).await;
@@ -1,6 +1,8 @@
 use crate::core::{Database, Repository};
-use crate::rooms::entity::{ActiveShareRow, ChatRoomRow, InactiveShareRow, LastMessagePreviewJson, RoomMemberRow};
-use crate::rooms::model::{RoomPaginationCursor, RoomType};
+use crate::rooms::entity::{
+    ActiveShareRow, ChatRoomRow, InactiveShareRow, InviteLinkRow, LastMessagePreviewJson, RoomInvitationRow, RoomMemberRow, RoomOverviewRow, UnreadCountRow,
+};
+use crate::rooms::model::{NotificationLevel, RoomCreatedCursor, RoomPaginationCursor, RoomRole, RoomType};
 use chrono::{DateTime, Utc};
 use sqlx::types::Json;
 use sqlx::{PgConnection, Postgres, QueryBuilder};
@@ -34,7 +36,8 @@ impl RoomRepository {
                    users.display_name,
                    users.profile_picture,
                    participants.joined_at AS "joined_at?",
-                   participants.last_message_read_at
+                   participants.last_message_read_at,
+                   participants.role AS "role?: RoomRole"
             FROM chat_room_participant AS participants
             JOIN app_user AS users ON participants.user_id = users.id
             WHERE participants.room_id = $1
@@ -62,6 +65,7 @@ impl RoomRepository {
         name_filter: Option<&str>,
         cursor: RoomPaginationCursor,
         limit: i64,
+        default_retention_secs: i32,
     ) -> Result<Vec<ChatRoomRow>, sqlx::Error> {
         let rooms = sqlx::query_as!(
             ChatRoomRow,
@@ -74,7 +78,24 @@ impl RoomRepository {
                 room.latest_message_preview_text AS "latest_message_preview_text: Json<LastMessagePreviewJson>",
                 COALESCE(other_user.display_name, room.room_name) AS room_name,
                 COALESCE(other_user.profile_picture, room.room_image_url) AS room_image_url,
-                COALESCE(p1.last_message_read_at < room.latest_message, TRUE) AS unread
+                room.room_topic,
+                room.retention_secs,
+                COALESCE(p1.last_message_read_at < room.latest_message, TRUE) AS unread,
+                p1.notification_level AS "notification_level?: NotificationLevel",
+                p1.muted_until,
+                (
+                    SELECT COUNT(*)
+                    FROM chat_message AS message
+                    WHERE message.chat_room_id = room.id
+                      AND message.sender_id <> $1
+                      AND message.deleted_at IS NULL
+                      AND message.thread_root_id IS NULL
+                      AND message.created_at > COALESCE(p1.last_message_read_at, '-infinity'::timestamptz)
+                      AND (
+                          COALESCE(room.retention_secs, $6) = 0
+                          OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $6))
+                      )
+                ) AS unread_count
             FROM
                 chat_room_participant AS p1
             JOIN
@@ -109,13 +130,68 @@ impl RoomRepository {
             name_filter,
             cursor.last_seen_latest_message,
             cursor.last_seen_room_id,
-            limit
+            limit,
+            default_retention_secs
         )
         .fetch_all(self.db.pool())
         .await?;
         Ok(rooms)
     }
 
+    /// Every room, regardless of who asks — the moderation view. Newest first, keyset over
+    /// `(created_at, id)`; callers pass `limit = page_size + 1`.
+    ///
+    /// The optional name filter matches a group's own name or the display name of any member, which
+    /// is the only name a 1-1 room has.
+    pub async fn select_all_rooms(&self, name_filter: Option<&str>, cursor: RoomCreatedCursor, limit: i64) -> Result<Vec<RoomOverviewRow>, sqlx::Error> {
+        let rooms = sqlx::query_as::<_, RoomOverviewRow>(
+            r#"
+            SELECT
+                room.id,
+                room.room_type,
+                room.room_name,
+                room.room_image_url,
+                room.room_topic,
+                room.retention_secs,
+                room.created_at,
+                room.latest_message,
+                room.latest_message_preview_text,
+                NULL::boolean AS unread,
+                NULL::bigint AS unread_count,
+                NULL::varchar AS notification_level,
+                NULL::timestamptz AS muted_until,
+                (SELECT COUNT(*) FROM chat_room_participant p WHERE p.room_id = room.id) AS member_count
+            FROM chat_room AS room
+            WHERE
+                (
+                    $1::text IS NULL
+                    OR room.room_name ILIKE concat('%', $1, '%')
+                    OR EXISTS (
+                        SELECT 1
+                        FROM chat_room_participant p
+                        JOIN app_user member ON member.id = p.user_id
+                        WHERE p.room_id = room.id AND member.display_name ILIKE concat('%', $1, '%')
+                    )
+                )
+                AND (
+                    $2::timestamptz IS NULL
+                    OR room.created_at < $2
+                    OR (room.created_at = $2 AND room.id < $3)
+                )
+            ORDER BY
+                room.created_at DESC, room.id DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(name_filter)
+        .bind(cursor.last_created_at)
+        .bind(cursor.last_room_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rooms)
+    }
+
     /// *Active* section of the share-target list: group rooms the client is in, plus
     /// friends with whom an 1-1 room already exists, merged and ordered by recent
     /// activity (`active_at DESC`, `room_id` tie-breaker). Friends without a 1-1 room
@@ -256,7 +332,7 @@ impl RoomRepository {
         Ok(())
     }
 
-    pub async fn find_specific_joined_room(&self, room_id: &Uuid, user_id: &Uuid) -> Result<Option<ChatRoomRow>, sqlx::Error> {
+    pub async fn find_specific_joined_room(&self, room_id: &Uuid, user_id: &Uuid, default_retention_secs: i32) -> Result<Option<ChatRoomRow>, sqlx::Error> {
         let room = sqlx::query_as!(
             ChatRoomRow,
             r#"
@@ -268,7 +344,24 @@ impl RoomRepository {
                 room.latest_message_preview_text AS "latest_message_preview_text: Json<LastMessagePreviewJson>",
                 COALESCE(other_user.display_name, room.room_name) AS room_name,
                 COALESCE(other_user.profile_picture, room.room_image_url) AS room_image_url,
-                COALESCE(participants.last_message_read_at < room.latest_message, TRUE) AS unread
+                room.room_topic,
+                room.retention_secs,
+                COALESCE(participants.last_message_read_at < room.latest_message, TRUE) AS unread,
+                participants.notification_level AS "notification_level?: NotificationLevel",
+                participants.muted_until,
+                (
+                    SELECT COUNT(*)
+                    FROM chat_message AS message
+                    WHERE message.chat_room_id = room.id
+                      AND message.sender_id <> $1
+                      AND message.deleted_at IS NULL
+                      AND message.thread_root_id IS NULL
+                      AND message.created_at > COALESCE(participants.last_message_read_at, '-infinity'::timestamptz)
+                      AND (
+                          COALESCE(room.retention_secs, $3) = 0
+                          OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $3))
+                      )
+                ) AS unread_count
             FROM
                 chat_room_participant AS participants
             JOIN
@@ -291,7 +384,8 @@ impl RoomRepository {
                 AND room.id = $2
             "#,
             user_id,
-            room_id
+            room_id,
+            default_retention_secs
         )
         .fetch_optional(self.db.pool())
         .await?;
@@ -311,6 +405,7 @@ impl RoomRepository {
         room_type: RoomType,
         room_name: Option<&str>,
         participants: &[Uuid],
+        owner: Option<&Uuid>,
     ) -> Result<ChatRoomRow, sqlx::Error> {
         let now = Utc::now();
         let room = sqlx::query_as!(
@@ -318,7 +413,8 @@ impl RoomRepository {
             r#"
             INSERT INTO chat_room (id, room_type, room_name, created_at, latest_message, latest_message_preview_text)
             VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, room_name, created_at, room_type as "room_type: RoomType", latest_message, latest_message_preview_text AS "latest_message_preview_text: Json<LastMessagePreviewJson>", room_image_url, TRUE as "unread: _"
+            RETURNING id, room_name, created_at, room_type as "room_type: RoomType", latest_message, latest_message_preview_text AS "latest_message_preview_text: Json<LastMessagePreviewJson>", room_image_url,
+                room_topic, retention_secs, TRUE as "unread: _", NULL::bigint as "unread_count: _", NULL::varchar as "notification_level: NotificationLevel", NULL::timestamptz as "muted_until: _"
             "#,
             Uuid::new_v4(),
             room_type.to_string(),
@@ -331,10 +427,11 @@ impl RoomRepository {
         .await?;
 
         //https://docs.rs/sqlx-core/0.5.13/sqlx_core/query_builder/struct.QueryBuilder.html#method.push_values
-        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("INSERT INTO chat_room_participant (user_id, room_id, joined_at) ");
+        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("INSERT INTO chat_room_participant (user_id, room_id, joined_at, role) ");
         builder
             .push_values(participants, |mut db, user| {
-                db.push_bind(user).push_bind(room.id).push_bind(Utc::now());
+                let role = if Some(user) == owner { RoomRole::Owner } else { RoomRole::Member };
+                db.push_bind(user).push_bind(room.id).push_bind(Utc::now()).push_bind(role.to_string());
             })
             .build()
             .execute(&mut *conn)
@@ -354,8 +451,13 @@ impl RoomRepository {
                 created_at,
                 latest_message,
                 room_image_url,
+                room_topic,
+                retention_secs,
                 latest_message_preview_text AS "latest_message_preview_text: Json<LastMessagePreviewJson>",
-                NULL::boolean as "unread: _"
+                NULL::boolean as "unread: _",
+                NULL::bigint as "unread_count: _",
+                NULL::varchar as "notification_level: NotificationLevel",
+                NULL::timestamptz as "muted_until: _"
             FROM chat_room
             WHERE id = $1
             "#,
@@ -428,7 +530,8 @@ impl RoomRepository {
                 users.display_name,
                 users.profile_picture,
                 participants.joined_at AS "joined_at?",
-                participants.last_message_read_at
+                participants.last_message_read_at,
+                participants.role AS "role?: RoomRole"
             FROM chat_room_participant AS participants
             JOIN app_user AS users ON participants.user_id = users.id
             WHERE participants.user_id = $1 AND participants.room_id = $2
@@ -441,6 +544,46 @@ impl RoomRepository {
         Ok(user)
     }
 
+    /// The user's role in the room, `None` if they are not in it.
+    pub async fn select_member_role(&self, room_id: &Uuid, user_id: &Uuid) -> Result<Option<RoomRole>, sqlx::Error> {
+        let role = sqlx::query_scalar::<_, RoomRole>("SELECT role FROM chat_room_participant WHERE room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(user_id)
+            .fetch_optional(self.db.pool())
+            .await?;
+        Ok(role)
+    }
+
+    /// Sets a participant's role. Handing over ownership is two calls in one transaction — demote
+    /// the owner first, or the one-owner index rejects the promotion.
+    pub async fn update_member_role(&self, conn: &mut PgConnection, room_id: &Uuid, user_id: &Uuid, role: RoomRole) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE chat_room_participant SET role = $3 WHERE room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(user_id)
+            .bind(role.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Who inherits a group whose owner is leaving: the longest-standing admin, or failing that the
+    /// longest-standing member. Read inside the leave transaction, after the owner's row is gone.
+    pub async fn select_successor(&self, conn: &mut PgConnection, room_id: &Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+        let successor = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT user_id
+            FROM chat_room_participant
+            WHERE room_id = $1
+            ORDER BY (role = 'ADMIN') DESC, joined_at, user_id
+            LIMIT 1
+            "#,
+        )
+        .bind(room_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(successor)
+    }
+
     pub async fn select_room_participants_ids(&self, room_id: &Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
         let result = sqlx::query!(r#"SELECT user_id FROM chat_room_participant WHERE room_id = $1"#, room_id)
             .fetch_all(self.db.pool())
@@ -466,27 +609,91 @@ impl RoomRepository {
         Ok(())
     }
 
-    pub async fn update_user_read_status<'e, E>(&self, exec: E, room_id: &Uuid, user_id: &Uuid) -> Result<(), sqlx::Error>
+    /// Moves the user's read marker forward to `read_at`. A marker already at or past it is left
+    /// alone, so a device reporting a stale position cannot un-read the room. Returns whether the
+    /// marker moved.
+    pub async fn advance_read_marker<'e, E>(&self, exec: E, room_id: &Uuid, user_id: &Uuid, read_at: DateTime<Utc>) -> Result<bool, sqlx::Error>
     where
         E: sqlx::Executor<'e, Database = Postgres>,
     {
-        sqlx::query!(
-            "Update chat_room_participant SET last_message_read_at = NOW() WHERE user_id = $1 AND room_id = $2",
-            user_id,
-            room_id
+        let result = sqlx::query(
+            r#"
+            UPDATE chat_room_participant
+            SET last_message_read_at = $3
+            WHERE user_id = $1 AND room_id = $2
+              AND (last_message_read_at IS NULL OR last_message_read_at < $3)
+            "#,
         )
+        .bind(user_id)
+        .bind(room_id)
+        .bind(read_at)
         .execute(exec)
         .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    pub async fn update_room_img_url(&self, room_id: &Uuid, image_url: &String) -> Result<(), sqlx::Error> {
+    pub async fn update_room_img_url(&self, conn: &mut PgConnection, room_id: &Uuid, image_url: &String) -> Result<(), sqlx::Error> {
         sqlx::query!("UPDATE chat_room SET room_image_url = $1 WHERE id = $2", image_url, room_id)
-            .execute(self.db.pool())
+            .execute(&mut *conn)
             .await?;
         Ok(())
     }
 
+    pub async fn update_room_name(&self, conn: &mut PgConnection, room_id: &Uuid, room_name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE chat_room SET room_name = $1 WHERE id = $2")
+            .bind(room_name)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_room_topic(&self, conn: &mut PgConnection, room_id: &Uuid, topic: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE chat_room SET room_topic = $1 WHERE id = $2")
+            .bind(topic)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// `None` puts the room back on the server default.
+    pub async fn update_room_retention(&self, conn: &mut PgConnection, room_id: &Uuid, retention_secs: Option<i32>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE chat_room SET retention_secs = $1 WHERE id = $2")
+            .bind(retention_secs)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn select_room_retention(&self, room_id: &Uuid) -> Result<Option<i32>, sqlx::Error> {
+        let retention_secs = sqlx::query_scalar::<_, Option<i32>>("SELECT retention_secs FROM chat_room WHERE id = $1")
+            .bind(room_id)
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(retention_secs)
+    }
+
+    /// Empties the room preview once the reaper took every message up to `until` and nothing newer
+    /// is left — the preview then quoted an expired message. `latest_message` stays, so the room
+    /// keeps its place in the list.
+    pub async fn clear_expired_preview(&self, conn: &mut PgConnection, room_id: &Uuid, until: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE chat_room
+            SET latest_message_preview_text = NULL
+            WHERE id = $1
+              AND NOT EXISTS (SELECT 1 FROM chat_message WHERE chat_room_id = $1 AND created_at > $2)
+            "#,
+        )
+        .bind(room_id)
+        .bind(until)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
     pub async fn remove_user_from_room(
         &self,
         conn: &mut PgConnection,
@@ -536,7 +743,8 @@ impl RoomRepository {
                 users.display_name,
                 users.profile_picture,
                 participants.joined_at AS "joined_at?",
-                participants.last_message_read_at AS "last_message_read_at?"
+                participants.last_message_read_at AS "last_message_read_at?",
+                participants.role AS "role?: RoomRole"
             FROM app_user AS users
                 LEFT JOIN chat_room_participant AS participants
                     ON participants.user_id = users.id AND participants.room_id = $1
@@ -581,4 +789,293 @@ impl RoomRepository {
         .await?;
         Ok(())
     }
+
+    /// Rewrites the room's preview text, but only if the message created at `message_created_at` is
+    /// still the newest one in the room. Returns whether the preview was replaced.
+    ///
+    /// `latest_message` is left alone on purpose: editing or deleting an old message must not bump
+    /// the room to the top of everyone's list or mark it unread again.
+    pub async fn refresh_preview_if_latest(
+        &self,
+        conn: &mut PgConnection,
+        room_id: &Uuid,
+        message_created_at: DateTime<Utc>,
+        preview_text: &LastMessagePreviewJson,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE chat_room
+            SET latest_message_preview_text = $2
+            WHERE id = $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM chat_message
+                  WHERE chat_room_id = $1 AND created_at > $3
+              )
+            "#,
+        )
+        .bind(room_id)
+        .bind(Json(preview_text))
+        .bind(message_created_at)
+        .execute(&mut *conn)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records an invitation. Returns `false` when the user is already invited to the room.
+    pub async fn insert_invitation(
+        &self,
+        conn: &mut PgConnection,
+        room_id: &Uuid,
+        user_id: &Uuid,
+        invited_by: &Uuid,
+        at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO room_invitation (room_id, user_id, invited_by, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (room_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(room_id)
+        .bind(user_id)
+        .bind(invited_by)
+        .bind(at)
+        .execute(&mut *conn)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The user's pending invitations, newest first.
+    pub async fn select_invitations(&self, user_id: &Uuid) -> Result<Vec<RoomInvitationRow>, sqlx::Error> {
+        let invitations = sqlx::query_as::<_, RoomInvitationRow>(
+            r#"
+            SELECT
+                invitation.room_id,
+                room.room_name,
+                room.room_image_url,
+                invitation.invited_by,
+                inviter.display_name AS invited_by_name,
+                invitation.created_at
+            FROM room_invitation AS invitation
+            JOIN chat_room AS room ON room.id = invitation.room_id
+            JOIN app_user AS inviter ON inviter.id = invitation.invited_by
+            WHERE invitation.user_id = $1
+            ORDER BY invitation.created_at DESC, invitation.room_id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(invitations)
+    }
+
+    /// Deletes the invitation, returning who sent it — `None` if there was none to take.
+    pub async fn take_invitation(&self, conn: &mut PgConnection, room_id: &Uuid, user_id: &Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+        let invited_by = sqlx::query_scalar::<_, Uuid>("DELETE FROM room_invitation WHERE room_id = $1 AND user_id = $2 RETURNING invited_by")
+            .bind(room_id)
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        Ok(invited_by)
+    }
+
+    /// Returns `false` when there was no such invitation.
+    pub async fn delete_invitation(&self, room_id: &Uuid, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM room_invitation WHERE room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(user_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn insert_invite_link(&self, link: &InviteLinkRow) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO room_invite_link (token, room_id, created_by, created_at, expires_at, max_uses, use_count)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&link.token)
+        .bind(link.room_id)
+        .bind(link.created_by)
+        .bind(link.created_at)
+        .bind(link.expires_at)
+        .bind(link.max_uses)
+        .bind(link.use_count)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// The room's invite links, newest first — including spent and expired ones, which stay until
+    /// revoked.
+    pub async fn select_invite_links(&self, room_id: &Uuid) -> Result<Vec<InviteLinkRow>, sqlx::Error> {
+        let links = sqlx::query_as::<_, InviteLinkRow>("SELECT * FROM room_invite_link WHERE room_id = $1 ORDER BY created_at DESC, token")
+            .bind(room_id)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(links)
+    }
+
+    pub async fn select_invite_link(&self, token: &str) -> Result<Option<InviteLinkRow>, sqlx::Error> {
+        let link = sqlx::query_as::<_, InviteLinkRow>("SELECT * FROM room_invite_link WHERE token = $1")
+            .bind(token)
+            .fetch_optional(self.db.pool())
+            .await?;
+        Ok(link)
+    }
+
+    /// Whether `user_id` is in the room, read while locking the room's row until the transaction
+    /// ends so two joins of the same user cannot both see them missing. `None` if there is no such
+    /// room. The lock leaves the row's key alone, so messages keep being inserted meanwhile.
+    pub async fn lock_room_membership(&self, conn: &mut PgConnection, room_id: &Uuid, user_id: &Uuid) -> Result<Option<bool>, sqlx::Error> {
+        let is_member = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM chat_room_participant WHERE room_id = $1 AND user_id = $2)
+            FROM chat_room
+            WHERE id = $1
+            FOR NO KEY UPDATE
+            "#,
+        )
+        .bind(room_id)
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(is_member)
+    }
+
+    /// Reads the link and locks it until the transaction ends, so two joins racing for its last use
+    /// cannot both see one left.
+    pub async fn lock_invite_link(&self, conn: &mut PgConnection, token: &str) -> Result<Option<InviteLinkRow>, sqlx::Error> {
+        let link = sqlx::query_as::<_, InviteLinkRow>("SELECT * FROM room_invite_link WHERE token = $1 FOR UPDATE")
+            .bind(token)
+            .fetch_optional(&mut *conn)
+            .await?;
+        Ok(link)
+    }
+
+    pub async fn record_invite_link_use(&self, conn: &mut PgConnection, token: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE room_invite_link SET use_count = use_count + 1 WHERE token = $1")
+            .bind(token)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns `false` when the room has no such link.
+    pub async fn delete_invite_link(&self, room_id: &Uuid, token: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM room_invite_link WHERE room_id = $1 AND token = $2")
+            .bind(room_id)
+            .bind(token)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Stores the participant's notification settings. Returns `false` when they are not in the room.
+    pub async fn update_notification_settings(
+        &self,
+        room_id: &Uuid,
+        user_id: &Uuid,
+        level: NotificationLevel,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE chat_room_participant SET notification_level = $3, muted_until = $4 WHERE room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(user_id)
+            .bind(level.to_string())
+            .bind(muted_until)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Participants of `room_id` who currently want no push for an ordinary message: those on
+    /// `MENTIONS`, and those on `MUTED` whose mute has not run out. Each comes with whether they
+    /// are muted rather than on mentions-only.
+    pub async fn select_quiet_members(&self, room_id: &Uuid) -> Result<Vec<(Uuid, bool)>, sqlx::Error> {
+        let members = sqlx::query_as::<_, (Uuid, bool)>(
+            r#"
+            SELECT user_id, notification_level = 'MUTED' AS muted
+            FROM chat_room_participant
+            WHERE room_id = $1
+              AND (
+                  notification_level = 'MENTIONS'
+                  OR (notification_level = 'MUTED' AND (muted_until IS NULL OR muted_until > NOW()))
+              )
+            "#,
+        )
+        .bind(room_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(members)
+    }
+
+    /// Unread counts in `room_id` for those of `user_ids` who are in it.
+    ///
+    /// Uses the rule behind `ChatRoomRow::unread_count`. One statement for the whole audience of
+    /// an event, so a message to a large group costs one query rather than one per member. Only
+    /// the room is counted: totals across every room are [`Self::count_total_unread`]'s, asked
+    /// for one user at a time.
+    pub async fn select_room_unread_counts(&self, room_id: &Uuid, user_ids: &[Uuid], default_retention_secs: i32) -> Result<Vec<UnreadCountRow>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, UnreadCountRow>(
+            r#"
+            SELECT
+                participant.user_id,
+                COUNT(message.message_id) AS room_unread
+            FROM chat_room_participant AS participant
+            JOIN chat_room AS room ON room.id = participant.room_id
+            LEFT JOIN chat_message AS message
+                ON message.chat_room_id = participant.room_id
+                AND message.sender_id <> participant.user_id
+                AND message.deleted_at IS NULL
+                AND message.thread_root_id IS NULL
+                AND message.created_at > COALESCE(participant.last_message_read_at, '-infinity'::timestamptz)
+                AND (
+                    COALESCE(room.retention_secs, $3) = 0
+                    OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $3))
+                )
+            WHERE participant.room_id = $1
+              AND participant.user_id = ANY($2)
+            GROUP BY participant.user_id
+            "#,
+        )
+        .bind(room_id)
+        .bind(user_ids)
+        .bind(default_retention_secs)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    /// The caller's unread messages across every joined room, and how many rooms they fall in.
+    pub async fn count_total_unread(&self, user_id: &Uuid, default_retention_secs: i32) -> Result<(i64, i64), sqlx::Error> {
+        let totals = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                COUNT(message.message_id) AS total,
+                COUNT(DISTINCT message.chat_room_id) AS rooms
+            FROM chat_room_participant AS participant
+            JOIN chat_room AS room ON room.id = participant.room_id
+            JOIN chat_message AS message
+                ON message.chat_room_id = participant.room_id
+                AND message.sender_id <> participant.user_id
+                AND message.deleted_at IS NULL
+                AND message.thread_root_id IS NULL
+                AND message.created_at > COALESCE(participant.last_message_read_at, '-infinity'::timestamptz)
+                AND (
+                    COALESCE(room.retention_secs, $2) = 0
+                    OR message.created_at > NOW() - make_interval(secs => COALESCE(room.retention_secs, $2))
+                )
+            WHERE participant.user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(default_retention_secs)
+        .fetch_one(self.db.pool())
+        .await?;
+        Ok(totals)
+    }
 }
@@ -3,7 +3,7 @@
 use crate::core::ApiRequest;
 use crate::core::cursor::PageSize;
 use crate::messaging::request::FirstMessageRequest;
-use crate::rooms::model::RoomType;
+use crate::rooms::model::{NotificationLevel, RoomRole, RoomType};
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
@@ -81,14 +81,141 @@ pub struct RoomSearchQuery {
 
 impl ApiRequest for RoomSearchQuery {}
 
+/// Body of `PUT /api/v1/rooms/{room_id}/members/{user_id}/role`.
+///
+/// Setting `OWNER` hands the group over; the current owner becomes an admin in the same step.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeRoleRequest {
+    pub role: RoomRole,
+}
+
+impl ApiRequest for ChangeRoleRequest {}
+
+/// Body of `PUT /api/v1/rooms/{room_id}/name`. Same bounds as a name given at creation.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameRoomRequest {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters long."))]
+    pub room_name: String,
+}
+
+impl ApiRequest for RenameRoomRequest {}
+
+/// Body of `PUT /api/v1/rooms/{room_id}/topic`. A `null` topic clears it.
+#[derive(Debug, Deserialize, Validate)]
+pub struct RoomTopicRequest {
+    #[validate(length(min = 1, max = 500, message = "must be between 1 and 500 characters long."))]
+    pub topic: Option<String>,
+}
+
+impl ApiRequest for RoomTopicRequest {}
+
+/// Body of `PUT /api/v1/rooms/{room_id}/retention`: seconds a message stays, such as `86400` for a
+/// day. `0` keeps messages forever; `null` follows the server default again. At most a year.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomRetentionRequest {
+    #[validate(range(min = 0, max = 31_536_000, message = "must be between 0 and 31536000 seconds."))]
+    pub retention_secs: Option<i32>,
+}
+
+impl ApiRequest for RoomRetentionRequest {}
+
+/// Body of `PUT /api/v1/rooms/{room_id}/notification-settings`.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "check_mute_window"))]
+pub struct RoomNotificationSettingsRequest {
+    pub level: NotificationLevel,
+    /// Only with `MUTED`: when the mute ends. Absent mutes until the settings change again.
+    pub muted_until: Option<DateTime<Utc>>,
+}
+
+impl ApiRequest for RoomNotificationSettingsRequest {}
+
+/// A mute end belongs to a mute, and one already in the past would be a mute that never happened.
+fn check_mute_window(request: &RoomNotificationSettingsRequest) -> Result<(), ValidationError> {
+    match request.muted_until {
+        Some(_) if request.level != NotificationLevel::Muted => Err(ValidationError::new("muted_until_requires_muted_level")),
+        Some(until) if until <= Utc::now() => Err(ValidationError::new("muted_until_must_be_in_the_future")),
+        _ => Ok(()),
+    }
+}
+
+/// Body of `POST /api/v1/rooms/{room_id}/invite-links`. Both limits are optional; a link without
+/// either works until it is revoked.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "check_link_expiry"))]
+pub struct CreateInviteLinkRequest {
+    pub expires_at: Option<DateTime<Utc>>,
+    #[validate(range(min = 1, max = 1000, message = "must be between 1 and 1000."))]
+    pub max_uses: Option<i32>,
+}
+
+impl ApiRequest for CreateInviteLinkRequest {}
+
+fn check_link_expiry(request: &CreateInviteLinkRequest) -> Result<(), ValidationError> {
+    match request.expires_at {
+        Some(expires_at) if expires_at <= Utc::now() => Err(ValidationError::new("expires_at_must_be_in_the_future")),
+        _ => Ok(()),
+    }
+}
+
 /// Query params for `GET /api/v1/rooms/{room_id}/timeline`.
 ///
-/// The timeline pages backwards from a timestamp rather than through an opaque cursor, because
-/// `chat_message` is keyset-indexed on `(chat_room_id, created_at DESC)` and the client already
-/// holds the timestamp of its oldest loaded message.
+/// At most one of the three: `before` and `after` take a cursor from a previous page, `around` the
+/// id of a message to load the page around. With none, the newest page is loaded.
 #[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "check_timeline_anchor"))]
 pub struct TimelineQuery {
-    pub timestamp: DateTime<Utc>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub around: Option<Uuid>,
+    #[serde(default)]
+    pub limit: PageSize,
 }
 
 impl ApiRequest for TimelineQuery {}
+
+fn check_timeline_anchor(query: &TimelineQuery) -> Result<(), ValidationError> {
+    let given = [query.before.is_some(), query.after.is_some(), query.around.is_some()];
+    if given.into_iter().filter(|given| *given).count() > 1 {
+        return Err(ValidationError::new("only_one_of_before_after_around"));
+    }
+    Ok(())
+}
+
+/// Query params for `GET /api/v1/rooms/{room_id}/messages/{message_id}/thread`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ThreadQuery {
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: PageSize,
+}
+
+impl ApiRequest for ThreadQuery {}
+
+/// Query params for `POST /api/v1/rooms/{room_id}/mark-read`.
+///
+/// Names the position the client has actually read up to: a message, or a point in time. With
+/// neither, the whole room is read — what the endpoint always did, so older clients posting without
+/// parameters keep working. The marker only ever moves forward; see
+/// [`RoomService::mark_room_as_read`](crate::rooms::RoomService::mark_room_as_read).
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "check_read_position"))]
+pub struct MarkReadQuery {
+    pub message_id: Option<Uuid>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl ApiRequest for MarkReadQuery {}
+
+/// A read position is either a message or a timestamp, never both: two positions could disagree.
+fn check_read_position(query: &MarkReadQuery) -> Result<(), ValidationError> {
+    if query.message_id.is_some() && query.read_at.is_some() {
+        return Err(ValidationError::new("message_id_and_read_at_are_exclusive"));
+    }
+    Ok(())
+}
@@ -1,8 +1,10 @@
 //! Client-facing shapes for the rooms domain.
 
 use crate::core::ApiResponse;
-use crate::rooms::entity::{ActiveShareRow, ChatRoomRow, InactiveShareRow, LastMessagePreviewJson, RoomMemberRow};
-use crate::rooms::model::{RoomChangeType, RoomType};
+use crate::rooms::entity::{
+    ActiveShareRow, ChatRoomRow, InactiveShareRow, InviteLinkRow, LastMessagePreviewJson, RoomInvitationRow, RoomMemberRow, UnreadCountRow,
+};
+use crate::rooms::model::{NotificationLevel, RoomChangeType, RoomRole, RoomType};
 use crate::utils::truncate_preview;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -19,10 +21,24 @@ pub struct RoomResponse {
     pub room_type: RoomType,
     pub room_image_url: Option<String>,
     pub room_name: Option<String>,
+    /// A group's description. Omitted while none is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_topic: Option<String>,
+    /// How long the room keeps its messages, in seconds, `0` meaning forever. Omitted while the room
+    /// follows the server default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_secs: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub latest_message: Option<DateTime<Utc>>,
     pub unread: Option<bool>,
+    /// Omitted where it was not computed for a caller, such as a freshly inserted room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unread_count: Option<i64>,
     pub latest_message_preview_text: LastMessagePreviewResponse,
+    /// The caller's own settings for this room. Omitted, like `unreadCount`, where there is no
+    /// caller to read them for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_settings: Option<RoomNotificationSettingsResponse>,
 }
 
 impl ApiResponse for RoomResponse {}
@@ -34,14 +50,21 @@ impl From<ChatRoomRow> for RoomResponse {
             room_type: row.room_type,
             room_image_url: row.room_image_url,
             room_name: row.room_name,
+            room_topic: row.room_topic,
+            retention_secs: row.retention_secs,
             created_at: row.created_at,
             latest_message: row.latest_message,
             unread: row.unread,
+            unread_count: row.unread_count,
             // A room with no messages has a NULL preview column; `New` is how that reads to a client.
             latest_message_preview_text: row
                 .latest_message_preview_text
                 .map(|json| LastMessagePreviewResponse::from(json.0))
                 .unwrap_or(LastMessagePreviewResponse::New),
+            notification_settings: row.notification_level.map(|level| RoomNotificationSettingsResponse {
+                level,
+                muted_until: row.muted_until,
+            }),
         }
     }
 }
@@ -53,18 +76,74 @@ impl From<&ChatRoomRow> for RoomResponse {
             room_type: row.room_type,
             room_image_url: row.room_image_url.clone(),
             room_name: row.room_name.clone(),
+            room_topic: row.room_topic.clone(),
+            retention_secs: row.retention_secs,
             created_at: row.created_at,
             latest_message: row.latest_message,
             unread: row.unread,
+            unread_count: row.unread_count,
             latest_message_preview_text: row
                 .latest_message_preview_text
                 .as_ref()
                 .map(|json| LastMessagePreviewResponse::from(json.0.clone()))
                 .unwrap_or(LastMessagePreviewResponse::New),
+            notification_settings: row.notification_level.map(|level| RoomNotificationSettingsResponse {
+                level,
+                muted_until: row.muted_until,
+            }),
         }
     }
 }
 
+/// A participant's notification settings for one room. `mutedUntil` is only ever set with `MUTED`,
+/// and is `null` for a mute without an end.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomNotificationSettingsResponse {
+    pub level: NotificationLevel,
+    pub muted_until: Option<DateTime<Utc>>,
+}
+
+impl ApiResponse for RoomNotificationSettingsResponse {}
+
+/// A user's unread counters as carried on `ChatMessage` and `UserReadChat`: the room the event is
+/// about, and on `UserReadChat` also the total across every joined room, so both badges can be set
+/// without a refetch. `ChatMessage` goes to every member of the room and leaves the total out;
+/// clients add one to theirs, or ask `GET /api/v1/rooms/unread`.
+///
+/// Only ever sent to the user the numbers belong to — see
+/// [`RoomNotifier::notify_users_with_unread`](crate::rooms::RoomNotifier::notify_users_with_unread).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCountResponse {
+    pub room: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl ApiResponse for UnreadCountResponse {}
+
+impl From<UnreadCountRow> for UnreadCountResponse {
+    fn from(row: UnreadCountRow) -> Self {
+        UnreadCountResponse {
+            room: row.room_unread,
+            total: None,
+        }
+    }
+}
+
+/// Body of `GET /api/v1/rooms/unread`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadTotalResponse {
+    /// Unread messages across every joined room.
+    pub total: i64,
+    /// How many of those rooms have at least one.
+    pub rooms: i64,
+}
+
+impl ApiResponse for UnreadTotalResponse {}
+
 /// A room plus its current participants. The room is flattened, so the payload is a
 /// [`RoomResponse`] with one extra `users` key.
 #[derive(Debug, Serialize)]
@@ -89,6 +168,9 @@ pub struct RoomMemberResponse {
     pub profile_picture: Option<String>,
     pub joined_at: Option<DateTime<Utc>>,
     pub last_message_read_at: Option<DateTime<Utc>>,
+    /// Omitted for former members, who appear as message authors only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<RoomRole>,
 }
 
 impl ApiResponse for RoomMemberResponse {}
@@ -101,6 +183,7 @@ impl From<RoomMemberRow> for RoomMemberResponse {
             profile_picture: row.profile_picture,
             joined_at: row.joined_at,
             last_message_read_at: row.last_message_read_at,
+            role: row.role,
         }
     }
 }
@@ -133,6 +216,13 @@ pub enum LastMessagePreviewResponse {
         sender_username: String,
         room_change_type: RoomChangeType,
     },
+    Poll {
+        sender_username: String,
+        question: String,
+    },
+    Deleted {
+        sender_username: String,
+    },
     New,
 }
 
@@ -157,6 +247,11 @@ impl From<LastMessagePreviewJson> for LastMessagePreviewResponse {
                 sender_username,
                 room_change_type,
             },
+            LastMessagePreviewJson::Poll { sender_username, question } => LastMessagePreviewResponse::Poll {
+                sender_username,
+                question: truncate_preview(&question),
+            },
+            LastMessagePreviewJson::Deleted { sender_username } => LastMessagePreviewResponse::Deleted { sender_username },
             LastMessagePreviewJson::New => LastMessagePreviewResponse::New,
         }
     }
@@ -172,6 +267,81 @@ pub struct RoomImageUploadResponse {
 
 impl ApiResponse for RoomImageUploadResponse {}
 
+/// An invitation waiting for its invitee's answer.
+///
+/// `Deserialize` under the notification exception in [`ApiResponse`]: this type is embedded in
+/// `RoomInvitationReceived`, which round-trips through the Redis replay stream.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomInvitationResponse {
+    pub room_id: Uuid,
+    pub room_name: Option<String>,
+    pub room_image_url: Option<String>,
+    pub invited_by: Uuid,
+    pub invited_by_name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiResponse for RoomInvitationResponse {}
+
+impl From<RoomInvitationRow> for RoomInvitationResponse {
+    fn from(row: RoomInvitationRow) -> Self {
+        RoomInvitationResponse {
+            room_id: row.room_id,
+            room_name: row.room_name,
+            room_image_url: row.room_image_url,
+            invited_by: row.invited_by,
+            invited_by_name: row.invited_by_name,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// An invite link as its room's owners and admins manage it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteLinkResponse {
+    pub token: String,
+    pub room_id: Uuid,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+}
+
+impl ApiResponse for InviteLinkResponse {}
+
+impl From<InviteLinkRow> for InviteLinkResponse {
+    fn from(row: InviteLinkRow) -> Self {
+        InviteLinkResponse {
+            token: row.token,
+            room_id: row.room_id,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            max_uses: row.max_uses,
+            use_count: row.use_count,
+        }
+    }
+}
+
+/// What someone holding an invite link sees before joining. Deliberately not a [`RoomResponse`]:
+/// that carries the latest message preview, which is not for people outside the room.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteLinkPreviewResponse {
+    pub room_id: Uuid,
+    pub room_name: Option<String>,
+    pub room_image_url: Option<String>,
+    pub room_topic: Option<String>,
+    pub member_count: usize,
+    /// Whether the caller is in the room already, in which case joining is refused.
+    pub is_member: bool,
+}
+
+impl ApiResponse for InviteLinkPreviewResponse {}
+
 /// A single suggestion of where the client can send shared content (like an Instagram "share to
 /// chat" sheet). Merges friends and group rooms into one list; `target` tells the client whether to
 /// post into an existing room or to create one first.
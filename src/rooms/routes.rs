@@ -1,11 +1,14 @@
 use crate::core::AppState;
 use crate::rooms::handler::{
-    handle_create_room, handle_get_joined_rooms, handle_get_read_states, handle_get_room_list_item_by_id, handle_get_room_with_details,
-    handle_get_share_targets, handle_get_users_in_room, handle_invite_to_room, handle_leave_room, handle_save_room_image, handle_scroll_chat_timeline,
-    handle_search_existing_single_room, mark_room_as_read,
+    handle_accept_invitation, handle_change_member_role, handle_create_invite_link, handle_create_room, handle_decline_invitation, handle_get_invitations,
+    handle_get_invite_links, handle_get_joined_rooms, handle_get_message_read_states, handle_get_read_states, handle_get_room_list_item_by_id,
+    handle_get_room_with_details, handle_get_share_targets, handle_get_unread_total, handle_get_users_in_room, handle_invite_to_room,
+    handle_join_by_invite_link, handle_kick_member, handle_leave_room, handle_preview_invite_link, handle_rename_room, handle_revoke_invite_link,
+    handle_save_room_image, handle_scroll_chat_timeline, handle_scroll_thread, handle_search_existing_single_room, handle_set_notification_settings,
+    handle_set_room_retention, handle_set_room_topic, mark_room_as_read,
 };
 use axum::Router;
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post, put};
 use std::sync::Arc;
 
 pub fn create_room_routes() -> Router<Arc<AppState>> {
@@ -14,13 +17,28 @@ pub fn create_room_routes() -> Router<Arc<AppState>> {
         .route("/rooms/{room_id}/users", get(handle_get_users_in_room))
         .route("/rooms/{room_id}/detailed", get(handle_get_room_with_details))
         .route("/rooms/{room_id}/timeline", get(handle_scroll_chat_timeline))
+        .route("/rooms/{room_id}/messages/{message_id}/thread", get(handle_scroll_thread))
         .route("/rooms/{room_id}", get(handle_get_room_list_item_by_id))
         .route("/rooms/{room_id}/leave", post(handle_leave_room))
         .route("/rooms/search", get(handle_search_existing_single_room))
         .route("/rooms/share-targets", get(handle_get_share_targets))
+        .route("/rooms/unread", get(handle_get_unread_total))
         .route("/rooms/{room_id}/invite/{user_id}", post(handle_invite_to_room))
+        .route("/rooms/{room_id}/members/{user_id}", delete(handle_kick_member))
+        .route("/rooms/{room_id}/members/{user_id}/role", put(handle_change_member_role))
         .route("/rooms/{room_id}/upload-img", post(handle_save_room_image))
+        .route("/rooms/{room_id}/name", put(handle_rename_room))
+        .route("/rooms/{room_id}/topic", put(handle_set_room_topic))
+        .route("/rooms/{room_id}/retention", put(handle_set_room_retention))
+        .route("/rooms/{room_id}/notification-settings", put(handle_set_notification_settings))
+        .route("/rooms/{room_id}/invite-links", post(handle_create_invite_link).get(handle_get_invite_links))
+        .route("/rooms/{room_id}/invite-links/{token}", delete(handle_revoke_invite_link))
+        .route("/rooms/invitations", get(handle_get_invitations))
+        .route("/rooms/invitations/{room_id}/accept", post(handle_accept_invitation))
+        .route("/rooms/invitations/{room_id}/decline", post(handle_decline_invitation))
+        .route("/rooms/join/{token}", get(handle_preview_invite_link).post(handle_join_by_invite_link))
         .route("/rooms", get(handle_get_joined_rooms))
         .route("/rooms/{room_id}/mark-read", post(mark_room_as_read))
         .route("/rooms/{room_id}/read-states", get(handle_get_read_states))
+        .route("/rooms/{room_id}/messages/{message_id}/read-states", get(handle_get_message_read_states))
 }
@@ -2,22 +2,28 @@ use crate::broadcast::NotificationEvent;
 use crate::broadcast::NotificationEvent::{LeaveRoom, RoomChangeEvent, UserReadChat};
 use crate::core::cursor::{CursorResults, next_cursor};
 use crate::core::errors::AppError;
-use crate::core::{Database, Service};
+use crate::core::{Database, PgTransaction, Service};
 use crate::messaging::ChatRepository;
 use crate::messaging::entity::{MessageBodyJson, MessageRow, RoomChangeJson};
 use crate::messaging::request::FirstMessageRequest;
 use crate::messaging::response::MessageResponse;
+use crate::notify_user;
 use crate::object_storage::ObjectStorage;
-use crate::rooms::entity::{ChatRoomRow, LastMessagePreviewJson, RoomMemberRow, RoomMemberSnapshotJson};
-use crate::rooms::model::{RoomChangeType, RoomPaginationCursor, RoomType};
-use crate::rooms::request::NewRoomRequest;
-use crate::rooms::response::{LastMessagePreviewResponse, RoomDetailResponse, RoomImageUploadResponse, RoomMemberResponse, RoomResponse};
+use crate::rooms::entity::{ChatRoomRow, InviteLinkRow, LastMessagePreviewJson, RoomMemberRow, RoomMemberSnapshotJson};
+use crate::rooms::model::{NotificationLevel, RetentionPolicy, RoomChangeType, RoomPaginationCursor, RoomRole, RoomType};
+use crate::rooms::request::{MarkReadQuery, NewRoomRequest};
+use crate::rooms::response::{
+    InviteLinkPreviewResponse, InviteLinkResponse, LastMessagePreviewResponse, RoomDetailResponse, RoomImageUploadResponse, RoomInvitationResponse,
+    RoomMemberResponse, RoomNotificationSettingsResponse, RoomResponse, UnreadCountResponse, UnreadTotalResponse,
+};
 use crate::rooms::{RoomNotifier, RoomRepository};
 use crate::users::UserRepository;
 use crate::users::response::UserProfileResponse;
 use crate::utils::crop_image_from_center;
-use crate::{notify_room, notify_user};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use std::collections::HashSet;
 use tracing::error;
 use uuid::Uuid;
@@ -40,6 +46,8 @@ pub struct RoomService {
     /// The bucket name, not the whole `ObjectStorageConfig` — a service takes the slice of
     /// configuration it uses.
     bucket: String,
+    /// For the unread counters, which leave out messages past their room's retention.
+    retention: RetentionPolicy,
 }
 
 impl Service for RoomService {
@@ -56,6 +64,7 @@ impl RoomService {
         notifier: RoomNotifier,
         storage: ObjectStorage,
         bucket: String,
+        retention: RetentionPolicy,
     ) -> Self {
         Self {
             db,
@@ -65,6 +74,7 @@ impl RoomService {
             notifier,
             storage,
             bucket,
+            retention,
         }
     }
 
@@ -100,7 +110,13 @@ impl RoomService {
     ) -> Result<CursorResults<RoomResponse>, AppError> {
         let mut rooms = self
             .rooms
-            .get_joined_rooms(&client_id, name_filter.as_deref(), cursor, (page_size + 1) as i64)
+            .get_joined_rooms(
+                &client_id,
+                name_filter.as_deref(),
+                cursor,
+                (page_size + 1) as i64,
+                self.retention.default_secs(),
+            )
             .await?;
 
         let next_cursor = next_cursor(&mut rooms, page_size, |room| RoomPaginationCursor {
@@ -118,7 +134,7 @@ impl RoomService {
     pub async fn get_room_with_details(&self, client_id: Uuid, room_id: Uuid) -> Result<RoomDetailResponse, AppError> {
         let (chat_room, users) = tokio::try_join!(
             //executing 2 queries async
-            self.rooms.find_specific_joined_room(&room_id, &client_id),
+            self.rooms.find_specific_joined_room(&room_id, &client_id, self.retention.default_secs()),
             self.rooms.select_all_room_member(&room_id)
         )?;
 
@@ -134,26 +150,103 @@ impl RoomService {
         }
     }
 
-    pub async fn mark_room_as_read(&self, client_id: Uuid, room_id: Uuid) -> Result<(), AppError> {
+    /// Moves the caller's read marker to the position in `position`: a message of this room, a
+    /// timestamp, or — with neither — now. Timestamps in the future are clamped to now, since they
+    /// would otherwise pre-read messages not yet sent.
+    ///
+    /// The marker never moves backwards. A report behind the stored position is accepted and
+    /// ignored, without an event: with several devices, the one that scrolled least is routinely
+    /// the last to report.
+    pub async fn mark_room_as_read(&self, client_id: Uuid, room_id: Uuid, position: MarkReadQuery) -> Result<(), AppError> {
         self.ensure_member(&client_id, &room_id).await?;
-        self.rooms.update_user_read_status(self.db.pool(), &room_id, &client_id).await?;
+        let now = Utc::now();
+        let read_at = match (position.message_id, position.read_at) {
+            (Some(message_id), _) => self.chats.fetch_message_by_id(&message_id, &room_id).await?.created_at,
+            (None, Some(read_at)) => read_at.min(now),
+            (None, None) => now,
+        };
+        if !self.rooms.advance_read_marker(self.db.pool(), &room_id, &client_id, read_at).await? {
+            return Ok(());
+        }
 
         let room = self.rooms.select_room(&room_id).await?;
         if room.latest_message.is_none() {
             return Ok(());
         }
 
-        notify_room!(self.notifier, &room_id, UserReadChat { user_id: client_id, room_id });
+        // The reader's own devices get their fresh counters; everyone else only learns how far the
+        // room was read.
+        let context = self.notifier.room_context(&room_id).await?;
+        let (total, _) = self.rooms.count_total_unread(&client_id, self.retention.default_secs()).await?;
+        let unread = self
+            .rooms
+            .select_room_unread_counts(&room_id, &[client_id], self.retention.default_secs())
+            .await?
+            .into_iter()
+            .map(|row| {
+                let counts = UnreadCountResponse {
+                    room: row.room_unread,
+                    total: Some(total),
+                };
+                (row.user_id, counts)
+            })
+            .collect();
+        self.notifier
+            .notify_users_with_unread(
+                context.member_ids(),
+                UserReadChat {
+                    user_id: client_id,
+                    room_id,
+                    read_at: Some(read_at),
+                    unread: None,
+                },
+                unread,
+            )
+            .await;
         Ok(())
     }
 
+    /// Sets how much of the room is pushed to the caller while offline. Only ever their own
+    /// settings; nobody else is told.
+    pub async fn set_notification_settings(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        level: NotificationLevel,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> Result<RoomNotificationSettingsResponse, AppError> {
+        if !self.rooms.update_notification_settings(&room_id, &client_id, level, muted_until).await? {
+            return Err(AppError::Forbidden("Invalid permissions to interact with this room".to_string()));
+        }
+        Ok(RoomNotificationSettingsResponse { level, muted_until })
+    }
+
+    /// Unread messages across all of the caller's rooms, for an app-icon or tab badge.
+    pub async fn get_unread_total(&self, client_id: Uuid) -> Result<UnreadTotalResponse, AppError> {
+        let (total, rooms) = self.rooms.count_total_unread(&client_id, self.retention.default_secs()).await?;
+        Ok(UnreadTotalResponse { total, rooms })
+    }
+
+    /// Members who have read the room's latest message.
     pub async fn get_read_states(&self, client_id: Uuid, room_id: Uuid) -> Result<Vec<RoomMemberResponse>, AppError> {
         self.ensure_member(&client_id, &room_id).await?;
-        let users = self.rooms.select_all_room_member(&room_id).await?;
         let room = self.rooms.select_room(&room_id).await?;
+        self.readers_at(&room_id, room.latest_message).await
+    }
+
+    /// Members who have read `message_id`, i.e. whose read marker is at or past it — the "seen by"
+    /// list of a single message.
+    pub async fn get_message_read_states(&self, client_id: Uuid, room_id: Uuid, message_id: Uuid) -> Result<Vec<RoomMemberResponse>, AppError> {
+        self.ensure_member(&client_id, &room_id).await?;
+        let message = self.chats.fetch_message_by_id(&message_id, &room_id).await?;
+        self.readers_at(&room_id, Some(message.created_at)).await
+    }
+
+    async fn readers_at(&self, room_id: &Uuid, position: Option<DateTime<Utc>>) -> Result<Vec<RoomMemberResponse>, AppError> {
+        let users = self.rooms.select_all_room_member(room_id).await?;
         let read_users = users
             .into_iter()
-            .filter(|user| user_has_read(user, room.latest_message))
+            .filter(|user| user_has_read(user, position))
             .map(RoomMemberResponse::from)
             .collect();
         Ok(read_users)
@@ -211,7 +304,13 @@ impl RoomService {
         let mut tx = self.db.begin().await?;
         let room_entity = self
             .rooms
-            .insert_room(&mut tx, new_room.room_type, new_room.room_name.as_deref(), &new_room.invited_users)
+            .insert_room(
+                &mut tx,
+                new_room.room_type,
+                new_room.room_name.as_deref(),
+                &new_room.invited_users,
+                (new_room.room_type == RoomType::Group).then_some(&client_id),
+            )
             .await?;
 
         let first_message = match &new_room.first_message {
@@ -240,8 +339,8 @@ impl RoomService {
             //sending 2 specific room views to the users, because private rooms are shown like another user
             let (room_client, room_receiver) = tokio::try_join!(
                 //executing 2 queries async
-                self.rooms.find_specific_joined_room(&room_entity.id, &client_id),
-                self.rooms.find_specific_joined_room(&room_entity.id, other_user)
+                self.rooms.find_specific_joined_room(&room_entity.id, &client_id, self.retention.default_secs()),
+                self.rooms.find_specific_joined_room(&room_entity.id, other_user, self.retention.default_secs())
             )?;
 
             if let (Some(creator_room), Some(participator_room)) = (room_client, room_receiver) {
@@ -288,7 +387,7 @@ impl RoomService {
     pub async fn get_room_list_item_by_id(&self, client_id: Uuid, room_id: Uuid) -> Result<RoomResponse, AppError> {
         let room = self
             .rooms
-            .find_specific_joined_room(&room_id, &client_id)
+            .find_specific_joined_room(&room_id, &client_id, self.retention.default_secs())
             .await?
             .ok_or_else(|| AppError::NotFound("Room not found.".to_string()))?;
         Ok(RoomResponse::from(room))
@@ -309,14 +408,16 @@ impl RoomService {
 
         if room.room_type == RoomType::Single {
             //if someone leaves a single room, the whole room is getting wiped!
-            self.leave_private_room(room, users).await
+            self.wipe_room(room, users).await
         } else {
             //handle the group leave logic
             self.leave_group_room(room, users, leaving_user).await
         }
     }
 
-    /// Adds a user to a group room and announces it.
+    /// Invites a user into a group. They are not a member until they accept — see
+    /// [`Self::accept_invitation`]; the room sees `UserInvited`, the invitee gets
+    /// `RoomInvitationReceived`.
     ///
     /// The block check moved here from the handler for the same reason as in [`Self::create_room`]:
     /// "a user who blocked you cannot be pulled into a room by you" is a property of inviting, not
@@ -327,32 +428,148 @@ impl RoomService {
             return Err(AppError::Forbidden("User is blocked.".to_string()));
         }
 
-        let (room, users, creator) = tokio::try_join!(
-            //executing 3 queries async
+        let (room, users, inviter_user, invitee) = tokio::try_join!(
+            //executing 4 queries async
             self.rooms.select_room(&room_id),
             self.rooms.select_all_room_member(&room_id),
-            self.users.find_user_by_id(&client_id)
+            self.users.find_user_by_id(&client_id),
+            self.users.find_user_by_id(&user_id)
         )?;
 
-        let creator_profile = UserProfileResponse::from(creator.ok_or_else(|| AppError::NotFound("UserID not found.".to_string()))?);
+        let inviter_user = inviter_user.ok_or_else(|| AppError::NotFound("UserID not found.".to_string()))?;
+        let invitee = invitee.ok_or_else(|| AppError::NotFound("UserID not found.".to_string()))?;
 
         if room.room_type == RoomType::Single {
             return Err(AppError::Validation("Private rooms doesn't allow invites!.".to_string()));
         };
 
-        //we have to check if the inviter is in the room and the invited user isn't!
-        users
+        //we have to check if the inviter may invite and the invited user isn't in the room!
+        let inviter = users
             .iter()
             .find(|user| user.id == client_id)
             .ok_or_else(|| AppError::Forbidden("Client is not in this room.".to_string()))?;
+        if !may_manage(inviter) {
+            return Err(AppError::Forbidden("Only owners and admins can invite to this room.".to_string()));
+        }
 
         let user_to_exclude = users.iter().find(|user| user.id == user_id);
         if user_to_exclude.is_some() {
             return Err(AppError::Validation("User is already in this room.".to_string()));
         }
 
-        //1. add him to the room
+        let now = Utc::now();
         let mut tx = self.db.begin().await?;
+        if !self.rooms.insert_invitation(&mut tx, &room_id, &user_id, &client_id, now).await? {
+            return Err(AppError::Validation("User is already invited to this room.".to_string()));
+        }
+        let preview_text = LastMessagePreviewJson::RoomChange {
+            sender_username: invitee.display_name.clone(),
+            room_change_type: RoomChangeType::INVITE,
+        };
+        self.rooms.update_last_room_message(&mut tx, &room_id, &preview_text).await?;
+        // Not a member yet, so there are no membership values to freeze into the snapshot.
+        let message = MessageRow::new(
+            room_id,
+            client_id,
+            MessageBodyJson::RoomChange(RoomChangeJson::UserInvited {
+                related_user: RoomMemberSnapshotJson {
+                    id: invitee.id,
+                    display_name: invitee.display_name.clone(),
+                    profile_picture: invitee.profile_picture.clone(),
+                    joined_at: None,
+                    last_message_read_at: None,
+                },
+            }),
+        );
+        self.chats.insert_message(&mut *tx, &message).await?;
+        tx.commit().await?;
+
+        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        self.notifier.notify_users(send_to, room_change_event(message, preview_text)).await;
+        notify_user!(
+            self.notifier,
+            &user_id,
+            NotificationEvent::RoomInvitationReceived {
+                invitation: RoomInvitationResponse {
+                    room_id,
+                    room_name: room.room_name,
+                    room_image_url: room.room_image_url,
+                    invited_by: client_id,
+                    invited_by_name: inviter_user.display_name,
+                    created_at: now,
+                },
+            }
+        );
+        Ok(())
+    }
+
+    /// The caller's pending invitations, newest first.
+    pub async fn get_invitations(&self, client_id: Uuid) -> Result<Vec<RoomInvitationResponse>, AppError> {
+        let invitations = self.rooms.select_invitations(&client_id).await?;
+        Ok(invitations.into_iter().map(RoomInvitationResponse::from).collect())
+    }
+
+    /// Accepts an invitation: the caller joins the room, announced as `UserJoined` to its members
+    /// and `NewRoom` to the caller, with the inviter as `created_by`.
+    pub async fn accept_invitation(&self, client_id: Uuid, room_id: Uuid) -> Result<RoomResponse, AppError> {
+        let mut tx = self.db.begin().await?;
+        let invited_by = self
+            .rooms
+            .take_invitation(&mut tx, &room_id, &client_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Invitation not found.".to_string()))?;
+        let users = self.rooms.select_all_room_member(&room_id).await?;
+        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        // A block made after the invitation was sent still keeps the two apart.
+        if !self.users.find_blocked_relationships(&client_id, &send_to).await?.is_empty() {
+            return Err(AppError::Forbidden("Unable to join this room.".to_string()));
+        }
+        let created_by = self.inviter_or_self(&invited_by, &client_id).await?;
+        self.admit_member(tx, room_id, client_id, send_to, created_by).await
+    }
+
+    /// Declines an invitation. Nobody is told; the `UserInvited` record stays in the timeline.
+    pub async fn decline_invitation(&self, client_id: Uuid, room_id: Uuid) -> Result<(), AppError> {
+        if !self.rooms.delete_invitation(&room_id, &client_id).await? {
+            return Err(AppError::NotFound("Invitation not found.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The profile a join is announced as coming from. An inviter may have left ISM since; the
+    /// joiner then stands in.
+    async fn inviter_or_self(&self, inviter_id: &Uuid, client_id: &Uuid) -> Result<UserProfileResponse, AppError> {
+        let user = match self.users.find_user_by_id(inviter_id).await? {
+            Some(inviter) => inviter,
+            None => self
+                .users
+                .find_user_by_id(client_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("UserID not found.".to_string()))?,
+        };
+        Ok(UserProfileResponse::from(user))
+    }
+
+    /// Adds `user_id` to the room inside `tx` — which may already hold the caller's own writes —
+    /// commits, and announces it: `UserJoined` to `audience`, the room's previous members, and
+    /// `NewRoom` to the new member. Returns the room as the new member sees it.
+    async fn admit_member(
+        &self,
+        mut tx: PgTransaction,
+        room_id: Uuid,
+        user_id: Uuid,
+        audience: Vec<Uuid>,
+        created_by: UserProfileResponse,
+    ) -> Result<RoomResponse, AppError> {
+        // Checked again under the room's lock: whatever the caller checked was read outside `tx`,
+        // where a concurrent join of the same user was not visible yet.
+        match self.rooms.lock_room_membership(&mut tx, &room_id, &user_id).await? {
+            Some(false) => {}
+            Some(true) => return Err(AppError::Validation("User is already in this room.".to_string())),
+            None => return Err(AppError::NotFound("Room not found.".to_string())),
+        }
+
+        //1. add him to the room
         let user = self.rooms.add_user_to_room(&mut tx, &user_id, &room_id).await?;
         let preview_text = LastMessagePreviewJson::RoomChange {
             sender_username: user.display_name.clone(),
@@ -369,32 +586,217 @@ impl RoomService {
             }),
         );
         self.chats.insert_message(&mut *tx, &message).await?;
-
-        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
         tx.commit().await?;
 
         // Membership changed, so the cached participant snapshot is stale — drop it before
         // anything reacts to the events below.
         self.notifier.invalidate(&room_id).await?;
-        self.notifier.notify_users(send_to, room_change_event(message, preview_text)).await;
+        self.notifier.notify_users(audience, room_change_event(message, preview_text)).await;
 
         //sending new room event to invited user
         let room_for_user = self
             .rooms
-            .find_specific_joined_room(&room_id, &user_id)
+            .find_specific_joined_room(&room_id, &user_id, self.retention.default_secs())
             .await?
             .ok_or_else(|| AppError::Processing("Unable to find room for the invited user.".to_string()))?;
+        let room = RoomResponse::from(room_for_user);
 
         notify_user!(
             self.notifier,
             &user.id,
             NotificationEvent::NewRoom {
-                room: RoomResponse::from(room_for_user),
-                created_by: creator_profile,
+                room: room.clone(),
+                created_by,
                 first_message: None,
             }
         );
 
+        Ok(room)
+    }
+
+    /// Creates a link anyone can join the group with. Owners and admins only, like inviting.
+    pub async fn create_invite_link(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        expires_at: Option<DateTime<Utc>>,
+        max_uses: Option<i32>,
+    ) -> Result<InviteLinkResponse, AppError> {
+        self.ensure_manager(&client_id, &room_id).await?;
+        let link = InviteLinkRow {
+            token: new_invite_token(),
+            room_id,
+            created_by: client_id,
+            created_at: Utc::now(),
+            expires_at,
+            max_uses,
+            use_count: 0,
+        };
+        self.rooms.insert_invite_link(&link).await?;
+        Ok(InviteLinkResponse::from(link))
+    }
+
+    pub async fn get_invite_links(&self, client_id: Uuid, room_id: Uuid) -> Result<Vec<InviteLinkResponse>, AppError> {
+        self.ensure_manager(&client_id, &room_id).await?;
+        let links = self.rooms.select_invite_links(&room_id).await?;
+        Ok(links.into_iter().map(InviteLinkResponse::from).collect())
+    }
+
+    pub async fn revoke_invite_link(&self, client_id: Uuid, room_id: Uuid, token: String) -> Result<(), AppError> {
+        self.ensure_manager(&client_id, &room_id).await?;
+        if !self.rooms.delete_invite_link(&room_id, &token).await? {
+            return Err(AppError::NotFound("Invite link not found.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// What the room behind a link looks like from outside. A spent, expired or revoked link is
+    /// indistinguishable from one that never existed.
+    pub async fn preview_invite_link(&self, client_id: Uuid, token: String) -> Result<InviteLinkPreviewResponse, AppError> {
+        let link = self
+            .rooms
+            .select_invite_link(&token)
+            .await?
+            .filter(|link| link_usable(link, Utc::now()))
+            .ok_or_else(invite_link_gone)?;
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&link.room_id),
+            self.rooms.select_all_room_member(&link.room_id)
+        )?;
+        Ok(InviteLinkPreviewResponse {
+            room_id: room.id,
+            room_name: room.room_name,
+            room_image_url: room.room_image_url,
+            room_topic: room.room_topic,
+            member_count: users.len(),
+            is_member: users.iter().any(|user| user.id == client_id),
+        })
+    }
+
+    /// Joins the room behind a link, using one of its uses. Announced exactly like an accepted
+    /// invitation, with the link's creator standing in for the inviter.
+    ///
+    /// Refused if the caller and anyone in the room have blocked each other: a link must not become
+    /// a way around a block.
+    pub async fn join_by_invite_link(&self, client_id: Uuid, token: String) -> Result<RoomResponse, AppError> {
+        let mut tx = self.db.begin().await?;
+        let link = self
+            .rooms
+            .lock_invite_link(&mut tx, &token)
+            .await?
+            .filter(|link| link_usable(link, Utc::now()))
+            .ok_or_else(invite_link_gone)?;
+
+        let users = self.rooms.select_all_room_member(&link.room_id).await?;
+        if users.iter().any(|user| user.id == client_id) {
+            return Err(AppError::Validation("User is already in this room.".to_string()));
+        }
+        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        if !self.users.find_blocked_relationships(&client_id, &send_to).await?.is_empty() {
+            return Err(AppError::Forbidden("Unable to join this room.".to_string()));
+        }
+
+        let created_by = self.inviter_or_self(&link.created_by, &client_id).await?;
+        self.rooms.record_invite_link_use(&mut tx, &link.token).await?;
+        // Joining by link answers any invitation to the same room.
+        self.rooms.take_invitation(&mut tx, &link.room_id, &client_id).await?;
+        self.admit_member(tx, link.room_id, client_id, send_to, created_by).await
+    }
+
+    /// Removes a member from a group on behalf of another member, who must outrank them: the owner
+    /// can remove anyone, an admin only plain members. The room sees `UserKicked`, the removed
+    /// member gets `LeaveRoom`.
+    pub async fn kick_member(&self, client_id: Uuid, room_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        if client_id == user_id {
+            return Err(AppError::Validation("Leave the room instead of removing yourself.".to_string()));
+        }
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&room_id),
+            self.rooms.select_all_room_member(&room_id)
+        )?;
+        if room.room_type == RoomType::Single {
+            return Err(AppError::Validation("Private rooms have no members to remove.".to_string()));
+        }
+        let (actor, target) = actor_and_target(&users, &client_id, &user_id)?;
+        if !may_manage(actor) || actor.role <= target.role {
+            return Err(AppError::Forbidden("Insufficient room role to remove this member.".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let preview_text = LastMessagePreviewJson::RoomChange {
+            sender_username: target.display_name.clone(),
+            room_change_type: RoomChangeType::KICK,
+        };
+        self.rooms.remove_user_from_room(&mut tx, &room_id, &user_id, &preview_text).await?;
+        let message = MessageRow::new(
+            room_id,
+            client_id,
+            MessageBodyJson::RoomChange(RoomChangeJson::UserKicked {
+                related_user: RoomMemberSnapshotJson::from(target.clone()),
+            }),
+        );
+        self.chats.insert_message(&mut *tx, &message).await?;
+        tx.commit().await?;
+
+        let send_to: Vec<Uuid> = users.iter().filter(|user| user.id != user_id).map(|user| user.id).collect();
+        self.notifier.invalidate(&room_id).await?;
+        self.notifier.notify_users(send_to, room_change_event(message, preview_text)).await;
+        notify_user!(self.notifier, &user_id, LeaveRoom { room_id });
+        Ok(())
+    }
+
+    /// Gives a group member a new role. Only the owner changes roles; making someone else `Owner`
+    /// hands the room over, and the former owner stays on as an admin.
+    pub async fn change_member_role(&self, client_id: Uuid, room_id: Uuid, user_id: Uuid, role: RoomRole) -> Result<(), AppError> {
+        if client_id == user_id {
+            return Err(AppError::Validation("The owner cannot change their own role.".to_string()));
+        }
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&room_id),
+            self.rooms.select_all_room_member(&room_id)
+        )?;
+        if room.room_type == RoomType::Single {
+            return Err(AppError::Validation("Private rooms have no roles.".to_string()));
+        }
+        let (actor, target) = actor_and_target(&users, &client_id, &user_id)?;
+        if actor.role != Some(RoomRole::Owner) {
+            return Err(AppError::Forbidden("Only the owner can change roles.".to_string()));
+        }
+        if target.role == Some(role) {
+            return Ok(());
+        }
+
+        let mut changes = vec![(target, role)];
+        if role == RoomRole::Owner {
+            // Demoted first: the one-owner index would reject the promotion otherwise.
+            changes.insert(0, (actor, RoomRole::Admin));
+        }
+        let preview_text = LastMessagePreviewJson::RoomChange {
+            sender_username: target.display_name.clone(),
+            room_change_type: RoomChangeType::ROLE,
+        };
+
+        let mut tx = self.db.begin().await?;
+        let mut messages = Vec::with_capacity(changes.len());
+        for (member, role) in changes {
+            self.rooms.update_member_role(&mut tx, &room_id, &member.id, role).await?;
+            let message = role_change_message(room_id, client_id, member, role);
+            self.chats.insert_message(&mut *tx, &message).await?;
+            messages.push(message);
+        }
+        self.rooms.update_last_room_message(&mut tx, &room_id, &preview_text).await?;
+        tx.commit().await?;
+
+        self.notifier.invalidate(&room_id).await?;
+        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        for message in messages {
+            self.notifier
+                .notify_users(send_to.clone(), room_change_event(message, preview_text.clone()))
+                .await;
+        }
         Ok(())
     }
 
@@ -404,7 +806,7 @@ impl RoomService {
     }
 
     pub async fn set_room_image(&self, client_id: Uuid, room_id: Uuid, image_data: Bytes) -> Result<RoomImageUploadResponse, AppError> {
-        self.ensure_member(&client_id, &room_id).await?;
+        let users = self.ensure_manager(&client_id, &room_id).await?;
 
         let img = crop_image_from_center(&image_data, 500, 500).map_err(|err| {
             error!(error = %err, "Unable to crop image");
@@ -412,11 +814,14 @@ impl RoomService {
         })?;
 
         let object_id = format!("{}/{}", self.bucket, room_id);
-        if let Err(err) = self.storage.insert_object(&room_id.to_string(), img).await {
+        if let Err(err) = self.storage.insert_object(&room_id.to_string(), img, "image/jpeg").await {
             error!(error = %err, "Image processing failed");
             return Err(AppError::S3("Unable save image in s3 bucket.".to_string()));
         };
-        self.rooms.update_room_img_url(&room_id, &object_id).await?;
+        let mut tx = self.db.begin().await?;
+        self.rooms.update_room_img_url(&mut tx, &room_id, &object_id).await?;
+        let change = RoomChangeJson::ImageChanged { image_url: object_id.clone() };
+        self.record_room_change(tx, client_id, room_id, &users, change, RoomChangeType::IMAGE).await?;
         let response = RoomImageUploadResponse {
             image_url: object_id.clone(),
             image_name: format!("{}.jpeg", object_id),
@@ -424,8 +829,119 @@ impl RoomService {
         Ok(response)
     }
 
-    /// A 1-1 room has no meaning once one side leaves, so it is deleted outright.
-    async fn leave_private_room(&self, room: ChatRoomRow, users: Vec<RoomMemberRow>) -> Result<(), AppError> {
+    /// Renames a group. Single rooms have no name of their own; they show the other participant's.
+    pub async fn rename_room(&self, client_id: Uuid, room_id: Uuid, room_name: String) -> Result<(), AppError> {
+        let users = self.ensure_manager(&client_id, &room_id).await?;
+        let mut tx = self.db.begin().await?;
+        self.rooms.update_room_name(&mut tx, &room_id, &room_name).await?;
+        let change = RoomChangeJson::RoomRenamed { room_name };
+        self.record_room_change(tx, client_id, room_id, &users, change, RoomChangeType::RENAME).await
+    }
+
+    /// Sets or, with `None`, clears a group's topic.
+    pub async fn set_room_topic(&self, client_id: Uuid, room_id: Uuid, topic: Option<String>) -> Result<(), AppError> {
+        let users = self.ensure_manager(&client_id, &room_id).await?;
+        let mut tx = self.db.begin().await?;
+        self.rooms.update_room_topic(&mut tx, &room_id, topic.as_deref()).await?;
+        let change = RoomChangeJson::TopicChanged { topic };
+        self.record_room_change(tx, client_id, room_id, &users, change, RoomChangeType::TOPIC).await
+    }
+
+    /// Sets how long the room keeps its messages. In a group that is for owners and admins; in a 1-1
+    /// room either participant may, as neither manages it.
+    pub async fn set_room_retention(&self, client_id: Uuid, room_id: Uuid, retention_secs: Option<i32>) -> Result<(), AppError> {
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&room_id),
+            self.rooms.select_all_room_member(&room_id)
+        )?;
+        let editor = users
+            .iter()
+            .find(|user| user.id == client_id)
+            .ok_or_else(|| AppError::Forbidden("Client is not in this room.".to_string()))?;
+        if room.room_type == RoomType::Group && !may_manage(editor) {
+            return Err(AppError::Forbidden("Only owners and admins can manage this room.".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        self.rooms.update_room_retention(&mut tx, &room_id, retention_secs).await?;
+        let change = RoomChangeJson::RetentionChanged { retention_secs };
+        self.record_room_change(tx, client_id, room_id, &users, change, RoomChangeType::RETENTION).await
+    }
+
+    /// Checks that the client may manage the room — edit its name, image and topic, hand out invite
+    /// links; only a group's owner and admins may — and returns its members.
+    async fn ensure_manager(&self, client_id: &Uuid, room_id: &Uuid) -> Result<Vec<RoomMemberRow>, AppError> {
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(room_id),
+            self.rooms.select_all_room_member(room_id)
+        )?;
+        let editor = users
+            .iter()
+            .find(|user| &user.id == client_id)
+            .ok_or_else(|| AppError::Forbidden("Client is not in this room.".to_string()))?;
+        if room.room_type == RoomType::Single {
+            return Err(AppError::Validation("Private rooms cannot be managed.".to_string()));
+        }
+        if !may_manage(editor) {
+            return Err(AppError::Forbidden("Only owners and admins can manage this room.".to_string()));
+        }
+        Ok(users)
+    }
+
+    /// Finishes a metadata change begun in `tx`: records it in the timeline and the room preview,
+    /// commits, and sends every member the `RoomChangeEvent` carrying the new value.
+    async fn record_room_change(
+        &self,
+        mut tx: PgTransaction,
+        client_id: Uuid,
+        room_id: Uuid,
+        users: &[RoomMemberRow],
+        change: RoomChangeJson,
+        room_change_type: RoomChangeType,
+    ) -> Result<(), AppError> {
+        let sender_username = users
+            .iter()
+            .find(|user| user.id == client_id)
+            .map(|user| user.display_name.clone())
+            .unwrap_or_default();
+        let preview_text = LastMessagePreviewJson::RoomChange {
+            sender_username,
+            room_change_type,
+        };
+        let message = MessageRow::new(room_id, client_id, MessageBodyJson::RoomChange(change));
+        self.chats.insert_message(&mut *tx, &message).await?;
+        self.rooms.update_last_room_message(&mut tx, &room_id, &preview_text).await?;
+        tx.commit().await?;
+
+        let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        self.notifier.notify_users(send_to, room_change_event(message, preview_text)).await;
+        Ok(())
+    }
+
+    /// Deletes a room with all of its messages, whoever is in it. A moderation action: the caller's
+    /// role is checked by the admin handler in front of this, not here. Every member is sent
+    /// `LeaveRoom`, exactly as when the last member of a room leaves.
+    pub async fn delete_room(&self, room_id: Uuid) -> Result<(), AppError> {
+        let (room, users) = tokio::try_join!(
+            //executing 2 queries async
+            self.rooms.select_room(&room_id),
+            self.rooms.select_all_room_member(&room_id)
+        )?;
+        let has_image = room.room_image_url.is_some();
+        self.wipe_room(room, users).await?;
+
+        // The room is gone either way; an orphaned image is only wasted storage.
+        if has_image && let Err(err) = self.storage.delete_object(&room_id.to_string()).await {
+            error!(%room_id, error = %err, "Unable to delete room image");
+        }
+        Ok(())
+    }
+
+    /// Deletes the room, its messages and their uploads, and tells `users` it is gone.
+    async fn wipe_room(&self, room: ChatRoomRow, users: Vec<RoomMemberRow>) -> Result<(), AppError> {
+        let media_keys = self.chats.select_room_media_keys(&room.id).await?;
         let mut tx = self.db.begin().await?;
         self.chats.delete_room_messages(&mut *tx, &room.id).await?;
         self.rooms.delete_room(&mut tx, &room.id).await?;
@@ -435,9 +951,20 @@ impl RoomService {
 
         let send_to: Vec<Uuid> = users.iter().map(|user| user.id).collect();
         self.notifier.notify_users(send_to, LeaveRoom { room_id: room.id }).await;
+        self.delete_room_media(&room.id, media_keys).await;
         Ok(())
     }
 
+    /// Deletes the uploads of a room that no longer exists. Best-effort: their rows went with the
+    /// room, so nobody can reach the bytes any more, and an orphaned object is only wasted storage.
+    async fn delete_room_media(&self, room_id: &Uuid, media_keys: Vec<String>) {
+        for key in media_keys {
+            if let Err(err) = self.storage.delete_object(&key).await {
+                error!(%room_id, object_key = %key, error = %err, "Unable to delete room media");
+            }
+        }
+    }
+
     async fn leave_group_room(&self, room: ChatRoomRow, users: Vec<RoomMemberRow>, leaving_user: RoomMemberRow) -> Result<(), AppError> {
         let mut tx = self.db.begin().await?;
 
@@ -449,6 +976,7 @@ impl RoomService {
 
         if users.len() == 1 {
             //last user, delete this room now
+            let media_keys = self.chats.select_room_media_keys(&room.id).await?;
             self.chats.delete_room_messages(&mut *tx, &room.id).await?;
             self.rooms.delete_room(&mut tx, &room.id).await?;
             tx.commit().await?;
@@ -463,6 +991,7 @@ impl RoomService {
                     .await
                     .map_err(|_| AppError::Processing("Unable to delete image from room".to_string()))?;
             }
+            self.delete_room_media(&room.id, media_keys).await;
 
             Ok(())
         } else {
@@ -475,12 +1004,29 @@ impl RoomService {
                 }),
             );
             self.chats.insert_message(&mut *tx, &message).await?;
+
+            // A group is never left without an owner: the role passes on with the leaving one.
+            let mut handover = None;
+            if leaving_user.role == Some(RoomRole::Owner)
+                && let Some(successor_id) = self.rooms.select_successor(&mut tx, &room.id).await?
+                && let Some(successor) = users.iter().find(|user| user.id == successor_id)
+            {
+                self.rooms.update_member_role(&mut tx, &room.id, &successor_id, RoomRole::Owner).await?;
+                let transfer = role_change_message(room.id, leaving_user.id, successor, RoomRole::Owner);
+                self.chats.insert_message(&mut *tx, &transfer).await?;
+                handover = Some(transfer);
+            }
             tx.commit().await?;
 
             let send_to: Vec<Uuid> = users.iter().filter(|user| user.id != leaving_user.id).map(|user| user.id).collect();
 
             self.notifier.invalidate(&room.id).await?;
-            self.notifier.notify_users(send_to, room_change_event(message, preview_message)).await;
+            self.notifier
+                .notify_users(send_to.clone(), room_change_event(message, preview_message.clone()))
+                .await;
+            if let Some(transfer) = handover {
+                self.notifier.notify_users(send_to, room_change_event(transfer, preview_message)).await;
+            }
 
             //send ack to the leaving user
             notify_user!(self.notifier, &leaving_user.id, LeaveRoom { room_id: room.id });
@@ -506,6 +1052,54 @@ fn first_message_preview_text(body: &FirstMessageRequest, sender_username: Strin
     }
 }
 
+/// Whether a member may manage the room: invite, and remove members ranked below them.
+fn may_manage(member: &RoomMemberRow) -> bool {
+    member.role.is_some_and(|role| role >= RoomRole::Admin)
+}
+
+/// Whether a link can still be joined with at `now`.
+fn link_usable(link: &InviteLinkRow, now: DateTime<Utc>) -> bool {
+    link.expires_at.is_none_or(|expires_at| expires_at > now) && link.max_uses.is_none_or(|max_uses| link.use_count < max_uses)
+}
+
+fn invite_link_gone() -> AppError {
+    AppError::NotFound("Invite link not found or expired.".to_string())
+}
+
+/// 32 random bytes from two v4 UUIDs, URL-safe: the token is the whole secret behind a link.
+fn new_invite_token() -> String {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(Uuid::new_v4().as_bytes());
+    bytes[16..].copy_from_slice(Uuid::new_v4().as_bytes());
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Finds the member acting and the member acted on. A caller outside the room is refused outright,
+/// a target outside it simply does not exist.
+fn actor_and_target<'a>(users: &'a [RoomMemberRow], client_id: &Uuid, user_id: &Uuid) -> Result<(&'a RoomMemberRow, &'a RoomMemberRow), AppError> {
+    let actor = users
+        .iter()
+        .find(|user| &user.id == client_id)
+        .ok_or_else(|| AppError::Forbidden("Client is not in this room.".to_string()))?;
+    let target = users
+        .iter()
+        .find(|user| &user.id == user_id)
+        .ok_or_else(|| AppError::NotFound("User is not in this room.".to_string()))?;
+    Ok((actor, target))
+}
+
+/// The timeline record of `member` getting `role`, sent by whoever caused it.
+fn role_change_message(room_id: Uuid, sender_id: Uuid, member: &RoomMemberRow, role: RoomRole) -> MessageRow {
+    MessageRow::new(
+        room_id,
+        sender_id,
+        MessageBodyJson::RoomChange(RoomChangeJson::UserRoleChanged {
+            related_user: RoomMemberSnapshotJson::from(member.clone()),
+            role,
+        }),
+    )
+}
+
 /// Wraps a persisted room-change message in the event clients render it with.
 fn room_change_event(message: MessageRow, preview_text: LastMessagePreviewJson) -> NotificationEvent {
     RoomChangeEvent {
@@ -514,10 +1108,10 @@ fn room_change_event(message: MessageRow, preview_text: LastMessagePreviewJson)
     }
 }
 
-// Helper used by `get_read_states` — extracted for easier unit testing of the read logic.
-fn user_has_read(user: &RoomMemberRow, room_latest: Option<chrono::DateTime<chrono::Utc>>) -> bool {
-    match (room_latest, user.last_message_read_at) {
-        (Some(latest_msg_time), Some(read_time)) => read_time >= latest_msg_time,
+// Helper used by `get_read_states` and `get_message_read_states` — extracted for easier unit testing of the read logic.
+fn user_has_read(user: &RoomMemberRow, position: Option<DateTime<Utc>>) -> bool {
+    match (position, user.last_message_read_at) {
+        (Some(position), Some(read_time)) => read_time >= position,
         (Some(_), None) => false,
         (None, _) => true,
     }
@@ -537,6 +1131,7 @@ mod tests {
             profile_picture: None,
             joined_at: Some(Utc::now()),
             last_message_read_at: read_at,
+            role: Some(RoomRole::Member),
         }
     }
 
@@ -569,4 +1164,52 @@ mod tests {
         let user = make_member(None);
         assert!(!user_has_read(&user, Some(latest)));
     }
+
+    #[test]
+    fn only_owners_and_admins_manage_a_room() {
+        let with_role = |role| RoomMemberRow { role, ..make_member(None) };
+        assert!(may_manage(&with_role(Some(RoomRole::Owner))));
+        assert!(may_manage(&with_role(Some(RoomRole::Admin))));
+        assert!(!may_manage(&with_role(Some(RoomRole::Member))));
+        assert!(!may_manage(&with_role(None)));
+    }
+
+    fn make_link(expires_at: Option<DateTime<Utc>>, max_uses: Option<i32>, use_count: i32) -> InviteLinkRow {
+        InviteLinkRow {
+            token: new_invite_token(),
+            room_id: Uuid::new_v4(),
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            expires_at,
+            max_uses,
+            use_count,
+        }
+    }
+
+    #[test]
+    fn invite_link_without_limits_stays_usable() {
+        assert!(link_usable(&make_link(None, None, 10_000), Utc::now()));
+    }
+
+    #[test]
+    fn invite_link_is_spent_at_max_uses() {
+        let now = Utc::now();
+        assert!(link_usable(&make_link(None, Some(3), 2), now));
+        assert!(!link_usable(&make_link(None, Some(3), 3), now));
+    }
+
+    #[test]
+    fn invite_link_expires() {
+        let now = Utc::now();
+        assert!(link_usable(&make_link(Some(now + Duration::minutes(1)), None, 0), now));
+        assert!(!link_usable(&make_link(Some(now), None, 0), now));
+    }
+
+    #[test]
+    fn invite_tokens_are_url_safe_and_unique() {
+        let (a, b) = (new_invite_token(), new_invite_token());
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 43);
+        assert!(a.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
 }
@@ -1,11 +1,14 @@
 use crate::core::Service;
+use crate::core::cursor::{encode_cursor, next_cursor};
 use crate::core::errors::{AppError, AppResponse};
 use crate::messaging::ChatRepository;
-use crate::messaging::entity::MessageBodyJson;
-use crate::messaging::response::{MessageResponse, TimelinePageResponse};
+use crate::messaging::entity::{MessageBodyJson, MessageRow, PollVoteCountRow};
+use crate::messaging::response::{MessageResponse, PollResultsResponse, ReactionResponse, ThreadPageResponse, TimelinePageResponse};
 use crate::rooms::RoomRepository;
+use crate::rooms::model::{RetentionPolicy, ThreadCursor, TimelineAnchor, TimelineCursor};
 use crate::rooms::response::RoomMemberResponse;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Reads a room's message history.
@@ -13,6 +16,7 @@ use uuid::Uuid;
 pub struct TimelineService {
     rooms: RoomRepository,
     chats: ChatRepository,
+    retention: RetentionPolicy,
 }
 
 impl Service for TimelineService {
@@ -20,21 +24,174 @@ impl Service for TimelineService {
 }
 
 impl TimelineService {
-    pub fn new(rooms: RoomRepository, chats: ChatRepository) -> Self {
-        Self { rooms, chats }
+    pub fn new(rooms: RoomRepository, chats: ChatRepository, retention: RetentionPolicy) -> Self {
+        Self { rooms, chats, retention }
     }
 
-    /// One page of a room's timeline, newest first.
+    /// One page of a room's timeline, newest first, with the cursors to continue it in either
+    /// direction. A cursor is only handed out where there is more to load, so a client paging
+    /// `after` knows it has caught up once `after_cursor` comes back empty.
     ///
     /// Membership is checked here rather than in the handler: whether the caller may read this
-    /// room is a question only the database can answer, which makes it service business.
-    pub async fn scroll_chat_timeline(&self, client_id: Uuid, room_id: Uuid, timestamp: DateTime<Utc>) -> AppResponse<TimelinePageResponse> {
-        if !self.rooms.is_user_in_room(&client_id, &room_id).await? {
-            return Err(AppError::Forbidden("User is not a member of this room.".to_string()));
+    /// room is a question only the database can answer, which makes it service business. Thread
+    /// replies are not part of the main timeline; their roots carry the counters instead. Messages
+    /// past the room's retention are never returned, whether or not the reaper got to them yet.
+    pub async fn scroll_chat_timeline(&self, client_id: Uuid, room_id: Uuid, anchor: TimelineAnchor, page_size: usize) -> AppResponse<TimelinePageResponse> {
+        if let TimelineAnchor::Before(cursor) | TimelineAnchor::After(cursor) = anchor
+            && !cursor.is_well_formed()
+        {
+            return Err(AppError::Validation("Invalid Cursor-Parameters.".to_string()));
         }
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let expired_until = self.expired_until(&room_id).await?;
+        let limit = (page_size + 1) as i64;
+        // Each arm yields the page, newest first, whether anything older and anything newer is
+        // left, and the position to resume from when the page came back empty.
+        let (entities, has_older, has_newer, fallback) = match anchor {
+            TimelineAnchor::Before(cursor) => {
+                let mut older = self
+                    .chats
+                    .fetch_messages(&room_id, cursor.last_created_at, cursor.last_message_id, expired_until, limit)
+                    .await?;
+                let has_older = older.len() > page_size;
+                older.truncate(page_size);
+                // The cursor came from a newer page, so there is one to go back to.
+                (older, has_older, cursor.last_created_at.is_some(), cursor)
+            }
+            TimelineAnchor::After(cursor) => {
+                let mut newer = self
+                    .chats
+                    .fetch_messages_after(&room_id, cursor.last_created_at, cursor.last_message_id, expired_until, limit)
+                    .await?;
+                let has_newer = newer.len() > page_size;
+                newer.truncate(page_size);
+                newer.reverse();
+                (newer, cursor.last_created_at.is_some(), has_newer, cursor)
+            }
+            TimelineAnchor::Around(message_id) => {
+                let anchor = self.chats.fetch_message_by_id(&message_id, &room_id).await?;
+                if expired_until.is_some_and(|until| anchor.created_at <= until) {
+                    return Err(AppError::NotFound("Message has expired.".to_string()));
+                }
+                if anchor.thread_root_id.is_some() {
+                    return Err(AppError::Validation("Message is a thread reply; load its thread instead.".to_string()));
+                }
+
+                // The anchor takes one slot; newer messages get the smaller half of the rest.
+                let newer_size = (page_size - 1) / 2;
+                let older_size = page_size - 1 - newer_size;
+                let position = TimelineCursor::at(anchor.created_at, anchor.message_id);
+                let (mut newer, mut older) = tokio::try_join!(
+                    self.chats.fetch_messages_after(
+                        &room_id,
+                        position.last_created_at,
+                        position.last_message_id,
+                        expired_until,
+                        (newer_size + 1) as i64
+                    ),
+                    self.chats.fetch_messages(
+                        &room_id,
+                        position.last_created_at,
+                        position.last_message_id,
+                        expired_until,
+                        (older_size + 1) as i64
+                    )
+                )?;
+                let (has_newer, has_older) = (newer.len() > newer_size, older.len() > older_size);
+                newer.truncate(newer_size);
+                older.truncate(older_size);
+                newer.reverse();
+                newer.push(anchor);
+                newer.append(&mut older);
+                (newer, has_older, has_newer, position)
+            }
+        };
+
+        let position = |message: Option<&MessageRow>| {
+            let cursor = message.map_or(fallback, |message| TimelineCursor::at(message.created_at, message.message_id));
+            encode_cursor(&cursor).map_err(|e| AppError::Processing(format!("Cursor encoding failed: {}", e)))
+        };
+        let before_cursor = if has_older { Some(position(entities.last())?) } else { None };
+        let after_cursor = if has_newer { Some(position(entities.first())?) } else { None };
+
+        let (messages, senders) = self.render_page(client_id, room_id, entities).await?;
+        Ok(TimelinePageResponse {
+            messages,
+            senders,
+            before_cursor,
+            after_cursor,
+        })
+    }
+
+    /// One page of the thread under `root_id`, oldest reply first.
+    pub async fn scroll_thread(
+        &self,
+        client_id: Uuid,
+        room_id: Uuid,
+        root_id: Uuid,
+        cursor: ThreadCursor,
+        page_size: usize,
+    ) -> AppResponse<ThreadPageResponse> {
+        if !cursor.is_well_formed() {
+            return Err(AppError::Validation("Invalid Cursor-Parameters.".to_string()));
+        }
+        self.ensure_member(&client_id, &room_id).await?;
+
+        let expired_until = self.expired_until(&room_id).await?;
+        let root = self.chats.fetch_message_by_id(&root_id, &room_id).await?;
+        if expired_until.is_some_and(|until| root.created_at <= until) {
+            return Err(AppError::NotFound("Message has expired.".to_string()));
+        }
+        if root.thread_root_id.is_some() {
+            return Err(AppError::Validation("Message is a thread reply, not a thread root.".to_string()));
+        }
+
+        let mut entities = self
+            .chats
+            .fetch_thread(
+                &room_id,
+                &root_id,
+                cursor.last_created_at,
+                cursor.last_message_id,
+                expired_until,
+                (page_size + 1) as i64,
+            )
+            .await?;
+        let cursor = next_cursor(&mut entities, page_size, |last| ThreadCursor {
+            last_created_at: Some(last.created_at),
+            last_message_id: Some(last.message_id),
+        })
+        .map_err(|e| AppError::Processing(format!("Cursor encoding failed: {}", e)))?;
 
-        let entities = self.chats.fetch_messages(room_id, timestamp).await?;
+        entities.insert(0, root);
+        let (mut messages, senders) = self.render_page(client_id, room_id, entities).await?;
+        let root = messages.remove(0);
 
+        Ok(ThreadPageResponse {
+            root,
+            cursor,
+            messages,
+            senders,
+        })
+    }
+
+    /// Where the room's visible history starts, as of now.
+    async fn expired_until(&self, room_id: &Uuid) -> AppResponse<Option<DateTime<Utc>>> {
+        let retention_secs = self.rooms.select_room_retention(room_id).await?;
+        Ok(self.retention.cutoff(retention_secs, Utc::now()))
+    }
+
+    async fn ensure_member(&self, client_id: &Uuid, room_id: &Uuid) -> AppResponse<()> {
+        if !self.rooms.is_user_in_room(client_id, room_id).await? {
+            return Err(AppError::Forbidden("User is not a member of this room.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Turns a page of rows into responses with their reactions and poll results attached, plus
+    /// the senders the client needs to render them.
+    async fn render_page(&self, client_id: Uuid, room_id: Uuid, entities: Vec<MessageRow>) -> AppResponse<(Vec<MessageResponse>, Vec<RoomMemberResponse>)> {
         // Collect the distinct authors of this page so the client can render every
         // message without a separate lookup — including authors that have since left.
         // Reply messages reference the original author (`reply_sender_id`), who may be
@@ -50,11 +207,42 @@ impl TimelineService {
         sender_ids.dedup();
 
         let senders = self.rooms.select_message_senders(&room_id, &sender_ids).await?;
-        let messages = entities.into_iter().map(MessageResponse::from).collect();
 
-        Ok(TimelinePageResponse {
-            messages,
-            senders: senders.into_iter().map(RoomMemberResponse::from).collect(),
-        })
+        let message_ids: Vec<Uuid> = entities.iter().map(|message| message.message_id).collect();
+        let mut reactions: HashMap<Uuid, Vec<ReactionResponse>> = HashMap::new();
+        for row in self.chats.fetch_reaction_counts(&message_ids, &client_id).await? {
+            reactions.entry(row.message_id).or_default().push(ReactionResponse::from(row));
+        }
+
+        let poll_ids: Vec<Uuid> = entities
+            .iter()
+            .filter(|message| matches!(message.msg_body.0, MessageBodyJson::Poll(_)))
+            .map(|message| message.message_id)
+            .collect();
+        let mut votes: HashMap<Uuid, Vec<PollVoteCountRow>> = HashMap::new();
+        if !poll_ids.is_empty() {
+            for row in self.chats.fetch_poll_vote_counts(&poll_ids, &client_id).await? {
+                votes.entry(row.message_id).or_default().push(row);
+            }
+        }
+
+        let messages = entities
+            .into_iter()
+            .map(|entity| {
+                let poll_results = match &entity.msg_body.0 {
+                    MessageBodyJson::Poll(poll) => Some(PollResultsResponse::tally(
+                        poll.options.len(),
+                        votes.remove(&entity.message_id).unwrap_or_default(),
+                    )),
+                    _ => None,
+                };
+                let mut message = MessageResponse::from(entity);
+                message.reactions = reactions.remove(&message.message_id).unwrap_or_default();
+                message.poll_results = poll_results;
+                message
+            })
+            .collect();
+
+        Ok((messages, senders.into_iter().map(RoomMemberResponse::from).collect()))
     }
 }
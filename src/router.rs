@@ -4,6 +4,7 @@
 //! handling, authentication, body limits — lives in [`crate::middleware`], including the order it
 //! runs in.
 
+use crate::admin::routes::create_admin_routes;
 use crate::core::AppState;
 use crate::messaging::routes::create_messaging_routes;
 use crate::middleware;
@@ -29,7 +30,8 @@ pub async fn init_router(app_state: AppState) -> Router {
         Router::new()
             .merge(create_room_routes())
             .merge(create_user_routes())
-            .merge(create_messaging_routes()),
+            .merge(create_messaging_routes())
+            .merge(create_admin_routes()),
     );
 
     // Borrowing the config has to finish before the state is moved into the `Arc`.
@@ -33,7 +33,8 @@ pub struct UserRow {
     /// query; never rendered to another user.
     pub email: String,
     pub created_at: DateTime<Utc>,
-    /// Soft-delete marker, set by the platform rather than by ISM.
+    /// Soft-delete marker, set by the platform — or by an ISM admin banning the user, see
+    /// [`AdminService`](crate::admin::AdminService).
     ///
     /// Every query that *offers* a user — search, friends, friend requests, share targets — filters
     /// on `deleted_at IS NULL`. Room membership and message authorship deliberately do not: see
@@ -143,6 +144,15 @@ where
     }
 }
 
+/// `app_user.last_seen_at` for one user.
+#[derive(Debug, Clone, FromRow)]
+pub struct LastSeenRow {
+    pub id: Uuid,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+impl DbRow for LastSeenRow {}
+
 #[cfg(test)]
 mod convention_guards {
     //! Rust has no negative trait bounds, so "this type must not implement `Serialize`" cannot be
@@ -156,4 +166,5 @@ mod convention_guards {
     const _: () = assert!(!impls!(UserRow: Serialize));
     const _: () = assert!(!impls!(UserRelationshipRow: Serialize));
     const _: () = assert!(!impls!(UserWithRelationshipRow: Serialize));
+    const _: () = assert!(!impls!(LastSeenRow: Serialize));
 }
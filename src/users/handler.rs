@@ -1,11 +1,11 @@
 use crate::auth::CurrentUser;
-use crate::core::ValidatedQuery;
 use crate::core::cursor::{CursorResults, decode_cursor};
 use crate::core::errors::{AppError, AppResponse};
-use crate::users::UserService;
+use crate::core::{ValidatedJson, ValidatedQuery};
 use crate::users::model::UserPaginationCursor;
-use crate::users::request::{FriendListQuery, UserSearchQuery};
-use crate::users::response::{RelationshipStateResponse, UserProfileResponse, UserWithRelationshipResponse};
+use crate::users::request::{FriendListQuery, PresenceQueryRequest, SetPresenceRequest, UserSearchQuery};
+use crate::users::response::{PresenceResponse, RelationshipStateResponse, UserProfileResponse, UserWithRelationshipResponse};
+use crate::users::{PresenceService, UserService};
 use axum::Json;
 use axum::extract::{Path, State};
 use uuid::Uuid;
@@ -105,3 +105,21 @@ pub async fn handle_undo_ignore_user(
     let response = RelationshipStateResponse { state: updated_state };
     Ok(Json(response))
 }
+
+pub async fn handle_get_presence(
+    State(presence): State<PresenceService>,
+    user: CurrentUser,
+    ValidatedJson(payload): ValidatedJson<PresenceQueryRequest>,
+) -> AppResponse<Json<Vec<PresenceResponse>>> {
+    let response = presence.get_presence(&user.subject, payload.user_ids).await?;
+    Ok(Json(response))
+}
+
+pub async fn handle_set_presence(
+    State(presence): State<PresenceService>,
+    user: CurrentUser,
+    ValidatedJson(payload): ValidatedJson<SetPresenceRequest>,
+) -> AppResponse<()> {
+    presence.set_away(user.subject, payload.away).await?;
+    Ok(())
+}
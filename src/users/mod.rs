@@ -8,4 +8,4 @@ pub mod routes;
 pub mod service;
 
 pub use repository::UserRepository;
-pub use service::UserService;
+pub use service::{PresenceService, UserService};
@@ -73,6 +73,19 @@ impl Display for RelationshipState {
     }
 }
 
+/// Whether a user is reachable right now.
+///
+/// Derived, never stored: `Online` and `Away` both mean at least one live SSE/WebSocket
+/// connection, `Away` being what the client reported while idle or backgrounded. `Offline` means
+/// no live connection on any instance.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Offline,
+}
+
 /// Keyset cursor for every user list: search, friends and friend requests.
 ///
 /// Ordered by `(display_name, id)` ascending, with `id` as the deterministic tie-breaker for
@@ -1,6 +1,7 @@
 use crate::core::{Database, Repository};
-use crate::users::entity::{UserRelationshipRow, UserRow, UserWithRelationshipRow};
+use crate::users::entity::{LastSeenRow, UserRelationshipRow, UserRow, UserWithRelationshipRow};
 use crate::users::model::{RelationshipState, UserPaginationCursor};
+use chrono::{DateTime, Utc};
 use sqlx::{Error, PgConnection, query_as};
 use uuid::Uuid;
 
@@ -349,4 +350,92 @@ impl UserRepository {
         let blocked_users: Vec<Uuid> = blocked_users_optional.into_iter().flatten().collect();
         Ok(blocked_users)
     }
+
+    /// Whether the two users are friends. Blocking replaces the friendship state, so friends are
+    /// never blocked.
+    pub async fn are_friends(&self, user_id: &Uuid, other_id: &Uuid) -> Result<bool, Error> {
+        let friends = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM user_relationship
+                WHERE ((user_a_id = $1 AND user_b_id = $2) OR (user_a_id = $2 AND user_b_id = $1))
+                  AND state = 'FRIEND'
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(other_id)
+        .fetch_one(self.db.pool())
+        .await?;
+        Ok(friends)
+    }
+
+    /// Stamps the moment a user was last connected.
+    pub async fn touch_last_seen(&self, user_id: &Uuid, at: DateTime<Utc>) -> Result<(), Error> {
+        sqlx::query("UPDATE app_user SET last_seen_at = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(at)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// `last_seen_at` for every id in `user_ids` that exists. Ids without a row are simply absent.
+    pub async fn select_last_seen(&self, user_ids: &[Uuid]) -> Result<Vec<LastSeenRow>, Error> {
+        let rows = query_as::<_, LastSeenRow>("SELECT id, last_seen_at FROM app_user WHERE id = ANY($1)")
+            .bind(user_ids)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(rows)
+    }
+
+    /// Everyone who gets to hear about `user_id`'s presence: their friends and everyone they share
+    /// a room with, minus anyone on either side of a block.
+    pub async fn select_presence_audience(&self, user_id: &Uuid) -> Result<Vec<Uuid>, Error> {
+        let audience = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT audience.user_id FROM (
+                SELECT CASE WHEN rl.user_a_id = $1 THEN rl.user_b_id ELSE rl.user_a_id END AS user_id
+                FROM user_relationship rl
+                WHERE (rl.user_a_id = $1 OR rl.user_b_id = $1) AND rl.state = 'FRIEND'
+                UNION
+                SELECT other.user_id
+                FROM chat_room_participant own
+                JOIN chat_room_participant other ON other.room_id = own.room_id
+                WHERE own.user_id = $1 AND other.user_id <> $1
+            ) audience
+            WHERE NOT EXISTS (
+                SELECT 1 FROM user_relationship blocked
+                WHERE blocked.state IN ('A_BLOCKED', 'B_BLOCKED', 'ALL_BLOCKED')
+                  AND ((blocked.user_a_id = $1 AND blocked.user_b_id = audience.user_id)
+                    OR (blocked.user_b_id = $1 AND blocked.user_a_id = audience.user_id))
+            )
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(audience)
+    }
+
+    /// Soft-deletes a user, which is how ISM bans one: from here on every query that offers users
+    /// leaves them out. `false` if the user was already deleted or does not exist.
+    pub async fn soft_delete_user(&self, user_id: &Uuid, at: DateTime<Utc>) -> Result<bool, Error> {
+        let result = sqlx::query("UPDATE app_user SET deleted_at = $2, last_modified_at = $2 WHERE id = $1 AND deleted_at IS NULL")
+            .bind(user_id)
+            .bind(at)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Reverts [`Self::soft_delete_user`]. `false` if the user was not deleted or does not exist.
+    pub async fn restore_user(&self, user_id: &Uuid, at: DateTime<Utc>) -> Result<bool, Error> {
+        let result = sqlx::query("UPDATE app_user SET deleted_at = NULL, last_modified_at = $2 WHERE id = $1 AND deleted_at IS NOT NULL")
+            .bind(user_id)
+            .bind(at)
+            .execute(self.db.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
 }
@@ -1,13 +1,15 @@
 //! Client-supplied inputs for the users domain.
 //!
-//! Both types are extracted with [`ValidatedQuery`](crate::core::ValidatedQuery), so the bounds
-//! below run before a handler body starts. `limit` needs no bound of its own: [`PageSize`] clamps
+//! The query types are extracted with [`ValidatedQuery`](crate::core::ValidatedQuery) and the
+//! bodies with [`ValidatedJson`](crate::core::ValidatedJson), so the bounds below run before a
+//! handler body starts. `limit` needs no bound of its own: [`PageSize`] clamps
 //! during deserialization, so an out-of-range value is capped at `MAX_PAGE_SIZE` rather than
 //! rejected — asking for more than the server serves is not a malformed request.
 
 use crate::core::ApiRequest;
 use crate::core::cursor::PageSize;
 use serde::Deserialize;
+use uuid::Uuid;
 use validator::Validate;
 
 /// Query params for `GET /api/v1/users/search`.
@@ -40,3 +42,22 @@ pub struct FriendListQuery {
 }
 
 impl ApiRequest for FriendListQuery {}
+
+/// Body of `POST /api/v1/users/presence`: whose presence the caller wants to know. A body rather
+/// than a query string because a contact list easily outgrows a URL.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceQueryRequest {
+    #[validate(length(min = 1, max = 100, message = "must contain between 1 and 100 users."))]
+    pub user_ids: Vec<Uuid>,
+}
+
+impl ApiRequest for PresenceQueryRequest {}
+
+/// Body of `PUT /api/v1/users/presence`: the client reports itself idle (`away: true`) or back.
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetPresenceRequest {
+    pub away: bool,
+}
+
+impl ApiRequest for SetPresenceRequest {}
@@ -6,7 +6,8 @@
 
 use crate::core::ApiResponse;
 use crate::users::entity::{UserRelationshipRow, UserRow, UserWithRelationshipRow};
-use crate::users::model::RelationshipState;
+use crate::users::model::{PresenceStatus, RelationshipState};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -160,3 +161,15 @@ pub struct RelationshipStateResponse {
 }
 
 impl ApiResponse for RelationshipStateResponse {}
+
+/// One user's presence as the caller may see it. A user on either side of a block with the caller
+/// is reported `OFFLINE` with no `lastSeenAt`, exactly like a user who never connected.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceResponse {
+    pub user_id: Uuid,
+    pub status: PresenceStatus,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+impl ApiResponse for PresenceResponse {}
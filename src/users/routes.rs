@@ -1,7 +1,7 @@
 use crate::core::AppState;
 use crate::users::handler::{
-    handle_accept_friend_request, handle_add_friend, handle_get_friends, handle_get_open_friend_requests, handle_ignore_user, handle_reject_friend_request,
-    handle_remove_friend, handle_search_user_by_id, handle_search_user_by_name, handle_undo_ignore_user,
+    handle_accept_friend_request, handle_add_friend, handle_get_friends, handle_get_open_friend_requests, handle_get_presence, handle_ignore_user,
+    handle_reject_friend_request, handle_remove_friend, handle_search_user_by_id, handle_search_user_by_name, handle_set_presence, handle_undo_ignore_user,
 };
 use axum::Router;
 use axum::routing::{delete, get, post};
@@ -11,6 +11,7 @@ pub fn create_user_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/users/{user_id}", get(handle_search_user_by_id))
         .route("/users/search", get(handle_search_user_by_name))
+        .route("/users/presence", post(handle_get_presence).put(handle_set_presence))
         .route("/users/friends/requests", get(handle_get_open_friend_requests))
         .route("/users/friends", get(handle_get_friends))
         .route("/users/friends/add/{user_id}", post(handle_add_friend))
@@ -0,0 +1,7 @@
+//! Business logic for the users domain.
+
+mod presence;
+mod user;
+
+pub use presence::{PresenceGuard, PresenceService};
+pub use user::UserService;
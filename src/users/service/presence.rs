@@ -0,0 +1,263 @@
+//! Online / away / offline presence.
+//!
+//! Presence is derived, never stored as a status: a user is online while at least one of their
+//! live connections keeps heartbeating into the shared presence hash in Redis, so every instance
+//! sees every connection. What *is* stored is `app_user.last_seen_at`, the one part of presence
+//! that has to outlive the connection.
+//!
+//! Without Redis each instance falls back to the connections it holds itself, which is exact for a
+//! single instance and all there is to know without a shared store.
+
+use crate::broadcast::{BroadcastChannel, NotificationEvent};
+use crate::cache::redis_cache::Cache;
+use crate::core::Service;
+use crate::core::errors::AppError;
+use crate::users::UserRepository;
+use crate::users::model::PresenceStatus;
+use crate::users::response::PresenceResponse;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tracing::warn;
+use uuid::Uuid;
+
+/// How often a live connection refreshes its presence. A third of the presence TTL, so two missed
+/// heartbeats in a row still do not take a user offline.
+const PRESENCE_HEARTBEAT: Duration = Duration::from_secs(20);
+
+/// Tracks who is connected and tells the people who care when that changes.
+///
+/// Transitions are detected by the instance that caused them, by reading the shared status before
+/// and after its own write. A user whose instance dies without cleaning up goes offline once the
+/// presence hash expires, silently: there is no one left to announce it. Clients reconcile on the
+/// next presence query.
+#[derive(Clone)]
+pub struct PresenceService {
+    cache: Arc<dyn Cache>,
+    bus: Arc<BroadcastChannel>,
+    users: UserRepository,
+    local: Arc<Mutex<HashMap<Uuid, LocalPresence>>>,
+}
+
+impl Service for PresenceService {
+    const NAME: &'static str = "PresenceService";
+}
+
+/// A user's connections on this instance. Only consulted when the cache cannot answer.
+#[derive(Default)]
+struct LocalPresence {
+    connections: HashSet<Uuid>,
+    away: bool,
+}
+
+impl LocalPresence {
+    fn status(&self) -> PresenceStatus {
+        match (self.connections.is_empty(), self.away) {
+            (true, _) => PresenceStatus::Offline,
+            (false, true) => PresenceStatus::Away,
+            (false, false) => PresenceStatus::Online,
+        }
+    }
+}
+
+impl PresenceService {
+    pub fn new(cache: Arc<dyn Cache>, bus: Arc<BroadcastChannel>, users: UserRepository) -> Self {
+        Self {
+            cache,
+            bus,
+            users,
+            local: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Registers a live connection of `user_id` and keeps it alive until the returned guard is
+    /// dropped. The stream handlers hold the guard for exactly as long as the stream is open.
+    pub fn track(&self, user_id: Uuid) -> PresenceGuard {
+        let connection_id = Uuid::new_v4();
+        let heartbeat = tokio::spawn(self.clone().heartbeat(user_id, connection_id));
+        PresenceGuard {
+            presence: self.clone(),
+            user_id,
+            connection_id,
+            heartbeat,
+        }
+    }
+
+    /// The client reports its user idle (`away`) or back. Only changes anything while the user is
+    /// connected: an offline user stays offline.
+    pub async fn set_away(&self, user_id: Uuid, away: bool) -> Result<(), AppError> {
+        let before = self.status_of(user_id).await;
+        if let Some(local) = self.lock_local().get_mut(&user_id) {
+            local.away = away;
+        }
+        self.cache.set_presence_away(&user_id, away).await?;
+        self.announce_if_changed(user_id, before).await;
+        Ok(())
+    }
+
+    /// Presence of each of `user_ids` as `client_id` may see it, in the order asked. Ids that do
+    /// not belong to a user are left out.
+    pub async fn get_presence(&self, client_id: &Uuid, user_ids: Vec<Uuid>) -> Result<Vec<PresenceResponse>, AppError> {
+        let blocked: HashSet<Uuid> = self.users.find_blocked_relationships(client_id, &user_ids).await?.into_iter().collect();
+        let last_seen: HashMap<Uuid, _> = self
+            .users
+            .select_last_seen(&user_ids)
+            .await?
+            .into_iter()
+            .map(|row| (row.id, row.last_seen_at))
+            .collect();
+        let statuses = self.statuses(&user_ids).await;
+
+        let presence = user_ids
+            .into_iter()
+            .zip(statuses)
+            .filter_map(|(user_id, status)| {
+                let last_seen_at = *last_seen.get(&user_id)?;
+                Some(if blocked.contains(&user_id) {
+                    PresenceResponse {
+                        user_id,
+                        status: PresenceStatus::Offline,
+                        last_seen_at: None,
+                    }
+                } else {
+                    PresenceResponse { user_id, status, last_seen_at }
+                })
+            })
+            .collect();
+        Ok(presence)
+    }
+
+    async fn heartbeat(self, user_id: Uuid, connection_id: Uuid) {
+        self.connected(user_id, connection_id).await;
+
+        let mut interval = time::interval(PRESENCE_HEARTBEAT);
+        interval.tick().await; // the first tick completes immediately; `connected` was that beat
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.cache.put_presence(&user_id, &connection_id).await {
+                warn!(%user_id, error = %err, "Failed to refresh presence");
+            }
+        }
+    }
+
+    async fn connected(&self, user_id: Uuid, connection_id: Uuid) {
+        let before = self.status_of(user_id).await;
+        self.lock_local().entry(user_id).or_default().connections.insert(connection_id);
+        if let Err(err) = self.cache.put_presence(&user_id, &connection_id).await {
+            warn!(%user_id, error = %err, "Failed to record presence");
+        }
+        self.announce_if_changed(user_id, before).await;
+    }
+
+    async fn disconnected(&self, user_id: Uuid, connection_id: Uuid) {
+        let before = self.status_of(user_id).await;
+        {
+            let mut local = self.lock_local();
+            if let Some(presence) = local.get_mut(&user_id) {
+                presence.connections.remove(&connection_id);
+                if presence.connections.is_empty() {
+                    local.remove(&user_id);
+                }
+            }
+        }
+        if let Err(err) = self.cache.remove_presence(&user_id, &connection_id).await {
+            warn!(%user_id, error = %err, "Failed to clear presence");
+        }
+        self.announce_if_changed(user_id, before).await;
+    }
+
+    /// Stamps `last_seen_at` and tells the user's audience, if the status moved away from `before`.
+    async fn announce_if_changed(&self, user_id: Uuid, before: PresenceStatus) {
+        let status = self.status_of(user_id).await;
+        if status == before {
+            return;
+        }
+
+        let now = Utc::now();
+        // Coming online is stamped as well, so a user whose instance dies without a clean
+        // disconnect still has a last-seen close to the truth.
+        if (status == PresenceStatus::Offline || before == PresenceStatus::Offline)
+            && let Err(err) = self.users.touch_last_seen(&user_id, now).await
+        {
+            warn!(%user_id, error = %err, "Failed to store last seen");
+        }
+
+        let audience = match self.users.select_presence_audience(&user_id).await {
+            Ok(audience) => audience,
+            Err(err) => {
+                warn!(%user_id, error = %err, "Could not resolve the presence audience");
+                return;
+            }
+        };
+        let last_seen_at = (status == PresenceStatus::Offline).then_some(now);
+        self.bus
+            .notify_all(audience, NotificationEvent::PresenceChanged { user_id, status, last_seen_at })
+            .await;
+    }
+
+    async fn status_of(&self, user_id: Uuid) -> PresenceStatus {
+        self.statuses(&[user_id]).await.pop().unwrap_or(PresenceStatus::Offline)
+    }
+
+    /// Shared presence from the cache, or this instance's own view when the cache cannot answer.
+    async fn statuses(&self, user_ids: &[Uuid]) -> Vec<PresenceStatus> {
+        match self.cache.get_presence(user_ids).await {
+            Ok(Some(statuses)) => return statuses,
+            Ok(None) => {}
+            Err(err) => warn!(error = %err, "Failed to read shared presence, using local connections"),
+        }
+        let local = self.lock_local();
+        user_ids
+            .iter()
+            .map(|user_id| local.get(user_id).map_or(PresenceStatus::Offline, LocalPresence::status))
+            .collect()
+    }
+
+    fn lock_local(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, LocalPresence>> {
+        self.local.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Keeps one connection counted as present. Dropping it stops the heartbeat and removes the
+/// connection; `Drop` cannot be async, hence the detached task, as in
+/// [`ConnectionGuard`](crate::messaging::service::ConnectionGuard).
+pub struct PresenceGuard {
+    presence: PresenceService,
+    user_id: Uuid,
+    connection_id: Uuid,
+    heartbeat: JoinHandle<()>,
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        let presence = self.presence.clone();
+        let (user_id, connection_id) = (self.user_id, self.connection_id);
+        tokio::spawn(async move {
+            presence.disconnected(user_id, connection_id).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_presence_needs_a_connection_to_be_anything_but_offline() {
+        let mut presence = LocalPresence {
+            away: true,
+            ..LocalPresence::default()
+        };
+        assert_eq!(presence.status(), PresenceStatus::Offline);
+
+        presence.connections.insert(Uuid::new_v4());
+        assert_eq!(presence.status(), PresenceStatus::Away);
+
+        presence.away = false;
+        assert_eq!(presence.status(), PresenceStatus::Online);
+    }
+}
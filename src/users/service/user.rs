@@ -15,7 +15,8 @@ use uuid::Uuid;
 
 /// User profiles and the relationship graph (friends, invites, blocks).
 ///
-/// The only service in the project that depends on another service. Blocking someone has to tear
+/// One of the two services that depend on other services, with
+/// [`AdminService`](crate::admin::AdminService). Blocking someone has to tear
 /// down the 1-1 room they share, and "leave a room" is a use case with its own transaction,
 /// cache invalidation and broadcasts — reimplementing it here against `RoomRepository` would be a
 /// second copy of that logic, drifting from the first. The dependency runs one way only:
@@ -93,6 +93,8 @@ fn event() -> Notification {
     Notification::new(NotificationEvent::UserReadChat {
         user_id: Uuid::new_v4(),
         room_id: Uuid::new_v4(),
+        read_at: None,
+        unread: None,
     })
 }
 
@@ -21,15 +21,25 @@
 use chrono::{DateTime, Utc};
 use ism::broadcast::{Notification, NotificationEvent};
 use ism::core::cursor::CursorResults;
-use ism::messaging::entity::{MediaJson, MessageBodyJson, RepliedMessageJson, ReplyJson, RoomChangeJson, TextJson};
+use ism::core::errors::AppError;
+use ism::messaging::entity::{
+    ForwardedFromJson, MediaJson, MentionJson, MessageBodyJson, PollJson, PollVoteCountRow, RepliedMessageJson, ReplyJson, RoomChangeJson, ScheduledBodyJson,
+    TextJson,
+};
 use ism::messaging::model::MsgType;
-use ism::messaging::response::{MessageBodyResponse, MessageResponse, TextBodyResponse, TimelinePageResponse};
+use ism::messaging::response::{
+    CommandOutcome, CommandReplyFrame, CommandResult, ForwardOutcome, ForwardedFromResponse, MediaDownloadUrlResponse, MediaUploadResponse,
+    MediaUploadUrlResponse, MessageBodyResponse, MessageResponse, MessageSearchHitResponse, NotificationCursorResponse, PollBodyResponse, PollResultsResponse,
+    ReactionResponse, RoomChangeResponse, ScheduledBodyResponse, ScheduledMessageResponse, TextBodyResponse, ThreadSummaryResponse, TimelinePageResponse,
+};
 use ism::rooms::entity::{LastMessagePreviewJson, RoomMemberSnapshotJson};
-use ism::rooms::model::{RoomChangeType, RoomContext, RoomType};
+use ism::rooms::model::{NotificationLevel, RoomChangeType, RoomContext, RoomRole, RoomType};
 use ism::rooms::response::{
-    LastMessagePreviewResponse, RoomDetailResponse, RoomImageUploadResponse, RoomMemberResponse, RoomResponse, ShareTargetRef, ShareTargetResponse,
+    InviteLinkPreviewResponse, InviteLinkResponse, LastMessagePreviewResponse, RoomDetailResponse, RoomImageUploadResponse, RoomInvitationResponse,
+    RoomMemberResponse, RoomNotificationSettingsResponse, RoomResponse, ShareTargetRef, ShareTargetResponse, UnreadCountResponse,
 };
-use ism::users::response::{Relationship, RelationshipStateResponse, UserProfileResponse, UserWithRelationshipResponse};
+use ism::users::model::PresenceStatus;
+use ism::users::response::{PresenceResponse, Relationship, RelationshipStateResponse, UserProfileResponse, UserWithRelationshipResponse};
 use serde_json::{Value, json};
 use uuid::Uuid;
 
@@ -87,6 +97,7 @@ fn member() -> RoomMemberResponse {
         profile_picture: Some("https://cdn.example/a.png".to_string()),
         joined_at: Some(ts(TS)),
         last_message_read_at: None,
+        role: None,
     }
 }
 
@@ -127,10 +138,14 @@ fn room() -> RoomResponse {
         room_type: RoomType::Single,
         room_image_url: None,
         room_name: Some("Team".to_string()),
+        room_topic: None,
+        retention_secs: None,
         created_at: ts(TS),
         latest_message: Some(ts(TS2)),
         unread: Some(true),
+        unread_count: None,
         latest_message_preview_text: preview(),
+        notification_settings: None,
     }
 }
 
@@ -154,9 +169,19 @@ fn message() -> MessageResponse {
         sender_id: uuid(USER_A),
         msg_body: MessageBodyResponse::Text(TextBodyResponse {
             text: "hello there".to_string(),
+            mentions: Vec::new(),
         }),
         msg_type: MsgType::Text,
         created_at: ts(TS),
+        edited_at: None,
+        deleted_at: None,
+        reactions: Vec::new(),
+        poll_results: None,
+        thread_root_id: None,
+        reply_count: 0,
+        last_reply_at: None,
+        client_message_id: None,
+        forwarded_from: None,
     }
 }
 
@@ -239,11 +264,91 @@ fn room_wire() {
     assert_wire(&room(), room_json());
 }
 
+#[test]
+fn room_with_topic_wire() {
+    let dto = RoomResponse {
+        room_topic: Some("Weekly planning".to_string()),
+        ..room()
+    };
+    let mut expected = room_json();
+    expected["roomTopic"] = json!("Weekly planning");
+    assert_wire(&dto, expected);
+}
+
+#[test]
+fn room_with_retention_wire() {
+    let dto = RoomResponse {
+        retention_secs: Some(86_400),
+        ..room()
+    };
+    let mut expected = room_json();
+    expected["retentionSecs"] = json!(86_400);
+    assert_wire(&dto, expected);
+}
+
+#[test]
+fn room_with_notification_settings_wire() {
+    let dto = RoomResponse {
+        notification_settings: Some(RoomNotificationSettingsResponse {
+            level: NotificationLevel::Muted,
+            muted_until: Some(ts(TS2)),
+        }),
+        ..room()
+    };
+    let mut expected = room_json();
+    expected["notificationSettings"] = json!({ "level": "MUTED", "mutedUntil": TS2 });
+    assert_wire(&dto, expected);
+}
+
+#[test]
+fn invite_link_wire() {
+    let dto = InviteLinkResponse {
+        token: "q1w2e3".to_string(),
+        room_id: uuid(ROOM_ID),
+        created_by: uuid(USER_A),
+        created_at: ts(TS),
+        expires_at: None,
+        max_uses: Some(10),
+        use_count: 3,
+    };
+    assert_wire(
+        &dto,
+        json!({ "token": "q1w2e3", "roomId": ROOM_ID, "createdBy": USER_A, "createdAt": TS, "expiresAt": null, "maxUses": 10, "useCount": 3 }),
+    );
+}
+
+#[test]
+fn invite_link_preview_wire() {
+    let dto = InviteLinkPreviewResponse {
+        room_id: uuid(ROOM_ID),
+        room_name: Some("Team".to_string()),
+        room_image_url: None,
+        room_topic: None,
+        member_count: 4,
+        is_member: false,
+    };
+    assert_wire(
+        &dto,
+        json!({ "roomId": ROOM_ID, "roomName": "Team", "roomImageUrl": null, "roomTopic": null, "memberCount": 4, "isMember": false }),
+    );
+}
+
 #[test]
 fn room_member_wire() {
     assert_wire(&member(), member_json());
 }
 
+#[test]
+fn room_member_with_role_wire() {
+    let dto = RoomMemberResponse {
+        role: Some(RoomRole::Admin),
+        ..member()
+    };
+    let mut expected = member_json();
+    expected["role"] = json!("ADMIN");
+    assert_wire(&dto, expected);
+}
+
 #[test]
 fn room_with_users_flattens_the_room() {
     let dto = RoomDetailResponse {
@@ -324,8 +429,18 @@ fn timeline_page_wire() {
     let page = TimelinePageResponse {
         messages: vec![message()],
         senders: vec![member()],
+        before_cursor: Some("b2xkZXI".to_string()),
+        after_cursor: None,
     };
-    assert_wire(&page, json!({ "messages": [message_json()], "senders": [member_json()] }));
+    assert_wire(
+        &page,
+        json!({
+            "messages": [message_json()],
+            "senders": [member_json()],
+            "beforeCursor": "b2xkZXI",
+            "afterCursor": null
+        }),
+    );
 }
 
 #[test]
@@ -349,6 +464,44 @@ fn cursor_results_wire() {
     assert_wire(&page, json!({ "cursor": "b3BhcXVl", "content": [user_json()] }));
 }
 
+#[test]
+fn message_search_page_wire() {
+    let page = CursorResults {
+        cursor: Some("b3BhcXVl".to_string()),
+        content: vec![MessageSearchHitResponse {
+            message: message(),
+            snippet: "<mark>hello</mark> there".to_string(),
+        }],
+    };
+    assert_wire(
+        &page,
+        json!({ "cursor": "b3BhcXVl", "content": [{ "message": message_json(), "snippet": "<mark>hello</mark> there" }] }),
+    );
+}
+
+#[test]
+fn forward_outcomes_wire() {
+    let outcomes = vec![
+        ForwardOutcome::Forwarded { message: Box::new(message()) },
+        ForwardOutcome::ForwardFailed {
+            error: AppError::Forbidden("User hasn't access to this room.".to_string()).into_error_response().1,
+        },
+    ];
+    let mut value = serde_json::to_value(&outcomes).expect("outcomes serialize");
+    // The only field that is not fixed.
+    value[1]["error"].as_object_mut().expect("error is an object").remove("timestamp");
+    assert_eq!(
+        value,
+        json!([
+            { "type": "Forwarded", "message": message_json() },
+            {
+                "type": "ForwardFailed",
+                "error": { "status": 403, "error": "Forbidden", "message": "User hasn't access to this room.", "errorCode": "INSUFFICIENT_PERMISSIONS" },
+            },
+        ])
+    );
+}
+
 #[test]
 fn cursor_results_last_page_wire() {
     let page: CursorResults<UserProfileResponse> = CursorResults { cursor: None, content: vec![] };
@@ -366,6 +519,8 @@ fn notification_envelope_carries_version_and_seq() {
         NotificationEvent::UserReadChat {
             user_id: uuid(USER_A),
             room_id: uuid(ROOM_ID),
+            read_at: None,
+            unread: None,
         },
     );
     assert_wire(
@@ -381,6 +536,33 @@ fn notification_envelope_carries_version_and_seq() {
     );
 }
 
+/// The reader's own copy: how far they read, and their fresh counters.
+#[test]
+fn user_read_chat_with_position_and_unread_wire() {
+    let n = notification(
+        Some(8),
+        NotificationEvent::UserReadChat {
+            user_id: uuid(USER_A),
+            room_id: uuid(ROOM_ID),
+            read_at: Some(ts(TS2)),
+            unread: Some(UnreadCountResponse { room: 0, total: Some(4) }),
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1,
+            "seq": 8,
+            "type": "UserReadChat",
+            "userId": USER_A,
+            "roomId": ROOM_ID,
+            "readAt": TS2,
+            "unread": { "room": 0, "total": 4 },
+            "createdAt": TS
+        }),
+    );
+}
+
 #[test]
 fn notification_omits_absent_seq() {
     let n = notification(None, NotificationEvent::Resync { reason: "gap".to_string() });
@@ -413,6 +595,8 @@ fn chat_message_event_wire() {
             message: message(),
             room_preview_text: preview(),
             sender: member(),
+            thread: None,
+            unread: None,
         },
     );
     assert_wire(
@@ -427,6 +611,330 @@ fn chat_message_event_wire() {
     );
 }
 
+/// A recipient's copy carries their count in the room only; the total is not recounted per send.
+#[test]
+fn chat_message_event_with_unread_wire() {
+    let n = notification(
+        Some(3),
+        NotificationEvent::ChatMessage {
+            message: message(),
+            room_preview_text: preview(),
+            sender: member(),
+            thread: None,
+            unread: Some(UnreadCountResponse { room: 5, total: None }),
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 3, "type": "ChatMessage",
+            "message": message_json(),
+            "roomPreviewText": preview_json(),
+            "sender": member_json(),
+            "unread": { "room": 5 },
+            "createdAt": TS
+        }),
+    );
+}
+
+/// The sender's client id rides on the message, so their other devices can reconcile the entry
+/// they showed optimistically.
+#[test]
+fn chat_message_event_with_client_message_id_wire() {
+    let mut sent = message();
+    sent.client_message_id = Some("c0ffee".to_string());
+    let mut expected_message = message_json();
+    expected_message["clientMessageId"] = json!("c0ffee");
+
+    let n = notification(
+        Some(3),
+        NotificationEvent::ChatMessage {
+            message: sent,
+            room_preview_text: preview(),
+            sender: member(),
+            thread: None,
+            unread: None,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 3, "type": "ChatMessage",
+            "message": expected_message,
+            "roomPreviewText": preview_json(),
+            "sender": member_json(),
+            "createdAt": TS
+        }),
+    );
+}
+
+/// A forward names the original it copies; messages that are not forwards omit the key.
+#[test]
+fn forwarded_message_wire() {
+    let mut forwarded = message();
+    forwarded.message_id = uuid(REPLY_ID);
+    forwarded.sender_id = uuid(USER_B);
+    forwarded.forwarded_from = Some(ForwardedFromResponse {
+        message_id: uuid(MSG_ID),
+        sender_id: uuid(USER_A),
+        created_at: ts(TS),
+    });
+    let mut expected = message_json();
+    expected["messageId"] = json!(REPLY_ID);
+    expected["senderId"] = json!(USER_B);
+    expected["forwardedFrom"] = json!({ "messageId": MSG_ID, "senderId": USER_A, "createdAt": TS });
+    assert_wire(&forwarded, expected);
+}
+
+/// `chat_message.forwarded_from`.
+#[test]
+fn stored_forwarded_from() {
+    let stored = ForwardedFromJson {
+        message_id: uuid(MSG_ID),
+        sender_id: uuid(USER_A),
+        created_at: ts(TS),
+    };
+    assert_wire(&stored, json!({ "messageId": MSG_ID, "senderId": USER_A, "createdAt": TS }));
+    assert_wire(&ForwardedFromResponse::from(stored.clone()), serde_json::to_value(&stored).unwrap());
+}
+
+#[test]
+fn scheduled_message_wire() {
+    let pending = ScheduledMessageResponse {
+        scheduled_message_id: uuid(REPLY_ID),
+        chat_room_id: uuid(ROOM_ID),
+        msg_body: ScheduledBodyResponse::Reply {
+            reply_msg_id: uuid(MSG_ID),
+            reply_text: "later".to_string(),
+        },
+        msg_type: MsgType::Reply,
+        thread_root_id: None,
+        send_at: ts(TS2),
+        created_at: ts(TS),
+        failed_at: None,
+        failure: None,
+    };
+    assert_wire(
+        &pending,
+        json!({
+            "scheduledMessageId": REPLY_ID,
+            "chatRoomId": ROOM_ID,
+            "msgBody": { "replyMsgId": MSG_ID, "replyText": "later" },
+            "msgType": "Reply",
+            "sendAt": TS2,
+            "createdAt": TS,
+        }),
+    );
+
+    let failed = ScheduledMessageResponse {
+        scheduled_message_id: uuid(REPLY_ID),
+        chat_room_id: uuid(ROOM_ID),
+        msg_body: ScheduledBodyResponse::Text { text: "later".to_string() },
+        msg_type: MsgType::Text,
+        thread_root_id: Some(uuid(MSG_ID)),
+        send_at: ts(TS2),
+        created_at: ts(TS),
+        failed_at: Some(ts(TS2)),
+        failure: Some("Sender is no longer a member of this room.".to_string()),
+    };
+    assert_wire(
+        &failed,
+        json!({
+            "scheduledMessageId": REPLY_ID,
+            "chatRoomId": ROOM_ID,
+            "msgBody": { "text": "later" },
+            "msgType": "Text",
+            "threadRootId": MSG_ID,
+            "sendAt": TS2,
+            "createdAt": TS,
+            "failedAt": TS2,
+            "failure": "Sender is no longer a member of this room.",
+        }),
+    );
+}
+
+/// `scheduled_message.msg_body`.
+#[test]
+fn stored_scheduled_body() {
+    assert_wire(
+        &ScheduledBodyJson::Text { text: "later".to_string() },
+        json!({ "type": "Text", "text": "later" }),
+    );
+    assert_wire(
+        &ScheduledBodyJson::Media {
+            media_url: "https://cdn.example/a.png".to_string(),
+            media_type: "image".to_string(),
+            mime_type: None,
+            alt_text: Some("a cat".to_string()),
+        },
+        json!({ "type": "Media", "mediaUrl": "https://cdn.example/a.png", "mediaType": "image", "mimeType": null, "altText": "a cat" }),
+    );
+    assert_wire(
+        &ScheduledBodyJson::Reply {
+            reply_msg_id: uuid(MSG_ID),
+            reply_text: "later".to_string(),
+        },
+        json!({ "type": "Reply", "replyMsgId": MSG_ID, "replyText": "later" }),
+    );
+    assert_wire(
+        &ScheduledBodyJson::UploadedMedia {
+            media_key: "rooms/r/m.png".to_string(),
+            alt_text: None,
+        },
+        json!({ "type": "UploadedMedia", "mediaKey": "rooms/r/m.png", "altText": null }),
+    );
+    assert_wire(
+        &ScheduledBodyJson::Poll {
+            question: "Lunch where?".to_string(),
+            options: vec!["Pizzeria".to_string(), "Sushi".to_string()],
+            multiple_choice: false,
+            closes_at: None,
+        },
+        json!({ "type": "Poll", "question": "Lunch where?", "options": ["Pizzeria", "Sushi"], "multipleChoice": false, "closesAt": null }),
+    );
+}
+
+fn poll() -> PollJson {
+    PollJson {
+        question: "Lunch where?".to_string(),
+        options: vec!["Pizzeria".to_string(), "Sushi".to_string(), "Canteen".to_string()],
+        multiple_choice: true,
+        closes_at: Some(ts(TS2)),
+        closed_at: None,
+    }
+}
+
+fn poll_results() -> PollResultsResponse {
+    PollResultsResponse {
+        votes: vec![2, 1, 0],
+        voter_count: 2,
+        my_votes: vec![0],
+    }
+}
+
+#[test]
+fn poll_message_wire() {
+    let mut message = message();
+    message.msg_type = MsgType::Poll;
+    message.msg_body = MessageBodyResponse::Poll(PollBodyResponse::from(poll()));
+    message.poll_results = Some(poll_results());
+    let mut expected = message_json();
+    expected["msgType"] = json!("Poll");
+    expected["msgBody"] = json!({
+        "question": "Lunch where?",
+        "options": ["Pizzeria", "Sushi", "Canteen"],
+        "multipleChoice": true,
+        "closesAt": TS2
+    });
+    expected["pollResults"] = json!({ "votes": [2, 1, 0], "voterCount": 2, "myVotes": [0] });
+    assert_wire(&message, expected);
+}
+
+#[test]
+fn poll_results_tally_wire() {
+    let row = |option_index: Option<i16>, count: i64, voted_by_me: bool| PollVoteCountRow {
+        message_id: uuid(MSG_ID),
+        option_index,
+        count,
+        voted_by_me,
+    };
+    // A vote for an option the poll does not have is left out rather than growing the list.
+    let results = PollResultsResponse::tally(3, [row(Some(1), 1, false), row(Some(0), 2, true), row(None, 2, true), row(Some(7), 1, false)]);
+    assert_eq!(results, poll_results());
+
+    let unvoted = PollResultsResponse::tally(2, []);
+    assert_wire(&unvoted, json!({ "votes": [0, 0], "voterCount": 0 }));
+}
+
+#[test]
+fn poll_updated_event_wire() {
+    let vote = notification(
+        Some(14),
+        NotificationEvent::PollUpdated {
+            room_id: uuid(ROOM_ID),
+            message_id: uuid(MSG_ID),
+            results: PollResultsResponse {
+                my_votes: Vec::new(),
+                ..poll_results()
+            },
+            user_id: Some(uuid(USER_B)),
+            choices: Some(vec![0, 1]),
+            closed_at: None,
+        },
+    );
+    assert_wire(
+        &vote,
+        json!({
+            "v": 1, "seq": 14, "type": "PollUpdated",
+            "roomId": ROOM_ID, "messageId": MSG_ID,
+            "results": { "votes": [2, 1, 0], "voterCount": 2 },
+            "userId": USER_B, "choices": [0, 1],
+            "createdAt": TS
+        }),
+    );
+
+    let close = notification(
+        Some(15),
+        NotificationEvent::PollUpdated {
+            room_id: uuid(ROOM_ID),
+            message_id: uuid(MSG_ID),
+            results: PollResultsResponse {
+                my_votes: Vec::new(),
+                ..poll_results()
+            },
+            user_id: None,
+            choices: None,
+            closed_at: Some(ts(TS2)),
+        },
+    );
+    assert_wire(
+        &close,
+        json!({
+            "v": 1, "seq": 15, "type": "PollUpdated",
+            "roomId": ROOM_ID, "messageId": MSG_ID,
+            "results": { "votes": [2, 1, 0], "voterCount": 2 },
+            "closedAt": TS2,
+            "createdAt": TS
+        }),
+    );
+}
+
+#[test]
+fn thread_reply_chat_message_event_wire() {
+    let mut reply = message();
+    reply.message_id = uuid(REPLY_ID);
+    reply.thread_root_id = Some(uuid(MSG_ID));
+    let mut expected_message = message_json();
+    expected_message["messageId"] = json!(REPLY_ID);
+    expected_message["threadRootId"] = json!(MSG_ID);
+
+    let n = notification(
+        Some(3),
+        NotificationEvent::ChatMessage {
+            message: reply,
+            room_preview_text: preview(),
+            sender: member(),
+            thread: Some(ThreadSummaryResponse {
+                root_message_id: uuid(MSG_ID),
+                reply_count: 3,
+                last_reply_at: ts(TS2),
+            }),
+            unread: None,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 3, "type": "ChatMessage",
+            "message": expected_message,
+            "roomPreviewText": preview_json(),
+            "sender": member_json(),
+            "thread": { "rootMessageId": MSG_ID, "replyCount": 3, "lastReplyAt": TS2 },
+            "createdAt": TS
+        }),
+    );
+}
+
 #[test]
 fn room_change_event_wire() {
     let n = notification(
@@ -515,6 +1023,224 @@ fn system_message_event_wire() {
     );
 }
 
+#[test]
+fn message_edited_event_wire() {
+    let mut edited = message();
+    edited.edited_at = Some(ts(TS2));
+    let mut expected_message = message_json();
+    expected_message["editedAt"] = json!(TS2);
+
+    let n = notification(
+        Some(10),
+        NotificationEvent::MessageEdited {
+            message: edited,
+            room_preview_text: Some(preview()),
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 10, "type": "MessageEdited",
+            "message": expected_message,
+            "roomPreviewText": preview_json(),
+            "createdAt": TS
+        }),
+    );
+}
+
+#[test]
+fn message_deleted_event_wire() {
+    let mut tombstone = message();
+    tombstone.msg_body = MessageBodyResponse::Text(TextBodyResponse {
+        text: String::new(),
+        mentions: Vec::new(),
+    });
+    tombstone.deleted_at = Some(ts(TS2));
+
+    let n = notification(
+        Some(11),
+        NotificationEvent::MessageDeleted {
+            message: tombstone,
+            room_preview_text: None,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 11, "type": "MessageDeleted",
+            "message": {
+                "chatRoomId": ROOM_ID,
+                "messageId": MSG_ID,
+                "senderId": USER_A,
+                "msgBody": { "text": "" },
+                "msgType": "Text",
+                "createdAt": TS,
+                "deletedAt": TS2
+            },
+            "roomPreviewText": null,
+            "createdAt": TS
+        }),
+    );
+}
+
+#[test]
+fn reaction_changed_event_wire() {
+    let n = notification(
+        Some(12),
+        NotificationEvent::ReactionChanged {
+            room_id: uuid(ROOM_ID),
+            message_id: uuid(MSG_ID),
+            user_id: uuid(USER_B),
+            emoji: "👍".to_string(),
+            reacted: true,
+            count: 2,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1, "seq": 12, "type": "ReactionChanged",
+            "roomId": ROOM_ID, "messageId": MSG_ID, "userId": USER_B,
+            "emoji": "👍", "reacted": true, "count": 2,
+            "createdAt": TS
+        }),
+    );
+}
+
+#[test]
+fn messages_expired_event_wire() {
+    let n = notification(
+        Some(13),
+        NotificationEvent::MessagesExpired {
+            room_id: uuid(ROOM_ID),
+            until: ts(TS2),
+        },
+    );
+    assert_wire(
+        &n,
+        json!({ "v": 1, "seq": 13, "type": "MessagesExpired", "roomId": ROOM_ID, "until": TS2, "createdAt": TS }),
+    );
+}
+
+/// Ephemeral, so never sequenced.
+#[test]
+fn typing_event_wire() {
+    let n = notification(
+        None,
+        NotificationEvent::Typing {
+            room_id: uuid(ROOM_ID),
+            user_id: uuid(USER_B),
+            active: true,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({ "v": 1, "type": "Typing", "roomId": ROOM_ID, "userId": USER_B, "active": true, "createdAt": TS }),
+    );
+}
+
+/// Ephemeral, so never sequenced. `lastSeenAt` only appears on the transition to offline.
+#[test]
+fn presence_changed_event_wire() {
+    let online = notification(
+        None,
+        NotificationEvent::PresenceChanged {
+            user_id: uuid(USER_B),
+            status: PresenceStatus::Online,
+            last_seen_at: None,
+        },
+    );
+    assert_wire(
+        &online,
+        json!({ "v": 1, "type": "PresenceChanged", "userId": USER_B, "status": "ONLINE", "createdAt": TS }),
+    );
+
+    let offline = notification(
+        None,
+        NotificationEvent::PresenceChanged {
+            user_id: uuid(USER_B),
+            status: PresenceStatus::Offline,
+            last_seen_at: Some(ts(TS)),
+        },
+    );
+    assert_wire(
+        &offline,
+        json!({ "v": 1, "type": "PresenceChanged", "userId": USER_B, "status": "OFFLINE", "lastSeenAt": TS, "createdAt": TS }),
+    );
+}
+
+#[test]
+fn presence_response_wire() {
+    let dto = PresenceResponse {
+        user_id: uuid(USER_B),
+        status: PresenceStatus::Away,
+        last_seen_at: None,
+    };
+    assert_wire(&dto, json!({ "userId": USER_B, "status": "AWAY", "lastSeenAt": null }));
+}
+
+#[test]
+fn user_banned_event_wire() {
+    let n = notification(
+        Some(11),
+        NotificationEvent::UserBanned {
+            user_id: uuid(USER_B),
+            banned: true,
+        },
+    );
+    assert_wire(
+        &n,
+        json!({ "v": 1, "seq": 11, "type": "UserBanned", "userId": USER_B, "banned": true, "createdAt": TS }),
+    );
+}
+
+#[test]
+fn room_invitation_received_event_wire() {
+    let n = notification(
+        Some(12),
+        NotificationEvent::RoomInvitationReceived {
+            invitation: RoomInvitationResponse {
+                room_id: uuid(ROOM_ID),
+                room_name: Some("Team".to_string()),
+                room_image_url: None,
+                invited_by: uuid(USER_A),
+                invited_by_name: "Ada".to_string(),
+                created_at: ts(TS),
+            },
+        },
+    );
+    assert_wire(
+        &n,
+        json!({
+            "v": 1,
+            "seq": 12,
+            "type": "RoomInvitationReceived",
+            "invitation": {
+                "roomId": ROOM_ID,
+                "roomName": "Team",
+                "roomImageUrl": null,
+                "invitedBy": USER_A,
+                "invitedByName": "Ada",
+                "createdAt": TS
+            },
+            "createdAt": TS
+        }),
+    );
+}
+
+#[test]
+fn message_with_reactions_wire() {
+    let mut dto = message();
+    dto.reactions = vec![ReactionResponse {
+        emoji: "👍".to_string(),
+        count: 2,
+        reacted_by_me: false,
+    }];
+    let mut expected = message_json();
+    expected["reactions"] = json!([{ "emoji": "👍", "count": 2, "reactedByMe": false }]);
+    assert_wire(&dto, expected);
+}
+
 // ---------------------------------------------------------------------------
 // Stored JSONB — chat_message.msg_body
 //
@@ -526,10 +1252,37 @@ fn system_message_event_wire() {
 fn stored_text_body() {
     let body = MessageBodyJson::Text(TextJson {
         text: "hello there".to_string(),
+        mentions: Vec::new(),
     });
     assert_wire(&body, json!({ "text": "hello there" }));
 }
 
+#[test]
+fn stored_text_body_with_mentions() {
+    let body = MessageBodyJson::Text(TextJson {
+        text: format!("@{USER_B} @room"),
+        mentions: vec![
+            MentionJson::User {
+                user_id: uuid(USER_B),
+                offset: 0,
+                length: 37,
+            },
+            MentionJson::Room { offset: 38, length: 5 },
+        ],
+    });
+    assert_wire(
+        &body,
+        json!({
+            "text": format!("@{USER_B} @room"),
+            "mentions": [
+                { "type": "User", "userId": USER_B, "offset": 0, "length": 37 },
+                { "type": "Room", "offset": 38, "length": 5 }
+            ]
+        }),
+    );
+    assert_wire(&MessageBodyResponse::from(body.clone()), serde_json::to_value(&body).unwrap());
+}
+
 #[test]
 fn stored_media_body() {
     let body = MessageBodyJson::Media(MediaJson {
@@ -537,6 +1290,7 @@ fn stored_media_body() {
         media_type: "image".to_string(),
         mime_type: Some("image/png".to_string()),
         alt_text: Some("a cat".to_string()),
+        media_key: None,
     });
     assert_wire(
         &body,
@@ -549,6 +1303,82 @@ fn stored_media_body() {
     );
 }
 
+#[test]
+fn stored_uploaded_media_body() {
+    let body = MessageBodyJson::Media(MediaJson {
+        media_url: format!("/api/v1/rooms/{ROOM_ID}/media/{REPLY_ID}"),
+        media_type: "image".to_string(),
+        mime_type: Some("image/png".to_string()),
+        alt_text: None,
+        media_key: Some(format!("rooms/{ROOM_ID}/media/{REPLY_ID}")),
+    });
+    assert_wire(
+        &body,
+        json!({
+            "mediaUrl": format!("/api/v1/rooms/{ROOM_ID}/media/{REPLY_ID}"),
+            "mediaType": "image",
+            "mimeType": "image/png",
+            "altText": null,
+            "mediaKey": format!("rooms/{ROOM_ID}/media/{REPLY_ID}")
+        }),
+    );
+}
+
+#[test]
+fn media_upload_response_wire() {
+    let response = MediaUploadResponse {
+        media_key: format!("rooms/{ROOM_ID}/media/{REPLY_ID}"),
+        media_url: format!("/api/v1/rooms/{ROOM_ID}/media/{REPLY_ID}"),
+        media_type: "video".to_string(),
+        mime_type: "video/mp4".to_string(),
+        size_bytes: 1_048_576,
+    };
+    assert_wire(
+        &response,
+        json!({
+            "mediaKey": format!("rooms/{ROOM_ID}/media/{REPLY_ID}"),
+            "mediaUrl": format!("/api/v1/rooms/{ROOM_ID}/media/{REPLY_ID}"),
+            "mediaType": "video",
+            "mimeType": "video/mp4",
+            "sizeBytes": 1048576
+        }),
+    );
+}
+
+#[test]
+fn media_upload_url_response_wire() {
+    let response = MediaUploadUrlResponse {
+        media_id: uuid(REPLY_ID),
+        media_key: format!("rooms/{ROOM_ID}/media/{REPLY_ID}"),
+        upload_url: "https://s3.example/mv-rooms/upload?X-Amz-Signature=abc".to_string(),
+        expires_at: ts(TS),
+    };
+    assert_wire(
+        &response,
+        json!({
+            "mediaId": REPLY_ID,
+            "mediaKey": format!("rooms/{ROOM_ID}/media/{REPLY_ID}"),
+            "uploadUrl": "https://s3.example/mv-rooms/upload?X-Amz-Signature=abc",
+            "expiresAt": TS
+        }),
+    );
+}
+
+#[test]
+fn media_download_url_response_wire() {
+    let response = MediaDownloadUrlResponse {
+        url: "https://s3.example/mv-rooms/download?X-Amz-Signature=abc".to_string(),
+        expires_at: ts(TS),
+    };
+    assert_wire(
+        &response,
+        json!({
+            "url": "https://s3.example/mv-rooms/download?X-Amz-Signature=abc",
+            "expiresAt": TS
+        }),
+    );
+}
+
 #[test]
 fn stored_reply_body() {
     let body = MessageBodyJson::Reply(ReplyJson {
@@ -556,8 +1386,12 @@ fn stored_reply_body() {
         reply_sender_id: uuid(USER_B),
         reply_msg_type: MsgType::Text,
         reply_created_at: ts(TS),
-        reply_msg_details: RepliedMessageJson::Text(TextJson { text: "original".to_string() }),
+        reply_msg_details: RepliedMessageJson::Text(TextJson {
+            text: "original".to_string(),
+            mentions: Vec::new(),
+        }),
         reply_text: "answer".to_string(),
+        mentions: Vec::new(),
     });
     assert_wire(
         &body,
@@ -580,6 +1414,45 @@ fn stored_reply_to_a_reply_body() {
     assert_wire(&details, json!({ "reply_text": "earlier answer" }));
 }
 
+#[test]
+fn stored_poll_body() {
+    let open = json!({
+        "question": "Lunch where?",
+        "options": ["Pizzeria", "Sushi", "Canteen"],
+        "multipleChoice": true,
+        "closesAt": TS2
+    });
+    assert_wire(&MessageBodyJson::Poll(poll()), open.clone());
+    // Untagged: the `msg_type` column is not consulted when decoding, so the shape alone must
+    // come back as a poll rather than as any earlier variant.
+    let decoded: MessageBodyJson = serde_json::from_value(open).expect("stored poll decodes");
+    assert!(matches!(decoded, MessageBodyJson::Poll(ref poll) if poll.options.len() == 3 && poll.closed_at.is_none()));
+
+    let closed = PollJson {
+        multiple_choice: false,
+        closes_at: None,
+        closed_at: Some(ts(TS)),
+        ..poll()
+    };
+    assert_wire(
+        &MessageBodyJson::Poll(closed),
+        json!({
+            "question": "Lunch where?",
+            "options": ["Pizzeria", "Sushi", "Canteen"],
+            "multipleChoice": false,
+            "closedAt": TS
+        }),
+    );
+}
+
+#[test]
+fn stored_reply_to_a_poll_body() {
+    let details = RepliedMessageJson::Poll {
+        question: "Lunch where?".to_string(),
+    };
+    assert_wire(&details, json!({ "question": "Lunch where?" }));
+}
+
 #[test]
 fn stored_room_change_body() {
     for (variant, tag) in [
@@ -606,6 +1479,67 @@ fn stored_room_change_body() {
     }
 }
 
+#[test]
+fn stored_moderation_room_change_body() {
+    assert_wire(
+        &MessageBodyJson::RoomChange(RoomChangeJson::UserKicked {
+            related_user: member_snapshot(),
+        }),
+        json!({ "type": "UserKicked", "related_user": member_json() }),
+    );
+    assert_wire(
+        &MessageBodyJson::RoomChange(RoomChangeJson::UserRoleChanged {
+            related_user: member_snapshot(),
+            role: RoomRole::Owner,
+        }),
+        json!({ "type": "UserRoleChanged", "related_user": member_json(), "role": "OWNER" }),
+    );
+}
+
+#[test]
+fn stored_room_metadata_change_body() {
+    for (variant, expected) in [
+        (
+            RoomChangeJson::RoomRenamed { room_name: "Team".to_string() },
+            json!({ "type": "RoomRenamed", "room_name": "Team" }),
+        ),
+        (
+            RoomChangeJson::ImageChanged {
+                image_url: "rooms/team".to_string(),
+            },
+            json!({ "type": "ImageChanged", "image_url": "rooms/team" }),
+        ),
+        (RoomChangeJson::TopicChanged { topic: None }, json!({ "type": "TopicChanged", "topic": null })),
+        (
+            RoomChangeJson::RetentionChanged { retention_secs: Some(0) },
+            json!({ "type": "RetentionChanged", "retention_secs": 0 }),
+        ),
+    ] {
+        assert_wire(&MessageBodyJson::RoomChange(variant), expected);
+    }
+}
+
+#[test]
+fn room_change_event_for_a_rename_wire() {
+    let mut renamed = message();
+    renamed.msg_type = MsgType::RoomChange;
+    renamed.msg_body = MessageBodyResponse::RoomChange(RoomChangeResponse::RoomRenamed { room_name: "Team".to_string() });
+    let event = NotificationEvent::RoomChangeEvent {
+        message: renamed,
+        room_preview_text: LastMessagePreviewResponse::RoomChange {
+            sender_username: "Ada".to_string(),
+            room_change_type: RoomChangeType::RENAME,
+        },
+    };
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "RoomChangeEvent");
+    assert_eq!(json["message"]["msgBody"], json!({ "type": "RoomRenamed", "room_name": "Team" }));
+    assert_eq!(
+        json["roomPreviewText"],
+        json!({ "type": "RoomChange", "sender_username": "Ada", "room_change_type": "RENAME" })
+    );
+}
+
 // ---------------------------------------------------------------------------
 // Stored JSONB — chat_room.latest_message_preview_text
 //
@@ -672,6 +1606,19 @@ fn preview_response_truncates_long_reply_text() {
     assert_wire(&response, json!({ "type": "Reply", "sender_username": "Ada", "reply_text": truncated_text() }));
 }
 
+#[test]
+fn preview_poll_keeps_the_full_question_and_truncates_it_for_display() {
+    let stored = LastMessagePreviewJson::Poll {
+        sender_username: "Ada".to_string(),
+        question: long_text(),
+    };
+    assert_wire(&stored, json!({ "type": "Poll", "sender_username": "Ada", "question": long_text() }));
+    assert_wire(
+        &LastMessagePreviewResponse::from(stored),
+        json!({ "type": "Poll", "sender_username": "Ada", "question": truncated_text() }),
+    );
+}
+
 #[test]
 fn truncation_is_idempotent_for_legacy_rows() {
     // Rows written before the fix already hold a shortened value: 43 chars, under the 50-char
@@ -705,6 +1652,12 @@ fn stored_preview_media_and_room_change() {
             },
             json!({ "type": "RoomChange", "sender_username": "Ada", "room_change_type": "JOIN" }),
         ),
+        (
+            LastMessagePreviewJson::Deleted {
+                sender_username: "Ada".to_string(),
+            },
+            json!({ "type": "Deleted", "sender_username": "Ada" }),
+        ),
         (LastMessagePreviewJson::New, json!({ "type": "New" })),
     ] {
         assert_wire(&LastMessagePreviewResponse::from(stored.clone()), expected.clone());
@@ -740,24 +1693,38 @@ fn stored_and_response_bodies_agree_today() {
     let stored_bodies = [
         MessageBodyJson::Text(TextJson {
             text: "hello there".to_string(),
+            mentions: Vec::new(),
         }),
         MessageBodyJson::Media(MediaJson {
             media_url: "https://cdn.example/pic.png".to_string(),
             media_type: "image".to_string(),
             mime_type: Some("image/png".to_string()),
             alt_text: Some("a cat".to_string()),
+            media_key: None,
+        }),
+        MessageBodyJson::Media(MediaJson {
+            media_url: "/api/v1/rooms/r/media/m".to_string(),
+            media_type: "document".to_string(),
+            mime_type: Some("application/pdf".to_string()),
+            alt_text: None,
+            media_key: Some("rooms/r/media/m".to_string()),
         }),
         MessageBodyJson::Reply(ReplyJson {
             reply_msg_id: uuid(REPLY_ID),
             reply_sender_id: uuid(USER_B),
             reply_msg_type: MsgType::Text,
             reply_created_at: ts(TS),
-            reply_msg_details: RepliedMessageJson::Text(TextJson { text: "original".to_string() }),
+            reply_msg_details: RepliedMessageJson::Text(TextJson {
+                text: "original".to_string(),
+                mentions: Vec::new(),
+            }),
             reply_text: "answer".to_string(),
+            mentions: Vec::new(),
         }),
         MessageBodyJson::RoomChange(RoomChangeJson::UserJoined {
             related_user: member_snapshot(),
         }),
+        MessageBodyJson::Poll(poll()),
     ];
 
     for stored in stored_bodies {
@@ -780,3 +1747,29 @@ fn stored_and_response_previews_agree_when_no_truncation_applies() {
     let response_json = serde_json::to_value(LastMessagePreviewResponse::from(stored)).expect("response preview serializes");
     assert_eq!(stored_json, response_json);
 }
+
+// ---------------------------------------------------------------------------
+// WebSocket command replies
+// ---------------------------------------------------------------------------
+
+#[test]
+fn command_ok_frame_wire() {
+    let frame = CommandReplyFrame {
+        v: 1,
+        id: Some("c-17".to_string()),
+        outcome: CommandOutcome::CommandOk {
+            result: Some(CommandResult::Cursor(NotificationCursorResponse { seq: 42 })),
+        },
+    };
+    assert_wire(&frame, json!({ "v": 1, "id": "c-17", "type": "CommandOk", "result": { "seq": 42 } }));
+}
+
+#[test]
+fn command_ok_frame_without_result_wire() {
+    let frame = CommandReplyFrame {
+        v: 1,
+        id: Some("c-18".to_string()),
+        outcome: CommandOutcome::CommandOk { result: None },
+    };
+    assert_wire(&frame, json!({ "v": 1, "id": "c-18", "type": "CommandOk" }));
+}